@@ -1 +1,6 @@
+pub mod FieldSplitter;
+pub mod MemorySSA;
+pub mod SSADestructor;
+pub mod SSAGraphviz;
 pub mod SSATransformer;
+pub mod SSAVerifier;
@@ -0,0 +1,186 @@
+use rustc_middle::mir::visit::{
+    MutVisitor, MutatingUseContext, NonMutatingUseContext, PlaceContext, Visitor,
+};
+use rustc_middle::mir::*;
+use rustc_middle::ty::{self, Ty, TyCtxt};
+use rustc_target::abi::FieldIdx;
+
+use std::collections::{HashMap, HashSet};
+
+/// 把只按字段访问的元组、结构体变量拆成每个标量字段一个变量
+///
+/// debug 模式下的带溢出检查运算会经过元组临时变量：
+/// `_9 = AddWithOverflow(..); assert(!move (_9.1: bool)) ..; _4 = move (_9.0: i32)`。
+/// 拆分之后变为
+/// `_9 = AddWithOverflow(..); _12 = copy (_9.0: i32); _13 = copy (_9.1: bool); assert(!move _13) ..; _4 = move _12`，
+/// 运算结果和溢出标志成为两个独立的变量，可以各自进行 SSA 重命名。
+///
+/// 只有满足以下条件的变量才会被拆分：类型是所有字段都是标量的元组或结构体，
+/// 不是参数或返回值，并且只以整体赋值、单层字段读取、单层字段写入的方式出现
+/// （借用、解引用、整体使用、作为调用返回值都会让它保持原样）。
+pub struct FieldSplitter<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// 每个被拆分的变量对应的字段变量
+    fields: HashMap<Local, Vec<Local>>,
+}
+
+impl<'tcx> FieldSplitter<'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+        Self {
+            tcx,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// 拆分 body 中的候选变量，返回每个字段变量对应的 (原变量, 字段下标)
+    pub fn split(mut self, body: &mut Body<'tcx>) -> HashMap<Local, (Local, FieldIdx)> {
+        let mut field_locals = HashMap::new();
+        for local in self.candidates(body) {
+            let decl = &body.local_decls[local];
+            let (ty, span) = (decl.ty, decl.source_info.span);
+            let Some(field_tys) = self.scalar_fields(ty) else {
+                continue;
+            };
+            let mut fields = Vec::with_capacity(field_tys.len());
+            for (i, field_ty) in field_tys.into_iter().enumerate() {
+                let field_local = body.local_decls.push(LocalDecl::new(field_ty, span));
+                field_locals.insert(field_local, (local, FieldIdx::from_usize(i)));
+                fields.push(field_local);
+            }
+            self.fields.insert(local, fields);
+        }
+        if self.fields.is_empty() {
+            return field_locals;
+        }
+
+        // 先把字段访问替换成字段变量，再在整体赋值之后补上提取各字段的拷贝
+        let (basic_blocks, local_decls) = body.basic_blocks_and_local_decls_mut();
+        for (bb, data) in basic_blocks.iter_enumerated_mut() {
+            self.visit_basic_block_data(bb, data);
+            let mut statements = Vec::with_capacity(data.statements.len());
+            for statement in std::mem::take(&mut data.statements) {
+                let whole_def = match &statement.kind {
+                    StatementKind::Assign(box (place, _)) => place
+                        .as_local()
+                        .and_then(|local| self.fields.get(&local).map(|f| (local, f))),
+                    _ => None,
+                };
+                let source_info = statement.source_info;
+                let extracts: Vec<Statement<'tcx>> = match whole_def {
+                    Some((local, fields)) => fields
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &field_local)| {
+                            let field = FieldIdx::from_usize(i);
+                            let field_place = self.tcx.mk_place_field(
+                                Place::from(local),
+                                field,
+                                local_decls[field_local].ty,
+                            );
+                            Statement {
+                                source_info,
+                                kind: StatementKind::Assign(Box::new((
+                                    Place::from(field_local),
+                                    Rvalue::Use(Operand::Copy(field_place)),
+                                ))),
+                            }
+                        })
+                        .collect(),
+                    None => Vec::new(),
+                };
+                statements.push(statement);
+                statements.extend(extracts);
+            }
+            data.statements = statements;
+        }
+        field_locals
+    }
+
+    /// 找出只以整体赋值和单层字段访问出现的元组、结构体变量
+    fn candidates(&self, body: &Body<'tcx>) -> Vec<Local> {
+        let mut collector = CandidateCollector {
+            rejected: HashSet::new(),
+        };
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            collector.visit_basic_block_data(bb, data);
+        }
+        let mut candidates: Vec<Local> = body
+            .local_decls
+            .indices()
+            .filter(|&local| local != RETURN_PLACE && !body.args_iter().any(|arg| arg == local))
+            .filter(|local| !collector.rejected.contains(local))
+            .filter(|&local| self.scalar_fields(body.local_decls[local].ty).is_some())
+            .collect();
+        candidates.sort();
+        candidates
+    }
+
+    /// 元组或结构体的所有字段都是标量时，返回字段类型
+    fn scalar_fields(&self, ty: Ty<'tcx>) -> Option<Vec<Ty<'tcx>>> {
+        let field_tys: Vec<Ty<'tcx>> = match ty.kind() {
+            ty::Tuple(tys) => tys.iter().collect(),
+            ty::Adt(adt_def, args) if adt_def.is_struct() => adt_def
+                .non_enum_variant()
+                .fields
+                .iter()
+                .map(|field| field.ty(self.tcx, args))
+                .collect(),
+            _ => return None,
+        };
+        if field_tys.is_empty() || !field_tys.iter().all(|ty| ty.is_scalar()) {
+            return None;
+        }
+        Some(field_tys)
+    }
+}
+
+impl<'tcx> MutVisitor<'tcx> for FieldSplitter<'tcx> {
+    fn tcx(&self) -> TyCtxt<'tcx> {
+        self.tcx
+    }
+
+    fn visit_place(&mut self, place: &mut Place<'tcx>, context: PlaceContext, location: Location) {
+        if let [ProjectionElem::Field(field, _)] = place.projection.as_slice()
+            && let Some(fields) = self.fields.get(&place.local)
+        {
+            *place = Place::from(fields[field.as_usize()]);
+            return;
+        }
+        self.super_place(place, context, location);
+    }
+}
+
+/// 记录不能拆分的变量
+struct CandidateCollector {
+    rejected: HashSet<Local>,
+}
+
+impl<'tcx> Visitor<'tcx> for CandidateCollector {
+    fn visit_place(&mut self, place: &Place<'tcx>, context: PlaceContext, location: Location) {
+        let allowed = match (place.projection.as_slice(), context) {
+            // 整体赋值，以及 StorageLive/StorageDead 等非使用
+            ([], PlaceContext::MutatingUse(MutatingUseContext::Store))
+            | ([], PlaceContext::NonUse(_)) => true,
+            // 单层字段的读取和写入
+            (
+                [ProjectionElem::Field(..)],
+                PlaceContext::NonMutatingUse(
+                    NonMutatingUseContext::Copy
+                    | NonMutatingUseContext::Move
+                    | NonMutatingUseContext::Inspect,
+                )
+                | PlaceContext::MutatingUse(MutatingUseContext::Store),
+            ) => true,
+            _ => false,
+        };
+        if !allowed {
+            self.rejected.insert(place.local);
+        }
+        // 投影中作为下标出现的变量同样需要检查
+        self.super_projection(place.as_ref(), context, location);
+    }
+
+    fn visit_local(&mut self, local: Local, _context: PlaceContext, _location: Location) {
+        self.rejected.insert(local);
+    }
+}
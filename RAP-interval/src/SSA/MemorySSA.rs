@@ -0,0 +1,571 @@
+use rustc_middle::mir::visit::{
+    MutatingUseContext, NonMutatingUseContext, PlaceContext, Visitor,
+};
+use rustc_middle::mir::*;
+use rustc_middle::ty::TyCtxt;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use super::SSATransformer::{PhiNode, SSATransformer};
+
+/// 内存访问的编号，`MemorySSA::accesses` 的下标
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MemoryAccessId(pub usize);
+
+impl MemoryAccessId {
+    /// 函数入口处的内存状态
+    pub const LIVE_ON_ENTRY: MemoryAccessId = MemoryAccessId(0);
+}
+
+impl fmt::Debug for MemoryAccessId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::LIVE_ON_ENTRY {
+            write!(f, "liveOnEntry")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryAccessKind {
+    LiveOnEntry,
+    /// 可能写内存的语句或终结符
+    ///
+    /// `must` 为真表示整体覆盖了唯一的被写变量（如 `x = ..`），
+    /// 经过指针的写入和以 `&mut` 传参的调用都只是可能写入。
+    Def {
+        location: Location,
+        defining: MemoryAccessId,
+        clobbers: HashSet<Local>,
+        must: bool,
+    },
+    /// 读内存的语句或终结符，`defining` 是它读到的内存状态
+    Use {
+        location: Location,
+        defining: MemoryAccessId,
+        reads: HashSet<Local>,
+    },
+    /// 汇合块入口的内存 phi，操作数与到达它的前驱对应
+    Phi {
+        block: BasicBlock,
+        operands: Vec<(BasicBlock, MemoryAccessId)>,
+    },
+}
+
+/// 取地址变量的内存 SSA
+///
+/// 被借用（`&x`、`&mut x`、`&raw x`）的变量可能经由指针被修改，不能像普通变量那样重命名。
+/// 这里把所有取地址变量看作同一块内存，为它建立一条单独的 SSA 链（与 LLVM 的 MemorySSA 相同）：
+/// 1. 直接赋值、经过指针的写入、以 `&mut` / `*mut` 传参的调用以及 `Drop` 是 `MemoryDef`；
+/// 2. 直接读取和经过指针的读取是 `MemoryUse`；
+/// 3. 在 `MemoryDef` 所在块的迭代支配前沿上放置 `MemoryPhi`；
+/// 4. 沿支配树先序遍历，为每个访问记录它所依赖的内存状态。
+///
+/// 指针可能指向哪些变量由一个流不敏感的指向分析给出，来源不明的指针（参数、调用返回值、
+/// 从内存中读出的指针）视为可能指向所有取地址变量。经由共享引用的内部可变性不做建模。
+pub struct MemorySSA {
+    pub address_taken: HashSet<Local>,
+    pub accesses: Vec<MemoryAccessKind>,
+    defs: HashMap<Location, MemoryAccessId>,
+    uses: HashMap<Location, MemoryAccessId>,
+    phis: HashMap<BasicBlock, MemoryAccessId>,
+    points_to: PointsTo,
+}
+
+impl MemorySSA {
+    pub fn new<'tcx>(
+        tcx: TyCtxt<'tcx>,
+        body: &Body<'tcx>,
+        phi_functions: &HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+    ) -> Self {
+        let address_taken = Self::address_taken_locals(body);
+        let points_to = PointsTo::new(body, phi_functions, &address_taken);
+        let mut memory_ssa = MemorySSA {
+            address_taken,
+            accesses: vec![MemoryAccessKind::LiveOnEntry],
+            defs: HashMap::new(),
+            uses: HashMap::new(),
+            phis: HashMap::new(),
+            points_to,
+        };
+        if memory_ssa.address_taken.is_empty() {
+            return memory_ssa;
+        }
+
+        let effects = memory_ssa.collect_effects(tcx, body, phi_functions);
+        let def_blocks: HashSet<BasicBlock> = effects
+            .iter()
+            .filter(|(_, effect)| !effect.writes.is_empty())
+            .map(|(location, _)| location.block)
+            .collect();
+        memory_ssa.insert_phis(body, &def_blocks);
+        memory_ssa.rename(body, &effects);
+        memory_ssa
+    }
+
+    /// 地址被取走的变量：借用或裸借用中不经过解引用的 place 的基变量
+    pub fn address_taken_locals(body: &Body<'_>) -> HashSet<Local> {
+        let mut collector = AddressTakenCollector::default();
+        collector.visit_body(body);
+        collector.locals
+    }
+
+    pub fn is_address_taken(&self, local: Local) -> bool {
+        self.address_taken.contains(&local)
+    }
+
+    pub fn access(&self, id: MemoryAccessId) -> &MemoryAccessKind {
+        &self.accesses[id.0]
+    }
+
+    /// 位于 `location` 的 `MemoryDef`
+    pub fn def_at(&self, location: Location) -> Option<MemoryAccessId> {
+        self.defs.get(&location).copied()
+    }
+
+    /// 位于 `location` 的 `MemoryUse`
+    pub fn use_at(&self, location: Location) -> Option<MemoryAccessId> {
+        self.uses.get(&location).copied()
+    }
+
+    /// 块入口的 `MemoryPhi`
+    pub fn phi_at(&self, block: BasicBlock) -> Option<MemoryAccessId> {
+        self.phis.get(&block).copied()
+    }
+
+    /// 指针 `local` 可能指向的变量
+    pub fn pointees(&self, local: Local) -> HashSet<Local> {
+        self.points_to.pointees(local, &self.address_taken)
+    }
+
+    /// `id` 是否可能改写 `local`
+    pub fn clobbers(&self, id: MemoryAccessId, local: Local) -> bool {
+        match self.access(id) {
+            MemoryAccessKind::LiveOnEntry | MemoryAccessKind::Phi { .. } => true,
+            MemoryAccessKind::Def { clobbers, .. } => clobbers.contains(&local),
+            MemoryAccessKind::Use { .. } => false,
+        }
+    }
+
+    /// 从 `id` 沿 SSA 链向上，找到最近一个可能改写 `local` 的访问
+    ///
+    /// 遇到 `MemoryPhi` 或 `liveOnEntry` 时停止。
+    /// 区间分析读取取地址变量时，只有这个访问是 must-def 才能沿用它写入的值的区间。
+    pub fn clobbering_access(&self, id: MemoryAccessId, local: Local) -> MemoryAccessId {
+        let mut current = id;
+        loop {
+            match self.access(current) {
+                MemoryAccessKind::Use { defining, .. } => current = *defining,
+                MemoryAccessKind::Def {
+                    defining, clobbers, ..
+                } if !clobbers.contains(&local) => current = *defining,
+                _ => return current,
+            }
+        }
+    }
+
+    /// 汇总每条语句和终结符的内存读写
+    fn collect_effects<'tcx>(
+        &self,
+        tcx: TyCtxt<'tcx>,
+        body: &Body<'tcx>,
+        phi_functions: &HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+    ) -> HashMap<Location, MemoryEffect> {
+        let mut effects = HashMap::new();
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            // phi 占位语句只搬运指针，不访问内存
+            let phi_count = phi_functions.get(&bb).map_or(0, Vec::len);
+            for (statement_index, statement) in data.statements.iter().enumerate().skip(phi_count) {
+                let location = Location {
+                    block: bb,
+                    statement_index,
+                };
+                let mut collector = EffectCollector::new(self);
+                collector.visit_statement(statement, location);
+                if !collector.effect.is_empty() {
+                    effects.insert(location, collector.effect);
+                }
+            }
+            let location = body.terminator_loc(bb);
+            let terminator = data.terminator();
+            let mut collector = EffectCollector::new(self);
+            collector.visit_terminator(terminator, location);
+            if let TerminatorKind::Call { args, .. } = &terminator.kind {
+                for arg in args.iter() {
+                    if !arg.node.ty(&body.local_decls, tcx).is_mutable_ptr() {
+                        continue;
+                    }
+                    if let Some(place) = arg.node.place() {
+                        collector.write_through(place);
+                    }
+                }
+            }
+            if !collector.effect.is_empty() {
+                effects.insert(location, collector.effect);
+            }
+        }
+        effects
+    }
+
+    /// 在写内存的块的迭代支配前沿上放置 `MemoryPhi`
+    fn insert_phis(&mut self, body: &Body<'_>, def_blocks: &HashSet<BasicBlock>) {
+        let dom_tree = SSATransformer::construct_dominance_tree(body);
+        let df = SSATransformer::compute_dominance_frontier(body, &dom_tree);
+        let mut worklist: VecDeque<BasicBlock> = def_blocks.iter().copied().collect();
+        let mut visited: HashSet<BasicBlock> = def_blocks.clone();
+        let mut phi_blocks: HashSet<BasicBlock> = HashSet::new();
+        while let Some(bb) = worklist.pop_front() {
+            let Some(frontier) = df.get(&bb) else {
+                continue;
+            };
+            for &y in frontier {
+                if phi_blocks.insert(y) && visited.insert(y) {
+                    worklist.push_back(y);
+                }
+            }
+        }
+        let mut phi_blocks: Vec<BasicBlock> = phi_blocks.into_iter().collect();
+        phi_blocks.sort();
+        for block in phi_blocks {
+            let id = self.push(MemoryAccessKind::Phi {
+                block,
+                operands: Vec::new(),
+            });
+            self.phis.insert(block, id);
+        }
+    }
+
+    /// 沿支配树先序遍历，把每个访问连到它之前的内存状态上，并填写 `MemoryPhi` 的操作数
+    fn rename(&mut self, body: &Body<'_>, effects: &HashMap<Location, MemoryEffect>) {
+        let children = SSATransformer::construct_dominance_tree(body);
+        let mut stack = vec![(START_BLOCK, MemoryAccessId::LIVE_ON_ENTRY)];
+        while let Some((bb, incoming)) = stack.pop() {
+            let mut current = self.phis.get(&bb).copied().unwrap_or(incoming);
+            let data = &body.basic_blocks[bb];
+            for statement_index in 0..=data.statements.len() {
+                let location = Location {
+                    block: bb,
+                    statement_index,
+                };
+                let Some(effect) = effects.get(&location) else {
+                    continue;
+                };
+                if !effect.reads.is_empty() {
+                    let id = self.push(MemoryAccessKind::Use {
+                        location,
+                        defining: current,
+                        reads: effect.reads.clone(),
+                    });
+                    self.uses.insert(location, id);
+                }
+                if !effect.writes.is_empty() {
+                    let must = effect.must && effect.writes.len() == 1;
+                    let id = self.push(MemoryAccessKind::Def {
+                        location,
+                        defining: current,
+                        clobbers: effect.writes.clone(),
+                        must,
+                    });
+                    self.defs.insert(location, id);
+                    current = id;
+                }
+            }
+            let mut successors: Vec<BasicBlock> = data.terminator().successors().collect();
+            successors.sort();
+            successors.dedup();
+            for succ in successors {
+                if let Some(&phi) = self.phis.get(&succ)
+                    && let MemoryAccessKind::Phi { operands, .. } = &mut self.accesses[phi.0]
+                {
+                    operands.push((bb, current));
+                }
+            }
+            // 逆序压栈，使子节点按编号顺序出栈
+            if let Some(children) = children.get(&bb) {
+                for &child in children.iter().rev() {
+                    stack.push((child, current));
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, access: MemoryAccessKind) -> MemoryAccessId {
+        self.accesses.push(access);
+        MemoryAccessId(self.accesses.len() - 1)
+    }
+}
+
+impl fmt::Debug for MemorySSA {
+    /// 按编号列出所有访问，格式与 LLVM 的 MemorySSA 输出类似
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, access) in self.accesses.iter().enumerate() {
+            let id = MemoryAccessId(i);
+            match access {
+                MemoryAccessKind::LiveOnEntry => writeln!(f, "{:?}", id)?,
+                MemoryAccessKind::Def {
+                    location,
+                    defining,
+                    clobbers,
+                    must,
+                } => {
+                    let mut clobbers: Vec<&Local> = clobbers.iter().collect();
+                    clobbers.sort();
+                    writeln!(
+                        f,
+                        "{:?}: {:?} = MemoryDef({:?}) {} {:?}",
+                        location,
+                        id,
+                        defining,
+                        if *must { "must" } else { "may" },
+                        clobbers
+                    )?
+                }
+                MemoryAccessKind::Use {
+                    location,
+                    defining,
+                    reads,
+                } => {
+                    let mut reads: Vec<&Local> = reads.iter().collect();
+                    reads.sort();
+                    writeln!(f, "{:?}: MemoryUse({:?}) {:?}", location, defining, reads)?
+                }
+                MemoryAccessKind::Phi { block, operands } => {
+                    write!(f, "{:?}: {:?} = MemoryPhi(", block, id)?;
+                    for (j, (pred, value)) in operands.iter().enumerate() {
+                        if j > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{{{:?}, {:?}}}", pred, value)?;
+                    }
+                    writeln!(f, ")")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 一条语句或终结符对取地址变量的读写
+#[derive(Debug, Default)]
+struct MemoryEffect {
+    reads: HashSet<Local>,
+    writes: HashSet<Local>,
+    /// 写入只有一次不经过指针的整体赋值
+    must: bool,
+}
+
+impl MemoryEffect {
+    fn is_empty(&self) -> bool {
+        self.reads.is_empty() && self.writes.is_empty()
+    }
+}
+
+struct EffectCollector<'a> {
+    memory_ssa: &'a MemorySSA,
+    effect: MemoryEffect,
+}
+
+impl<'a> EffectCollector<'a> {
+    fn new(memory_ssa: &'a MemorySSA) -> Self {
+        Self {
+            memory_ssa,
+            effect: MemoryEffect::default(),
+        }
+    }
+
+    /// `place` 所表示的内存可能是哪些取地址变量
+    fn targets(&self, place: &Place<'_>) -> HashSet<Local> {
+        match place.projection.iter().position(|elem| elem == ProjectionElem::Deref) {
+            None if self.memory_ssa.is_address_taken(place.local) => HashSet::from([place.local]),
+            None => HashSet::new(),
+            Some(0) => self.memory_ssa.pointees(place.local),
+            // 经过字段或下标取出的指针来源不明
+            Some(_) => self.memory_ssa.address_taken.clone(),
+        }
+    }
+
+    /// 经过 `place` 中的指针写入：以 `&mut` 传参的调用可能改写指针指向的任何变量
+    fn write_through(&mut self, place: Place<'_>) {
+        let pointees = if place.projection.is_empty() {
+            self.memory_ssa.pointees(place.local)
+        } else {
+            self.memory_ssa.address_taken.clone()
+        };
+        self.effect.must = false;
+        self.effect.writes.extend(pointees);
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for EffectCollector<'a> {
+    fn visit_place(&mut self, place: &Place<'tcx>, context: PlaceContext, _location: Location) {
+        for elem in place.projection.iter() {
+            if let ProjectionElem::Index(index) = elem
+                && self.memory_ssa.is_address_taken(index)
+            {
+                self.effect.reads.insert(index);
+            }
+        }
+        let targets = self.targets(place);
+        if targets.is_empty() {
+            return;
+        }
+        match context {
+            PlaceContext::MutatingUse(
+                MutatingUseContext::Store | MutatingUseContext::Call | MutatingUseContext::AsmOutput,
+            ) => {
+                let must = self.effect.writes.is_empty()
+                    && place.projection.is_empty()
+                    && targets.len() == 1;
+                self.effect.must = must;
+                self.effect.writes.extend(targets);
+            }
+            PlaceContext::MutatingUse(
+                MutatingUseContext::SetDiscriminant
+                | MutatingUseContext::Deinit
+                | MutatingUseContext::Drop,
+            ) => {
+                self.effect.must = false;
+                self.effect.writes.extend(targets);
+            }
+            PlaceContext::NonMutatingUse(
+                NonMutatingUseContext::Copy
+                | NonMutatingUseContext::Move
+                | NonMutatingUseContext::Inspect,
+            ) => self.effect.reads.extend(targets),
+            // 取地址本身不访问内存，存储标记和调试信息也不算
+            _ => {}
+        }
+    }
+}
+
+/// 流不敏感的指向分析：每个指针变量可能指向的取地址变量
+struct PointsTo {
+    targets: HashMap<Local, HashSet<Local>>,
+    /// 来源不明的指针，可能指向任何取地址变量
+    unknown: HashSet<Local>,
+}
+
+impl PointsTo {
+    fn new<'tcx>(
+        body: &Body<'tcx>,
+        phi_functions: &HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+        address_taken: &HashSet<Local>,
+    ) -> Self {
+        let mut points_to = PointsTo {
+            targets: HashMap::new(),
+            unknown: HashSet::new(),
+        };
+        if address_taken.is_empty() {
+            return points_to;
+        }
+        for arg in body.args_iter() {
+            if body.local_decls[arg].ty.is_any_ptr() {
+                points_to.unknown.insert(arg);
+            }
+        }
+
+        // 每条赋值被看作 dest ⊇ {直接取地址的变量} ∪ pts(来源指针)
+        let mut edges: Vec<(Local, Local)> = Vec::new();
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            if let Some(phis) = phi_functions.get(&bb) {
+                for phi in phis {
+                    for (_, operand) in &phi.operands {
+                        if let Some(place) = operand.place() {
+                            edges.push((phi.dest, place.local));
+                        }
+                    }
+                }
+            }
+            let phi_count = phi_functions.get(&bb).map_or(0, Vec::len);
+            for statement in data.statements.iter().skip(phi_count) {
+                let StatementKind::Assign(box (dest, rvalue)) = &statement.kind else {
+                    continue;
+                };
+                let Some(dest) = dest.as_local() else {
+                    continue;
+                };
+                if !body.local_decls[dest].ty.is_any_ptr() {
+                    continue;
+                }
+                match rvalue {
+                    Rvalue::Ref(_, _, place) | Rvalue::RawPtr(_, place) => {
+                        if !place.is_indirect() {
+                            points_to.targets.entry(dest).or_default().insert(place.local);
+                        } else if place.projection.first() == Some(&ProjectionElem::Deref) {
+                            // 重新借用 `&(*p).f` 与 `p` 指向相同
+                            edges.push((dest, place.local));
+                        } else {
+                            points_to.unknown.insert(dest);
+                        }
+                    }
+                    Rvalue::Use(operand) | Rvalue::Cast(_, operand, _) => {
+                        match operand.place().and_then(|place| place.as_local()) {
+                            Some(source) => edges.push((dest, source)),
+                            None if matches!(operand, Operand::Constant(_)) => {}
+                            None => {
+                                points_to.unknown.insert(dest);
+                            }
+                        }
+                    }
+                    _ => {
+                        points_to.unknown.insert(dest);
+                    }
+                }
+            }
+            if let TerminatorKind::Call { destination, .. } = &data.terminator().kind
+                && body.local_decls[destination.local].ty.is_any_ptr()
+            {
+                points_to.unknown.insert(destination.local);
+            }
+        }
+
+        // 沿赋值边传播到不动点
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(dest, source) in &edges {
+                if points_to.unknown.contains(&source) {
+                    changed |= points_to.unknown.insert(dest);
+                    continue;
+                }
+                let source_targets = points_to.targets.get(&source).cloned().unwrap_or_default();
+                let dest_targets = points_to.targets.entry(dest).or_default();
+                for target in source_targets {
+                    changed |= dest_targets.insert(target);
+                }
+            }
+        }
+        points_to
+    }
+
+    fn pointees(&self, local: Local, address_taken: &HashSet<Local>) -> HashSet<Local> {
+        if self.unknown.contains(&local) {
+            return address_taken.clone();
+        }
+        self.targets.get(&local).cloned().unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct AddressTakenCollector {
+    locals: HashSet<Local>,
+}
+
+impl<'tcx> Visitor<'tcx> for AddressTakenCollector {
+    fn visit_place(&mut self, place: &Place<'tcx>, context: PlaceContext, location: Location) {
+        let is_borrow = matches!(
+            context,
+            PlaceContext::NonMutatingUse(
+                NonMutatingUseContext::SharedBorrow
+                    | NonMutatingUseContext::FakeBorrow
+                    | NonMutatingUseContext::RawBorrow
+            ) | PlaceContext::MutatingUse(MutatingUseContext::Borrow | MutatingUseContext::RawBorrow)
+        );
+        if is_borrow && !place.is_indirect() {
+            self.locals.insert(place.local);
+        }
+        self.super_place(place, context, location);
+    }
+}
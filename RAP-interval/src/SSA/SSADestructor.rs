@@ -0,0 +1,195 @@
+use rustc_middle::mir::*;
+
+use std::collections::{HashMap, HashSet};
+
+use super::SSATransformer::PhiNode;
+
+/// 把 `SSATransformer` 生成的 SSA 形式还原成可以交还给 rustc 的 MIR
+///
+/// 1. 删除块开头的 phi，改为在每条入边上插入并行拷贝；
+/// 2. 前驱的终结符不是 `Goto` 时（关键边、带返回值的调用等）拆分这条边，
+///    拷贝放在新建的中间块里，避免拷贝覆盖其他后继仍然需要的值；
+/// 3. 并行拷贝按依赖顺序串行化，遇到环时借助临时变量打破；
+/// 4. 删除参与了重命名的变量及其各个版本的 StorageLive/StorageDead：重命名时标记
+///    跟随到达定义改名，与各版本的实际定义对不上；不带标记的变量在整个函数内都视为存活。
+///    没有改名的变量（取地址、部分写入、本来就是单赋值的变量）保留原有的标记。
+///
+/// sigma 本身就是普通的拷贝语句，保持不变。
+pub struct SSADestructor<'a, 'tcx> {
+    body: Body<'tcx>,
+    phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+    /// 参与了重命名的原始变量及其所有版本
+    renamed: HashSet<Local>,
+}
+
+impl<'a, 'tcx> SSADestructor<'a, 'tcx> {
+    pub fn new(
+        body: &Body<'tcx>,
+        phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+        renamed: HashSet<Local>,
+    ) -> Self {
+        Self {
+            body: body.clone(),
+            phi_functions,
+            renamed,
+        }
+    }
+
+    pub fn destruct(mut self) -> Body<'tcx> {
+        let phi_functions = self.phi_functions;
+        let mut blocks: Vec<BasicBlock> = phi_functions.keys().copied().collect();
+        blocks.sort();
+
+        // 1. 收集每条边上的并行拷贝，并删除 phi 语句
+        let mut edge_copies: Vec<(BasicBlock, BasicBlock, Vec<(Local, Operand<'tcx>)>)> =
+            Vec::new();
+        for bb in blocks {
+            let phis = &phi_functions[&bb];
+            let Some(first) = phis.first() else {
+                continue;
+            };
+            // 同一个前驱可能通过多条边到达（如 SwitchInt 的多个值指向同一块），只需一份拷贝
+            let mut seen = HashSet::new();
+            for (j, (pred, _)) in first.operands.iter().enumerate() {
+                if !seen.insert(*pred) {
+                    continue;
+                }
+                let copies = phis
+                    .iter()
+                    .map(|phi| (phi.dest, phi.operands[j].1.clone()))
+                    .collect();
+                edge_copies.push((*pred, bb, copies));
+            }
+            self.body.basic_blocks_mut()[bb]
+                .statements
+                .drain(0..phis.len());
+        }
+
+        // 2. 把拷贝放到前驱末尾，或拆分后的中间块里
+        for (pred, bb, copies) in edge_copies {
+            let source_info = self.body.basic_blocks[pred].terminator().source_info;
+            let statements = self.sequentialize(copies, source_info);
+            if statements.is_empty() {
+                continue;
+            }
+            let is_goto = matches!(
+                self.body.basic_blocks[pred].terminator().kind,
+                TerminatorKind::Goto { .. }
+            );
+            if is_goto {
+                self.body.basic_blocks_mut()[pred]
+                    .statements
+                    .extend(statements);
+            } else {
+                self.split_edge(pred, bb, statements, source_info);
+            }
+        }
+
+        // 3. 删除改过名的变量的存储标记
+        let renamed = &self.renamed;
+        for data in self.body.basic_blocks_mut().iter_mut() {
+            data.statements.retain(|statement| match statement.kind {
+                StatementKind::StorageLive(local) | StatementKind::StorageDead(local) => {
+                    !renamed.contains(&local)
+                }
+                _ => true,
+            });
+        }
+
+        self.body
+    }
+
+    /// 在 `pred -> bb` 之间插入一个只包含 `statements` 的新块
+    fn split_edge(
+        &mut self,
+        pred: BasicBlock,
+        bb: BasicBlock,
+        statements: Vec<Statement<'tcx>>,
+        source_info: SourceInfo,
+    ) {
+        let is_cleanup = self.body.basic_blocks[bb].is_cleanup;
+        let new_block = self.body.basic_blocks_mut().push(BasicBlockData {
+            statements,
+            terminator: Some(Terminator {
+                source_info,
+                kind: TerminatorKind::Goto { target: bb },
+            }),
+            is_cleanup,
+        });
+        for target in self.body.basic_blocks_mut()[pred]
+            .terminator_mut()
+            .successors_mut()
+        {
+            if *target == bb {
+                *target = new_block;
+            }
+        }
+    }
+
+    /// 把并行拷贝 `dest_i = src_i` 串行化
+    ///
+    /// 每次取出一个不再被其他拷贝读取的目标先赋值；剩下的都在环上时，
+    /// 把某个目标的旧值存到临时变量，并让读取它的拷贝改读临时变量。
+    fn sequentialize(
+        &mut self,
+        copies: Vec<(Local, Operand<'tcx>)>,
+        source_info: SourceInfo,
+    ) -> Vec<Statement<'tcx>> {
+        let reads = |operand: &Operand<'tcx>, local: Local| {
+            operand.place().is_some_and(|place| place.local == local)
+        };
+        let mut pending: Vec<(Local, Operand<'tcx>)> = copies
+            .into_iter()
+            .filter(|(dest, src)| src.place().and_then(|place| place.as_local()) != Some(*dest))
+            .map(|(dest, src)| match src.place().and_then(|place| place.as_local()) {
+                Some(local) => (dest, self.operand(local)),
+                None => (dest, src),
+            })
+            .collect();
+        let mut statements = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready = (0..pending.len()).find(|&i| {
+                let dest = pending[i].0;
+                !pending
+                    .iter()
+                    .enumerate()
+                    .any(|(j, (_, src))| j != i && reads(src, dest))
+            });
+            match ready {
+                Some(i) => {
+                    let (dest, src) = pending.remove(i);
+                    statements.push(Self::assign(dest, src, source_info));
+                }
+                None => {
+                    let dest = pending[0].0;
+                    let decl = self.body.local_decls[dest].clone();
+                    let tmp = self.body.local_decls.push(decl);
+                    statements.push(Self::assign(tmp, self.operand(dest), source_info));
+                    let tmp_operand = self.operand(tmp);
+                    for (_, src) in pending.iter_mut() {
+                        if reads(src, dest) {
+                            *src = tmp_operand.clone();
+                        }
+                    }
+                }
+            }
+        }
+        statements
+    }
+
+    /// 平凡可拷贝的类型用 copy，其余用 move
+    fn operand(&self, local: Local) -> Operand<'tcx> {
+        if self.body.local_decls[local].ty.is_trivially_pure_clone_copy() {
+            Operand::Copy(Place::from(local))
+        } else {
+            Operand::Move(Place::from(local))
+        }
+    }
+
+    fn assign(dest: Local, src: Operand<'tcx>, source_info: SourceInfo) -> Statement<'tcx> {
+        Statement {
+            source_info,
+            kind: StatementKind::Assign(Box::new((Place::from(dest), Rvalue::Use(src)))),
+        }
+    }
+}
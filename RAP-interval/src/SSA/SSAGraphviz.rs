@@ -0,0 +1,126 @@
+use rustc_middle::mir::*;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use super::SSATransformer::{PhiNode, SigmaNode};
+
+/// 以 Graphviz 格式输出 SSA 构造的中间结果：
+/// 控制流图（每个块列出其中的 phi / sigma）、支配树和支配前沿。
+///
+/// 三个函数都只写出一个 `digraph`，文件的创建由调用者负责。
+pub struct SSAGraphviz<'a, 'tcx> {
+    body: &'a Body<'tcx>,
+    phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+    sigma_functions: &'a HashMap<BasicBlock, Vec<SigmaNode>>,
+    /// 图的名字，一般为函数的 DefId
+    name: String,
+}
+
+impl<'a, 'tcx> SSAGraphviz<'a, 'tcx> {
+    pub fn new(
+        body: &'a Body<'tcx>,
+        phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+        sigma_functions: &'a HashMap<BasicBlock, Vec<SigmaNode>>,
+        name: String,
+    ) -> Self {
+        Self {
+            body,
+            phi_functions,
+            sigma_functions,
+            name,
+        }
+    }
+
+    /// 控制流图：节点标签为块名及其中的 phi / sigma，边上标注分支值，展开边用虚线
+    pub fn write_cfg(&self, w: &mut dyn Write) -> io::Result<()> {
+        writeln!(w, "digraph \"cfg {}\" {{", escape(&self.name))?;
+        writeln!(w, "    node [shape=box, fontname=\"monospace\"];")?;
+        for (bb, data) in self.body.basic_blocks.iter_enumerated() {
+            let mut label = format!("{:?}", bb);
+            if data.is_cleanup {
+                label.push_str(" (cleanup)");
+            }
+            label.push_str("\\l");
+            for phi in self.phi_functions.get(&bb).into_iter().flatten() {
+                label.push_str(&escape(&format!("{:?}", phi)));
+                label.push_str("\\l");
+            }
+            for sigma in self.sigma_functions.get(&bb).into_iter().flatten() {
+                label.push_str(&escape(&format!("{:?}", sigma)));
+                label.push_str("\\l");
+            }
+            writeln!(w, "    {:?} [label=\"{}\"];", bb, label)?;
+        }
+        for (bb, data) in self.body.basic_blocks.iter_enumerated() {
+            let terminator = data.terminator();
+            let unwind = match terminator.unwind() {
+                Some(UnwindAction::Cleanup(cleanup)) => Some(*cleanup),
+                _ => None,
+            };
+            let labels = terminator.kind.fmt_successor_labels();
+            for (i, succ) in terminator.successors().enumerate() {
+                let label = labels.get(i).map_or(String::new(), |label| escape(label));
+                let style = if Some(succ) == unwind { ", style=dashed" } else { "" };
+                writeln!(w, "    {:?} -> {:?} [label=\"{}\"{}];", bb, succ, label, style)?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    /// 支配树：从直接支配者指向被支配的块
+    pub fn write_dominator_tree(
+        &self,
+        w: &mut dyn Write,
+        dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
+    ) -> io::Result<()> {
+        writeln!(w, "digraph \"dom_tree {}\" {{", escape(&self.name))?;
+        writeln!(w, "    node [shape=ellipse];")?;
+        for bb in self.body.basic_blocks.indices() {
+            writeln!(w, "    {:?};", bb)?;
+        }
+        let mut idoms: Vec<&BasicBlock> = dom_tree.keys().collect();
+        idoms.sort();
+        for idom in idoms {
+            for child in &dom_tree[idom] {
+                writeln!(w, "    {:?} -> {:?};", idom, child)?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    /// 支配前沿：控制流边用浅色实线作为背景，`b -> y` 的虚线表示 y ∈ DF(b)
+    pub fn write_dominance_frontier(
+        &self,
+        w: &mut dyn Write,
+        df: &HashMap<BasicBlock, HashSet<BasicBlock>>,
+    ) -> io::Result<()> {
+        writeln!(w, "digraph \"df {}\" {{", escape(&self.name))?;
+        writeln!(w, "    node [shape=ellipse];")?;
+        for (bb, data) in self.body.basic_blocks.iter_enumerated() {
+            writeln!(w, "    {:?};", bb)?;
+            for succ in data.terminator().successors() {
+                writeln!(w, "    {:?} -> {:?} [color=gray];", bb, succ)?;
+            }
+        }
+        let mut blocks: Vec<&BasicBlock> = df.keys().collect();
+        blocks.sort();
+        for bb in blocks {
+            let mut frontier: Vec<&BasicBlock> = df[bb].iter().collect();
+            frontier.sort();
+            for y in frontier {
+                writeln!(
+                    w,
+                    "    {:?} -> {:?} [style=dashed, color=red, constraint=false];",
+                    bb, y
+                )?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+}
+
+/// 转义 dot 字符串中的反斜杠和引号
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,17 +1,27 @@
 use rustc_data_structures::graph::dominators::Dominators;
-// use rustc_mir_transform::ssa::SsaLocals;
-// use crate::ssa::SsaLocals;
+use crate::interval_analysis::ssa::SsaLocals;
 use rustc_data_structures::graph::{dominators, Predecessors};
 use rustc_hir::def_id::LocalDefId;
-use rustc_index::{Idx, IndexVec};
+use rustc_index::{Idx, IndexSlice};
 use rustc_interface::{interface::Compiler, Queries};
 use rustc_middle::mir::pretty::*;
 use rustc_middle::mir::*;
 use rustc_middle::{
-    mir::{visit::Visitor, Body, Local, Location},
+    mir::{
+        visit::{MutVisitor, MutatingUseContext, PlaceContext, Visitor},
+        Body, Local, Location,
+    },
     ty::TyCtxt,
 };
 use rustc_span::sym::new;
+use rustc_span::Span;
+use rustc_target::abi::FieldIdx;
+
+use super::FieldSplitter::FieldSplitter;
+use super::MemorySSA::MemorySSA;
+use super::SSADestructor::SSADestructor;
+use super::SSAGraphviz::SSAGraphviz;
+use super::SSAVerifier::{OutOfSSAVerifier, SSAVerifier, SSAViolation};
 
 use std::cell::{RefCell, UnsafeCell};
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -75,23 +85,196 @@ use tracing::{debug, error, info, warn};
 
 // }
 
+/// φ 函数：`dest = φ(pred_0: op_0, pred_1: op_1, ...)`
+///
+/// 在 body 中 phi 以基本块开头的一条 `dest = copy op_0` 占位，类型与 `dest`
+/// 一致，body 仍能通过 rustc 的类型检查。完整的操作数只记录在
+/// `SSATransformer::phi_functions` 中，是否为 phi 也只看这张表，不依赖右值的形状。
+#[derive(Clone)]
+pub struct PhiNode<'tcx> {
+    /// 被合并的原始变量
+    pub local: Local,
+    /// phi 定义的变量，重命名后为新的版本
+    pub dest: Local,
+    /// 每个前驱块及其传入的操作数，顺序与 `predecessors()` 一致
+    pub operands: Vec<(BasicBlock, Operand<'tcx>)>,
+}
+
+impl<'tcx> PhiNode<'tcx> {
+    /// 生成 body 中占位用的赋值语句，右值取第一个操作数
+    ///
+    /// phi 所在的块至少有两个前驱，操作数不会为空。
+    fn to_statement(&self, span: rustc_span::Span) -> Statement<'tcx> {
+        let (_, first) = &self.operands[0];
+        Statement {
+            source_info: SourceInfo::outermost(span),
+            kind: StatementKind::Assign(Box::new((
+                Place::from(self.dest),
+                Rvalue::Use(first.clone()),
+            ))),
+        }
+    }
+}
+
+impl<'tcx> std::fmt::Debug for PhiNode<'tcx> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} = phi(", self.dest)?;
+        for (i, (pred, op)) in self.operands.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}: {:?}", pred, op)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// σ 函数（e-SSA）：`dest = σ(source)`，位于条件分支某个后继块的开头
+///
+/// 分支条件只在这条边上成立，σ 为被比较的变量在该分支内起一个新名字，
+/// 区间分析可以把分支上的约束挂到 `dest` 上。
+#[derive(Clone)]
+pub struct SigmaNode {
+    /// 被细化的原始变量
+    pub local: Local,
+    /// σ 定义的变量，重命名后为新的版本
+    pub dest: Local,
+    /// 进入分支前的版本
+    pub source: Local,
+    /// 产生分支的 SwitchInt 所在块
+    pub switch_block: BasicBlock,
+    /// 这条边对应的判别值，`None` 表示 otherwise 分支
+    pub value: Option<u128>,
+}
+
+impl std::fmt::Debug for SigmaNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} = sigma({:?}) from {:?} on ", self.dest, self.source, self.switch_block)?;
+        match self.value {
+            Some(value) => write!(f, "{}", value),
+            None => write!(f, "otherwise"),
+        }
+    }
+}
+
+/// SSA 版本与源码变量的对应关系
+#[derive(Debug, Clone)]
+pub struct SSAVersion {
+    /// 重命名前的原始变量
+    pub local: Local,
+    /// 版本号，0 表示原始变量本身（参数、未改名的变量）
+    pub version: usize,
+    /// 源码中的变量名，来自 `var_debug_info`；编译器生成的临时变量没有名字
+    pub name: Option<String>,
+    /// 定义位置，原始变量为 `None`
+    pub def: Option<Location>,
+    pub span: Span,
+    /// `test1.rs:8` 形式的源码位置
+    pub position: String,
+}
+
+impl SSAVersion {
+    /// `i#3` 形式的简短名字，没有源码名时使用原始变量，如 `_5#2`
+    pub fn short_name(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{}#{}", name, self.version),
+            None => format!("{:?}#{}", self.local, self.version),
+        }
+    }
+}
+
+impl std::fmt::Display for SSAVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.short_name(), self.position)
+    }
+}
+
+/// phi 的放置策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PhiPlacement {
+    /// 最小 SSA：为每个在多个块中被赋值的变量，在迭代支配前沿上放置 phi
+    #[default]
+    Minimal,
+    /// 半剪枝 SSA：只考虑跨块活跃的变量，即在某个块中先被使用、后被定义的变量，
+    /// 只在单个块内使用的编译器临时变量不会产生 phi
+    SemiPruned,
+    /// 剪枝 SSA：只在变量于汇合块入口处活跃时放置 phi
+    Pruned,
+}
+
+/// `SSATransformer` 的构造选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SSAOptions {
+    pub phi_placement: PhiPlacement,
+    /// 重命名前先把只按字段访问的元组、结构体变量拆成每个字段一个变量，
+    /// 见 `FieldSplitter`
+    pub field_sensitive: bool,
+    /// 先询问 rustc 的 `SsaLocals`：合并拷贝等价类，已经是单赋值的变量保留原名、不放置 phi
+    pub reuse_ssa_locals: bool,
+    /// 消去 phi 之后用 `OutOfSSAVerifier` 校验得到的 MIR，发现问题时 panic
+    pub verify: bool,
+}
+
 pub struct SSATransformer<'tcx> {
     tcx: TyCtxt<'tcx>, // TyCtxt 上下文
     def_id: LocalDefId,
+    options: SSAOptions,
     pub body: Rc<RefCell<Body<'tcx>>>,
     // pub body:     &'tcx  Body<'tcx>,                  // MIR 的优化中间表示
     cfg: HashMap<BasicBlock, Vec<BasicBlock>>, // 控制流图
     dominators: Dominators<BasicBlock>,        // 支配者分析结果
     dom_tree: HashMap<BasicBlock, Vec<BasicBlock>>, // 支配树
+    dom_numbering: HashMap<BasicBlock, (usize, usize)>, // 支配树的先序、后序编号
     df: HashMap<BasicBlock, HashSet<BasicBlock>>, // 支配前沿
     local_assign_blocks: HashMap<Local, HashSet<BasicBlock>>, // 局部变量的赋值块映射
     reaching_def: HashMap<Local, Option<Local>>,
     local_defination_block: HashMap<Local, BasicBlock>,
+    /// 每个基本块开头的 phi 函数，与 body 中前 `len()` 条语句一一对应
+    pub phi_functions: HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+    /// 每个基本块中紧跟在 phi 之后的 sigma 函数
+    pub sigma_functions: HashMap<BasicBlock, Vec<SigmaNode>>,
+    /// 字段拆分产生的变量对应的 (原变量, 字段下标)
+    pub field_locals: HashMap<Local, (Local, FieldIdx)>,
+    /// 地址被取走的变量，可能经由指针被修改，不参与重命名，由 `memory_ssa` 建模
+    address_taken: HashSet<Local>,
+    /// 经由投影（字段、解引用）或 SetDiscriminant/Deinit 被就地写入的变量，
+    /// 写入只改变一部分值，无法给出新版本，不参与重命名
+    partial_writes: HashSet<Local>,
+    /// `SsaLocals` 认为已经是单赋值的变量，保留原名；插入 sigma 后不再属于此集合
+    ssa_locals: HashSet<Local>,
+    /// 重命名之后建立的内存 SSA
+    pub memory_ssa: Option<MemorySSA>,
+    /// 原始变量在 `var_debug_info` 中的名字
+    debug_names: HashMap<Local, String>,
+    /// 每个变量（包括重命名产生的版本）对应的源码变量与定义位置
+    pub versions: HashMap<Local, SSAVersion>,
+    /// 每个位置上定义的版本
+    version_defs: HashMap<Location, Local>,
+    /// 每个原始变量已经创建的版本数
+    version_counts: HashMap<Local, usize>,
 }
 
 impl<'tcx> SSATransformer<'tcx> {
-    pub fn new(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> Self {
+    pub fn new(tcx: TyCtxt<'tcx>, def_id: LocalDefId, options: SSAOptions) -> Self {
         let mut body_clone = tcx.optimized_mir(def_id).clone();
+        let field_locals = if options.field_sensitive {
+            FieldSplitter::new(tcx).split(&mut body_clone)
+        } else {
+            HashMap::default()
+        };
+        let ssa_locals = if options.reuse_ssa_locals {
+            let ssa = SsaLocals::new(tcx, &body_clone, tcx.param_env(def_id));
+            Self::merge_copy_classes(tcx, &mut body_clone, &ssa);
+            body_clone
+                .local_decls
+                .indices()
+                .filter(|&local| ssa.is_ssa(local) && ssa.copy_classes()[local] == local)
+                .collect()
+        } else {
+            HashSet::default()
+        };
+        Self::split_call_return_edges(&mut body_clone);
+        Self::split_sigma_edges(&mut body_clone);
         let body_ref = Rc::new(RefCell::new(body_clone));
         let cfg: HashMap<BasicBlock, Vec<BasicBlock>> =
             Self::extract_cfg_from_predecessors(&body_ref.borrow());
@@ -102,6 +285,7 @@ impl<'tcx> SSATransformer<'tcx> {
         let dom_tree: HashMap<BasicBlock, Vec<BasicBlock>> =
             Self::construct_dominance_tree(&body_ref.borrow());
 
+        let dom_numbering = Self::number_dominance_tree(&dom_tree);
         let df: HashMap<BasicBlock, HashSet<BasicBlock>> =
             Self::compute_dominance_frontier(&body_ref.borrow(), &dom_tree);
 
@@ -109,21 +293,79 @@ impl<'tcx> SSATransformer<'tcx> {
             Self::map_locals_to_assign_blocks(&body_ref.borrow());
         let local_defination_block: HashMap<Local, BasicBlock> =
             Self::map_locals_to_definition_block(&body_ref.borrow());
+        let address_taken = MemorySSA::address_taken_locals(&body_ref.borrow());
+        let partial_writes = Self::collect_partial_writes(&body_ref.borrow());
+        let debug_names = Self::collect_debug_names(&body_ref.borrow(), &field_locals);
         SSATransformer {
             tcx,
             def_id,
+            options,
             // body:RefCell::new(body),
             body: body_ref,
             cfg,
             dominators,
             dom_tree,
+            dom_numbering,
             df,
             local_assign_blocks,
             reaching_def: HashMap::default(),
             local_defination_block,
+            phi_functions: HashMap::default(),
+            sigma_functions: HashMap::default(),
+            field_locals,
+            address_taken,
+            partial_writes,
+            ssa_locals,
+            memory_ssa: None,
+            debug_names,
+            versions: HashMap::default(),
+            version_defs: HashMap::default(),
+            version_counts: HashMap::default(),
         }
     }
 
+    pub fn def_id(&self) -> LocalDefId {
+        self.def_id
+    }
+
+    /// 从 `var_debug_info` 收集变量名，拆分出的字段变量命名为 `name.k`
+    fn collect_debug_names(
+        body: &Body<'tcx>,
+        field_locals: &HashMap<Local, (Local, FieldIdx)>,
+    ) -> HashMap<Local, String> {
+        let mut names = HashMap::new();
+        for info in &body.var_debug_info {
+            if let VarDebugInfoContents::Place(place) = &info.value
+                && let Some(local) = place.as_local()
+            {
+                names.entry(local).or_insert_with(|| info.name.to_string());
+            }
+        }
+        for (&field_local, &(parent, field)) in field_locals {
+            if let Some(name) = names.get(&parent).cloned() {
+                names.insert(field_local, format!("{}.{}", name, field.as_usize()));
+            }
+        }
+        names
+    }
+
+    /// `test1.rs:8` 形式的源码位置
+    fn source_position(&self, span: Span) -> String {
+        let loc = self.tcx.sess.source_map().lookup_char_pos(span.lo());
+        let file = loc.file.name.prefer_local().to_string();
+        let file = std::path::Path::new(&file)
+            .file_name()
+            .map_or(file.clone(), |name| name.to_string_lossy().into_owned());
+        format!("{}:{}", file, loc.line)
+    }
+
+    /// 变量的可读名字，如 `i#3`；未知变量退回 `_27`
+    pub fn version_name(&self, local: Local) -> String {
+        self.versions
+            .get(&local)
+            .map_or_else(|| format!("{:?}", local), SSAVersion::short_name)
+    }
+
     /// 打印分析结果
     ///
     pub fn print_phi_mir(&self) {
@@ -135,12 +377,52 @@ impl<'tcx> SSATransformer<'tcx> {
         write_mir_fn(
             self.tcx,
             &self.body.borrow(),
-            &mut |_, _| Ok(()),
+            &mut |pass_where, w| self.write_ssa_annotation(pass_where, w),
             &mut w,
             options,
         )
         .unwrap();
     }
+    /// 在 phi / sigma 语句前输出注释，标明操作数来自哪个前驱或哪条分支
+    fn write_ssa_annotation(&self, pass_where: PassWhere, w: &mut dyn Write) -> io::Result<()> {
+        if let PassWhere::BeforeLocation(location) = pass_where {
+            if let Some(phi) = self.phi_at(location) {
+                writeln!(w, "        // {:?}", phi)?;
+            } else if let Some(sigma) = self.sigma_at(location) {
+                writeln!(w, "        // {:?}", sigma)?;
+            }
+            if let Some(local) = self.version_defs.get(&location) {
+                writeln!(w, "        // {:?}: {}", local, self.versions[local])?;
+            }
+            if let Some(memory_ssa) = &self.memory_ssa {
+                if location.statement_index == 0
+                    && let Some(phi) = memory_ssa.phi_at(location.block)
+                {
+                    writeln!(w, "        // {:?} = {:?}", phi, memory_ssa.access(phi))?;
+                }
+                if let Some(id) = memory_ssa.use_at(location) {
+                    writeln!(w, "        // {:?}", memory_ssa.access(id))?;
+                }
+                if let Some(id) = memory_ssa.def_at(location) {
+                    writeln!(w, "        // {:?} = {:?}", id, memory_ssa.access(id))?;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// 返回位于 `location` 的 phi 函数
+    pub fn phi_at(&self, location: Location) -> Option<&PhiNode<'tcx>> {
+        self.phi_functions
+            .get(&location.block)
+            .and_then(|phis| phis.get(location.statement_index))
+    }
+    /// 返回位于 `location` 的 sigma 函数，sigma 排在该块所有 phi 之后
+    pub fn sigma_at(&self, location: Location) -> Option<&SigmaNode> {
+        let phi_count = self.phi_functions.get(&location.block).map_or(0, Vec::len);
+        self.sigma_functions
+            .get(&location.block)
+            .and_then(|sigmas| sigmas.get(location.statement_index.checked_sub(phi_count)?))
+    }
     pub fn analyze(&self) {
         // println!("{:?}", self.cfg);
         // println!("{:?}", self.dominators);
@@ -165,45 +447,104 @@ impl<'tcx> SSATransformer<'tcx> {
         write_mir_fn(
             self.tcx,
             &self.body.borrow(),
-            &mut |_, _| Ok(()),
+            &mut |pass_where, w| self.write_ssa_annotation(pass_where, w),
             &mut w2,
             options,
         )
         .unwrap();
+
+        // 每个版本对应的源码变量
+        let versions_file_path = format!("{}/versions_{:?}.txt", dir_path, self.def_id);
+        let mut file3 = File::create(&versions_file_path).unwrap();
+        let mut w3 = io::BufWriter::new(&mut file3);
+        let mut locals: Vec<&Local> = self.versions.keys().collect();
+        locals.sort();
+        for local in locals {
+            writeln!(w3, "{:?}: {}", local, self.versions[local]).unwrap();
+        }
     }
+    /// 在 ssa_mir 目录下输出控制流图、支配树和支配前沿的 .dot 文件
+    pub fn print_graphviz(&self) {
+        let dir_path = "ssa_mir";
+        let body = self.body.borrow();
+        let graphviz = SSAGraphviz::new(
+            &body,
+            &self.phi_functions,
+            &self.sigma_functions,
+            format!("{:?}", self.def_id),
+        );
+        let mut file = File::create(format!("{}/cfg_{:?}.dot", dir_path, self.def_id)).unwrap();
+        graphviz.write_cfg(&mut io::BufWriter::new(&mut file)).unwrap();
+        let mut file =
+            File::create(format!("{}/dom_tree_{:?}.dot", dir_path, self.def_id)).unwrap();
+        graphviz
+            .write_dominator_tree(&mut io::BufWriter::new(&mut file), &self.dom_tree)
+            .unwrap();
+        let mut file = File::create(format!("{}/df_{:?}.dot", dir_path, self.def_id)).unwrap();
+        graphviz
+            .write_dominance_frontier(&mut io::BufWriter::new(&mut file), &self.df)
+            .unwrap();
+    }
+    /// 支配树的后序遍历，从 START_BLOCK 出发，用显式栈代替递归
     fn depth_first_search_postorder(
         dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
     ) -> Vec<BasicBlock> {
-        let mut visited: HashSet<BasicBlock> = HashSet::new();
         let mut postorder = Vec::new();
-
-        fn dfs(
-            node: BasicBlock,
-            dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
-            visited: &mut HashSet<BasicBlock>,
-            postorder: &mut Vec<BasicBlock>,
-        ) {
-            if visited.insert(node) {
-                // 遍历当前节点的子节点
-                if let Some(children) = dom_tree.get(&node) {
-                    for &child in children {
-                        dfs(child, dom_tree, visited, postorder);
-                    }
+        // (节点, 下一个要访问的子节点下标)
+        let mut stack: Vec<(BasicBlock, usize)> = vec![(START_BLOCK, 0)];
+        while let Some((node, next_child)) = stack.last_mut() {
+            let node = *node;
+            match dom_tree.get(&node).and_then(|children| children.get(*next_child)) {
+                Some(&child) => {
+                    *next_child += 1;
+                    stack.push((child, 0));
+                }
+                None => {
+                    // 当前节点的子树访问结束，加入后序结果
+                    postorder.push(node);
+                    stack.pop();
                 }
-                // 当前节点访问结束，加入后序结果
-                postorder.push(node);
             }
         }
-
-        // 开始从支配树的任意一个根节点进行 DFS
-        if let Some(&start_node) = dom_tree.keys().next() {
-            dfs(start_node, dom_tree, &mut visited, &mut postorder);
-        }
-
         postorder
     }
-    fn map_locals_to_definition_block(body: &Body) -> HashMap<Local, BasicBlock> {
+    /// 为支配树中的每个块编号 (先序号, 后序号)
+    ///
+    /// a 支配 b 当且仅当 b 在 a 的子树中，即 `pre[a] <= pre[b] && post[b] <= post[a]`，
+    /// 之后每次支配查询都是常数时间。
+    fn number_dominance_tree(
+        dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
+    ) -> HashMap<BasicBlock, (usize, usize)> {
+        let mut numbering: HashMap<BasicBlock, (usize, usize)> = HashMap::new();
+        let (mut pre, mut post) = (0, 0);
+        let mut stack: Vec<(BasicBlock, usize)> = vec![(START_BLOCK, 0)];
+        numbering.insert(START_BLOCK, (pre, 0));
+        while let Some((node, next_child)) = stack.last_mut() {
+            let node = *node;
+            match dom_tree.get(&node).and_then(|children| children.get(*next_child)) {
+                Some(&child) => {
+                    *next_child += 1;
+                    pre += 1;
+                    numbering.insert(child, (pre, 0));
+                    stack.push((child, 0));
+                }
+                None => {
+                    if let Some(entry) = numbering.get_mut(&node) {
+                        entry.1 = post;
+                    }
+                    post += 1;
+                    stack.pop();
+                }
+            }
+        }
+        numbering
+    }
+    fn map_locals_to_definition_block(body: &Body<'tcx>) -> HashMap<Local, BasicBlock> {
         let mut local_to_block_map: HashMap<Local, BasicBlock> = HashMap::new();
+        // 参数在入口处隐式定义
+        for arg in body.args_iter() {
+            local_to_block_map.insert(arg, START_BLOCK);
+        }
 
         // 遍历每个基本块
         for (bb, block_data) in body.basic_blocks.iter_enumerated() {
@@ -221,12 +562,24 @@ impl<'tcx> SSATransformer<'tcx> {
                     _ => {}
                 }
             }
+            // 函数调用的返回值同样是定义，位于正常返回的后继上
+            if let TerminatorKind::Call { destination, .. } = &block_data.terminator().kind
+                && let Some(local) = destination.as_local()
+            {
+                local_to_block_map
+                    .entry(local)
+                    .or_insert(Self::call_return_block(body, bb));
+            }
         }
 
         local_to_block_map
     }
-    fn map_locals_to_assign_blocks(body: &Body) -> HashMap<Local, HashSet<BasicBlock>> {
+    fn map_locals_to_assign_blocks(body: &Body<'tcx>) -> HashMap<Local, HashSet<BasicBlock>> {
         let mut local_to_blocks: HashMap<Local, HashSet<BasicBlock>> = HashMap::new();
+        // 参数在入口处隐式定义，之后在其他块中被重新赋值时同样需要 phi
+        for arg in body.args_iter() {
+            local_to_blocks.entry(arg).or_default().insert(START_BLOCK);
+        }
 
         for (bb, data) in body.basic_blocks.iter_enumerated() {
             for stmt in &data.statements {
@@ -240,11 +593,17 @@ impl<'tcx> SSATransformer<'tcx> {
                         .insert(bb);
                 }
             }
+            if let TerminatorKind::Call { destination, .. } = &data.terminator().kind {
+                local_to_blocks
+                    .entry(destination.local)
+                    .or_insert_with(HashSet::new)
+                    .insert(Self::call_return_block(body, bb));
+            }
         }
 
         local_to_blocks
     }
-    fn construct_dominance_tree(body: &Body<'_>) -> HashMap<BasicBlock, Vec<BasicBlock>> {
+    pub(crate) fn construct_dominance_tree(body: &Body<'_>) -> HashMap<BasicBlock, Vec<BasicBlock>> {
         let mut dom_tree: HashMap<BasicBlock, Vec<BasicBlock>> = HashMap::new();
         let dominators = body.basic_blocks.dominators();
         for (block, _) in body.basic_blocks.iter_enumerated() {
@@ -255,7 +614,7 @@ impl<'tcx> SSATransformer<'tcx> {
 
         dom_tree
     }
-    fn compute_dominance_frontier(
+    pub(crate) fn compute_dominance_frontier(
         body: &Body<'_>,
         dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
     ) -> HashMap<BasicBlock, HashSet<BasicBlock>> {
@@ -266,17 +625,24 @@ impl<'tcx> SSATransformer<'tcx> {
             dominance_frontier.entry(block).or_default();
         }
 
-        // 遍历每个块
+        // 遍历每个块，不可达的块没有直接支配者，既不在支配前沿中，也不贡献支配前沿
         for (block, block_data) in body.basic_blocks.iter_enumerated() {
             // 如果块有多个前驱，可能会出现在支配前沿
             if (predecessors[block].len() > 1) {
-                let preds = body.basic_blocks.predecessors()[block].clone();
-
-                for &pred in &preds {
+                let Some(idom) = dominators.immediate_dominator(block) else {
+                    continue;
+                };
+                for &pred in predecessors[block].iter() {
+                    if !dominators.is_reachable(pred) {
+                        continue;
+                    }
                     let mut runner = pred;
-                    while runner != dominators.immediate_dominator(block).unwrap() {
+                    while runner != idom {
                         dominance_frontier.entry(runner).or_default().insert(block);
-                        runner = dominators.immediate_dominator(runner).unwrap();
+                        match dominators.immediate_dominator(runner) {
+                            Some(next) => runner = next,
+                            None => break,
+                        }
                     }
                 }
             }
@@ -284,36 +650,19 @@ impl<'tcx> SSATransformer<'tcx> {
 
         dominance_frontier
     }
+    /// 支配树的先序遍历，从 START_BLOCK 出发，用显式栈代替递归
     fn depth_first_search_preorder(
         dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
     ) -> Vec<BasicBlock> {
-        let mut visited: HashSet<BasicBlock> = HashSet::new();
         let mut preorder = Vec::new();
-
-        fn dfs(
-            node: BasicBlock,
-            dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
-            visited: &mut HashSet<BasicBlock>,
-            preorder: &mut Vec<BasicBlock>,
-        ) {
-            if visited.insert(node) {
-                // 先加入前序结果
-                preorder.push(node);
-
-                // 遍历当前节点的子节点
-                if let Some(children) = dom_tree.get(&node) {
-                    for &child in children {
-                        dfs(child, dom_tree, visited, preorder);
-                    }
-                }
+        let mut stack = vec![START_BLOCK];
+        while let Some(node) = stack.pop() {
+            preorder.push(node);
+            // 逆序压栈，使子节点按编号顺序出栈
+            if let Some(children) = dom_tree.get(&node) {
+                stack.extend(children.iter().rev());
             }
         }
-
-        // 开始从支配树的任意一个根节点进行 DFS
-        if let Some(&start_node) = dom_tree.keys().next() {
-            dfs(start_node, dom_tree, &mut visited, &mut preorder);
-        }
-
         preorder
     }
     pub fn insert_phi_statment(&mut self) {
@@ -322,11 +671,23 @@ impl<'tcx> SSATransformer<'tcx> {
         for bb in self.body.borrow().basic_blocks.indices() {
             phi_functions.insert(bb, HashSet::new());
         }
+        // 半剪枝和剪枝模式都需要每个块向上暴露的使用，剪枝模式还需要块入口的活跃变量
+        let (upward_exposed, live_in) = match self.options.phi_placement {
+            PhiPlacement::Minimal => (None, None),
+            PhiPlacement::SemiPruned => {
+                let (upward_exposed, _) = Self::block_uses_and_defs(&self.body.borrow());
+                let global_names: HashSet<Local> = upward_exposed.into_values().flatten().collect();
+                (Some(global_names), None)
+            }
+            PhiPlacement::Pruned => (None, Some(Self::compute_live_in(&self.body.borrow()))),
+        };
         let variables: Vec<Local> = self
             .local_assign_blocks
             .iter()
             .filter(|(_, blocks)| blocks.len() >= 2) // 只保留基本块数量大于等于 2 的条目
             .map(|(&local, _)| local) // 提取 Local
+            .filter(|&local| self.is_renamable(local))
+            .filter(|local| upward_exposed.as_ref().map_or(true, |names| names.contains(local)))
             .collect();
         print!("{:?}", variables);
         for var in &variables {
@@ -339,9 +700,17 @@ impl<'tcx> SSATransformer<'tcx> {
                     if let Some(df_blocks) = self.df.get(&block) {
                         for &df_block in df_blocks {
                             if !processed.contains(&df_block) {
+                                // 剪枝模式下变量在汇合块入口不活跃时不需要 phi
+                                if let Some(live_in) = &live_in
+                                    && !live_in[&df_block].contains(var)
+                                {
+                                    processed.insert(df_block);
+                                    continue;
+                                }
                                 phi_functions.get_mut(&df_block).unwrap().insert(*var);
                                 processed.insert(df_block);
-                                if self.local_assign_blocks[var].contains(&df_block) {
+                                // phi 本身也是一次定义，原本没有定义该变量的块需要继续传播
+                                if !self.local_assign_blocks[var].contains(&df_block) {
                                     worklist.push_back(df_block);
                                 }
                             }
@@ -350,37 +719,261 @@ impl<'tcx> SSATransformer<'tcx> {
                 }
             }
         }
-        for (block, vars) in phi_functions {
+
+        // 按块号和变量号排序，保证每次生成的 MIR 一致
+        let mut phi_blocks: Vec<(BasicBlock, Vec<Local>)> = phi_functions
+            .into_iter()
+            .filter(|(_, vars)| !vars.is_empty())
+            .map(|(block, vars)| {
+                let mut vars: Vec<Local> = vars.into_iter().collect();
+                vars.sort();
+                (block, vars)
+            })
+            .collect();
+        phi_blocks.sort_by_key(|(block, _)| *block);
+
+        let mut binding = self.body.borrow_mut();
+        let span = binding.span;
+        for (block, vars) in phi_blocks {
+            // 每个操作数对应一个前驱，顺序与 predecessors() 保持一致
+            let predecessors = binding.basic_blocks.predecessors()[block].clone();
+            let mut phis = Vec::with_capacity(vars.len());
             for var in vars {
-                let decl = self.body.borrow().local_decls[var].clone();
-                // let new_var = self.body.local_decls.push(decl);
-                let predecessors = self.body.borrow().basic_blocks.predecessors()[block].clone();
-
-                // 构造元组元素，使用占位变量
-                let mut operands = IndexVec::with_capacity(predecessors.len());
-                for _ in 0..predecessors.len() {
-                    operands.push(Operand::Copy(Place::from(var)));
-                } // 创建 phi 语句
-                let phi_stmt = Statement {
-                    source_info: SourceInfo::outermost(self.body.borrow().span),
-                    kind: StatementKind::Assign(Box::new((
-                        Place::from(var), // 左值是变量
-                        Rvalue::Aggregate(
-                            Box::new(AggregateKind::Tuple), // 元组类型
-                            operands,
-                        ),
-                    ))),
-                };
+                // 操作数先使用占位变量，重命名阶段再替换为各前驱的到达定义；
+                // 重命名不访问不可达的前驱，它们的操作数保持占位变量，这条边在运行时不会被执行
+                phis.push(PhiNode {
+                    local: var,
+                    dest: var,
+                    operands: predecessors
+                        .iter()
+                        .map(|&pred| (pred, Operand::Copy(Place::from(var))))
+                        .collect(),
+                });
+                self.local_assign_blocks
+                    .entry(var)
+                    .or_insert_with(HashSet::new)
+                    .insert(block);
+            }
+
+            // 插入到基本块的开头
+            let phi_stmts: Vec<Statement<'tcx>> =
+                phis.iter().map(|phi| phi.to_statement(span)).collect();
+            binding.basic_blocks_mut()[block]
+                .statements
+                .splice(0..0, phi_stmts);
+            self.phi_functions.insert(block, phis);
+        }
+    }
+    /// 在比较驱动的 SwitchInt 的各个后继块开头插入 sigma 函数（e-SSA）
+    ///
+    /// 需要在 `insert_phi_statment` 之前调用，sigma 作为新的定义参与 phi 的放置。
+    /// `new` 已经拆分了指向汇合块的分支边，每个后继块只有这一个前驱，
+    /// sigma 的新版本恰好对应这一条分支边。
+    ///
+    /// 判断块中对被比较变量的 `StorageDead` 移到各后继块的 sigma 之后，
+    /// 保证 sigma 读取时变量仍然存活。
+    pub fn insert_sigma_statment(&mut self) {
+        let mut binding = self.body.borrow_mut();
+        let span = binding.span;
+        let mut sigma_functions: Vec<(BasicBlock, SigmaNode)> = Vec::new();
+        let mut storage_dead: Vec<(BasicBlock, Local, Vec<BasicBlock>)> = Vec::new();
+        for (bb, data) in binding.basic_blocks.iter_enumerated() {
+            let TerminatorKind::SwitchInt { discr, targets } = &data.terminator().kind else {
+                continue;
+            };
+            let Some(discr) = discr.place().and_then(|place| place.as_local()) else {
+                continue;
+            };
+            let mut vars = Self::compared_locals(data, discr);
+            // 取地址变量不重命名，也不需要 sigma；单赋值变量仍然需要 sigma 来区分分支
+            vars.retain(|&var| {
+                var != RETURN_PLACE
+                    && !self.address_taken.contains(&var)
+                    && !self.partial_writes.contains(&var)
+            });
+            if vars.is_empty() {
+                continue;
+            }
+            let predecessors = &binding.basic_blocks.predecessors();
+            let edges = targets
+                .iter()
+                .map(|(value, target)| (Some(value), target))
+                .chain(std::iter::once((None, targets.otherwise())));
+            for (value, target) in edges {
+                debug_assert_eq!(predecessors[target].len(), 1);
+                for &var in &vars {
+                    sigma_functions.push((
+                        target,
+                        SigmaNode {
+                            local: var,
+                            dest: var,
+                            source: var,
+                            switch_block: bb,
+                            value,
+                        },
+                    ));
+                }
+            }
+            for &var in &vars {
+                let dead = data.statements.iter().any(
+                    |stmt| matches!(stmt.kind, StatementKind::StorageDead(local) if local == var),
+                );
+                if dead {
+                    storage_dead.push((bb, var, targets.all_targets().to_vec()));
+                }
+            }
+        }
 
-                // 插入到基本块的开头
-                // let mut binding = self.body.borrow_mut();
+        for (target, sigma) in sigma_functions {
+            let sigmas = self.sigma_functions.entry(target).or_default();
+            // 占位语句 `x = copy x`，重命名后变为 `x_new = copy x_old`
+            let sigma_stmt = Statement {
+                source_info: SourceInfo::outermost(span),
+                kind: StatementKind::Assign(Box::new((
+                    Place::from(sigma.local),
+                    Rvalue::Use(Operand::Copy(Place::from(sigma.local))),
+                ))),
+            };
+            binding.basic_blocks_mut()[target]
+                .statements
+                .insert(sigmas.len(), sigma_stmt);
+            self.local_assign_blocks
+                .entry(sigma.local)
+                .or_insert_with(HashSet::new)
+                .insert(target);
+            // 有了 sigma 之后变量不再是单赋值的，需要正常重命名
+            self.ssa_locals.remove(&sigma.local);
+            sigmas.push(sigma);
+        }
 
-                // binding.basic_blocks_mut()[block]
-                //     .statements
-                //     .insert(0, phi_stmt);
-                // drop(binding);
+        for (bb, var, targets) in storage_dead {
+            let source_info = binding.basic_blocks[bb].terminator().source_info;
+            binding.basic_blocks_mut()[bb].statements.retain(
+                |stmt| !matches!(stmt.kind, StatementKind::StorageDead(local) if local == var),
+            );
+            for target in targets {
+                let index = self.sigma_functions[&target].len();
+                binding.basic_blocks_mut()[target].statements.insert(
+                    index,
+                    Statement {
+                        source_info,
+                        kind: StatementKind::StorageDead(var),
+                    },
+                );
+            }
+        }
+    }
+    /// 找出 `discr = cmp(a, b)` 中被比较的变量
+    ///
+    /// 被 move 的临时变量沿块内的 `tmp = copy x` 追溯到源变量，分支之后真正被使用的是 x。
+    fn compared_locals(data: &BasicBlockData<'tcx>, discr: Local) -> Vec<Local> {
+        let mut locals = Vec::new();
+        let Some((op1, op2)) = data.statements.iter().rev().find_map(|stmt| match &stmt.kind {
+            StatementKind::Assign(box (
+                lhs,
+                Rvalue::BinaryOp(
+                    BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge | BinOp::Eq | BinOp::Ne,
+                    box (op1, op2),
+                ),
+            )) if lhs.as_local() == Some(discr) => Some((op1, op2)),
+            _ => None,
+        }) else {
+            return locals;
+        };
+        for operand in [op1, op2] {
+            let mut operand = operand;
+            loop {
+                match operand {
+                    Operand::Copy(place) => {
+                        if let Some(local) = place.as_local()
+                            && !locals.contains(&local)
+                        {
+                            locals.push(local);
+                        }
+                        break;
+                    }
+                    Operand::Move(place) => {
+                        let Some(tmp) = place.as_local() else {
+                            break;
+                        };
+                        let source = data.statements.iter().rev().find_map(|stmt| match &stmt.kind {
+                            StatementKind::Assign(box (lhs, Rvalue::Use(source)))
+                                if lhs.as_local() == Some(tmp) =>
+                            {
+                                Some(source)
+                            }
+                            _ => None,
+                        });
+                        match source {
+                            Some(source) => operand = source,
+                            None => break,
+                        }
+                    }
+                    Operand::Constant(_) => break,
+                }
             }
         }
+        locals
+    }
+    /// 计算每个块向上暴露的使用（块内先使用、后定义）和块内的定义
+    fn block_uses_and_defs(
+        body: &Body<'tcx>,
+    ) -> (
+        HashMap<BasicBlock, HashSet<Local>>,
+        HashMap<BasicBlock, HashSet<Local>>,
+    ) {
+        let mut upward_exposed: HashMap<BasicBlock, HashSet<Local>> = HashMap::new();
+        let mut defs: HashMap<BasicBlock, HashSet<Local>> = HashMap::new();
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            let ue = upward_exposed.entry(bb).or_default();
+            let def = defs.entry(bb).or_default();
+            // 同一条语句中先处理使用再处理定义，`x = x + 1` 中的 x 是向上暴露的
+            let mut visit = |collector: LiveCollector| {
+                for local in collector.uses {
+                    if !def.contains(&local) {
+                        ue.insert(local);
+                    }
+                }
+                def.extend(collector.defs);
+            };
+            for (statement_index, statement) in data.statements.iter().enumerate() {
+                let mut collector = LiveCollector::default();
+                collector.visit_statement(
+                    statement,
+                    Location {
+                        block: bb,
+                        statement_index,
+                    },
+                );
+                visit(collector);
+            }
+            let mut collector = LiveCollector::default();
+            collector.visit_terminator(data.terminator(), body.terminator_loc(bb));
+            visit(collector);
+        }
+        (upward_exposed, defs)
+    }
+    /// 反向数据流迭代求每个块入口处活跃的变量
+    fn compute_live_in(body: &Body<'tcx>) -> HashMap<BasicBlock, HashSet<Local>> {
+        let (upward_exposed, defs) = Self::block_uses_and_defs(body);
+        let mut live_in: HashMap<BasicBlock, HashSet<Local>> = upward_exposed.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (bb, data) in body.basic_blocks.iter_enumerated().rev() {
+                let mut live: HashSet<Local> = HashSet::new();
+                for succ in data.terminator().successors() {
+                    live.extend(live_in[&succ].iter().copied());
+                }
+                live.retain(|local| !defs[&bb].contains(local));
+                live.extend(upward_exposed[&bb].iter().copied());
+                if live.len() != live_in[&bb].len() {
+                    live_in.insert(bb, live);
+                    changed = true;
+                }
+            }
+        }
+        live_in
     }
     fn extract_cfg_from_predecessors(body: &Body<'_>) -> HashMap<BasicBlock, Vec<BasicBlock>> {
         let mut cfg: HashMap<BasicBlock, Vec<BasicBlock>> = HashMap::new();
@@ -397,385 +990,634 @@ impl<'tcx> SSATransformer<'tcx> {
     }
     fn print_dominance_tree(
         dom_tree: &HashMap<BasicBlock, Vec<BasicBlock>>,
-        current: BasicBlock,
+        root: BasicBlock,
         depth: usize,
     ) {
-        // 打印当前块
-        println!("\n{}{:?}", "  ".repeat(depth), current);
-
-        // 遍历并递归打印子节点
-        if let Some(children) = dom_tree.get(&current) {
-            for &child in children {
-                Self::print_dominance_tree(dom_tree, child, depth + 1);
+        let mut stack = vec![(root, depth)];
+        while let Some((current, depth)) = stack.pop() {
+            // 打印当前块
+            println!("\n{}{:?}", "  ".repeat(depth), current);
+            if let Some(children) = dom_tree.get(&current) {
+                stack.extend(children.iter().rev().map(|&child| (child, depth + 1)));
             }
         }
     }
-    pub fn is_phi_statement(statement: &Statement<'tcx>) -> bool {
-        match &statement.kind {
-            StatementKind::Assign(box (lhs, rhs)) => {
-                // 1. 检查左值是 Local，且右值是 Aggregate 类型
-                return matches!(rhs, Rvalue::Aggregate(_, _));
-            }
-            _ => {}
-        }
-        false
+    /// phi 只存在于基本块开头，由 `phi_functions` 记录
+    pub fn is_phi_statement(&self, location: Location) -> bool {
+        self.phi_at(location).is_some()
     }
     /// 主算法：执行 SSA 变量重命名
+    ///
+    /// 按支配树先序遍历基本块：每条语句先把使用替换为到达定义，再为定义创建新版本；
+    /// 处理完终结符后，把当前的到达定义填入后继块 phi 中对应本块的操作数。
     pub fn rename_variables(&mut self) {
         // 初始化每个变量的 reachingDef
         for local in self.body.borrow().local_decls.indices() {
             self.reaching_def.insert(local, None);
         }
         self.local_defination_block = Self::map_locals_to_definition_block(&self.body.borrow());
-        print!("%%%%{:?}%%%%", self.reaching_def);
-        print!(
-            "\n local_defination_block after phi {:?}",
-            self.local_defination_block
-        );
+        // 重命名之前的变量都是版本 0
+        let original: Vec<(Local, Span)> = self
+            .body
+            .borrow()
+            .local_decls
+            .iter_enumerated()
+            .map(|(local, decl)| (local, decl.source_info.span))
+            .collect();
+        for (local, span) in original {
+            let version = SSAVersion {
+                local,
+                version: 0,
+                name: self.debug_names.get(&local).cloned(),
+                def: None,
+                span,
+                position: self.source_position(span),
+            };
+            self.versions.insert(local, version);
+        }
 
+        let body = self.body.clone();
+        let mut body = body.borrow_mut();
         // 深度优先先序遍历支配树
         for bb in Self::depth_first_search_preorder(&self.dom_tree) {
-            self.process_basic_block(bb);
+            self.process_basic_block(&mut body, bb);
         }
+        self.memory_ssa = Some(MemorySSA::new(self.tcx, &body, &self.phi_functions));
     }
 
-    /// 处理单个基本块
-    fn process_basic_block(&mut self, bb: BasicBlock) {
-        // 获取基本块的可变引用
-        let len = self.body.borrow().basic_blocks[bb].statements.len();
-        for i in 0..len {
-            self.rename_statement(i, bb);
+    /// 校验重命名后的 body 是否满足 SSA 形式，返回发现的所有问题
+    pub fn verify(&self) -> Vec<SSAViolation> {
+        SSAVerifier::new(
+            &self.body.borrow(),
+            &self.phi_functions,
+            self.unrenamed_locals(),
+        )
+        .verify()
+    }
+
+    /// 消去 phi，得到可以交还给 rustc 的 MIR
+    pub fn destruct(&self) -> Body<'tcx> {
+        let renamed = self
+            .versions
+            .iter()
+            .filter(|(_, version)| self.is_renamable(version.local))
+            .map(|(&local, _)| local)
+            .collect();
+        let body =
+            SSADestructor::new(&self.body.borrow(), &self.phi_functions, renamed).destruct();
+        if self.options.verify {
+            let violations = OutOfSSAVerifier::new(&body, &self.phi_functions).verify();
+            assert!(
+                violations.is_empty(),
+                "invalid MIR after SSA destruction of {:?}:\n{}",
+                self.def_id,
+                violations
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
         }
+        body
+    }
+
+    /// 输出消去 phi 之后的 MIR
+    pub fn print_out_of_ssa_mir(&self) {
+        let dir_path = "ssa_mir";
+        let mir_file_path = format!("{}/out_ssa_mir_{:?}.txt", dir_path, self.def_id);
+        let mut file = File::create(&mir_file_path).unwrap();
+        let mut w = io::BufWriter::new(&mut file);
+        let options = PrettyPrintMirOptions::from_cli(self.tcx);
+        write_mir_fn(self.tcx, &self.destruct(), &mut |_, _| Ok(()), &mut w, options).unwrap();
+    }
 
-        // if let Some(terminator) = &mut block.terminator {
-        //     self.rename_terminator(terminator);
+    /// 返回值 `_0` 固定不改名；取地址变量可能经由指针被修改，留给内存 SSA；
+    /// 被就地部分写入的变量不改名；rustc 已经认定为单赋值的变量保留原名
+    fn is_renamable(&self, local: Local) -> bool {
+        local != RETURN_PLACE
+            && !self.address_taken.contains(&local)
+            && !self.partial_writes.contains(&local)
+            && !self.ssa_locals.contains(&local)
+    }
 
-        let successors: Vec<_> = self.body.borrow().basic_blocks[bb]
-            .terminator()
-            .successors()
-            .collect();
-        for succ_bb in successors {
-            // self.process_phi_functions(succ_bb);
+    /// `local` 是否经由投影或 SetDiscriminant/Deinit 被就地写入，这样的变量没有重命名
+    pub fn is_partially_written(&self, local: Local) -> bool {
+        self.partial_writes.contains(&local)
+    }
+
+    /// 不参与重命名、也不受 SSA 校验约束的变量：取地址变量和被就地部分写入的变量
+    pub fn unrenamed_locals(&self) -> HashSet<Local> {
+        self.address_taken
+            .union(&self.partial_writes)
+            .copied()
+            .collect()
+    }
+
+    fn collect_partial_writes(body: &Body<'tcx>) -> HashSet<Local> {
+        let mut collector = PartialWriteCollector::default();
+        collector.visit_body(body);
+        collector.locals
+    }
+
+    /// 按 `SsaLocals::copy_classes` 把每个拷贝等价类合并到代表变量上
+    ///
+    /// 与 rustc 的 CopyProp 相同：使用替换为代表变量，`move` 改为 `copy`，
+    /// 被合并变量的存储标记以及变成自拷贝的赋值改为 `Nop`。
+    fn merge_copy_classes(tcx: TyCtxt<'tcx>, body: &mut Body<'tcx>, ssa: &SsaLocals) {
+        let copy_classes = ssa.copy_classes();
+        if copy_classes.iter_enumerated().all(|(local, &head)| local == head) {
+            return;
+        }
+        let mut merged: HashSet<Local> = HashSet::new();
+        for (local, &head) in copy_classes.iter_enumerated() {
+            if local != head {
+                merged.insert(local);
+                merged.insert(head);
+            }
+        }
+        let mut replacer = CopyClassReplacer {
+            tcx,
+            copy_classes,
+            merged: &merged,
+        };
+        for (bb, data) in body
+            .basic_blocks
+            .as_mut_preserves_cfg()
+            .iter_enumerated_mut()
+        {
+            replacer.visit_basic_block_data(bb, data);
+        }
+        for var_debug_info in body.var_debug_info.iter_mut() {
+            replacer.visit_var_debug_info(var_debug_info);
         }
     }
 
-    /// 处理后继块中的 φ 函数
-    fn process_phi_functions(&mut self, bb: BasicBlock) {
-        let mut binding = self.body.borrow_mut();
-        let block = binding.basic_blocks_mut();
-        let mut block = &mut block[bb];
-
-        // 遍历 phi 变量
-        // for statement in block.statements.iter_mut() {
-        //     if let StatementKind::Assign(box (place, rvalue)) = &mut statement.kind {
-        //         // 仅处理 Aggregate 类型
-        //         if let Rvalue::Aggregate(_, operands) = rvalue {
-        //             for operand in operands.iter_mut() {
-        //                 if let Operand::Copy(src) | Operand::Move(src) = operand {
-        //                     if let Some(local) = src.as_local() {
-        //                         if let Some(def_stack) = self.reaching_def.get(&local) {
-        //                             if let Some(current_def) = def_stack.last() {
-        //                                 *src = Place::from(*current_def);
-        //                             }
-        //                         }
-        //                     }
-        //                 }
-        //             }
-        //         }
-        //     }
-        // }
-        for statement in &mut block.statements {
-            if let StatementKind::Assign(box (place, rvalue)) = &mut statement.kind {
-                if let Rvalue::Aggregate(_, operands) = rvalue {
-                    let mut unique_local: Option<Local> = None;
-                    for operand in operands.iter_mut() {
-                        if let Operand::Copy(src) | Operand::Move(src) = operand {
-                            if let Some(local) = src.as_local() {
-                                // 获取最新的 reaching definition
-                                unique_local = Some(local);
-                                if let Some(def_stack) = self.reaching_def.get(&local) {
-                                    if let Some(&latest_def) = def_stack.into_iter().last() {
-                                        *src = Place::from(latest_def); // 替换变量使用
-                                    }
-                                }
-                            }
-                        }
-                    }
+    /// 处理单个基本块
+    fn process_basic_block(&mut self, body: &mut Body<'tcx>, bb: BasicBlock) {
+        let phi_count = self.phi_functions.get(&bb).map_or(0, Vec::len);
+        let len = body.basic_blocks[bb].statements.len();
+        for statement_index in 0..len {
+            if statement_index < phi_count {
+                self.rename_phi_def(body, bb, statement_index);
+            } else {
+                self.rename_statement(body, statement_index, bb);
+                self.sync_sigma(body, bb, statement_index - phi_count);
+            }
+        }
+        let unwind_done = self.rename_terminator(body, bb);
 
-                    // 更新 reaching_def，使 `place` 绑定到新的变量版本
-                    if let Some(new_local) = place.as_local() {
-                        self.reaching_def
-                            .insert(unique_local.unwrap(), Some(new_local));
-                    }
+        let mut successors: Vec<BasicBlock> =
+            body.basic_blocks[bb].terminator().successors().collect();
+        successors.sort();
+        successors.dedup();
+        successors.retain(|&succ| Some(succ) != unwind_done);
+        for succ_bb in successors {
+            self.process_phi_functions(body, bb, succ_bb);
+        }
+    }
+
+    /// 处理后继块中的 φ 函数：把来自 `pred` 的操作数替换为当前的到达定义
+    fn process_phi_functions(&mut self, body: &mut Body<'tcx>, pred: BasicBlock, bb: BasicBlock) {
+        let Some(mut phis) = self.phi_functions.remove(&bb) else {
+            return;
+        };
+        for (i, phi) in phis.iter_mut().enumerate() {
+            self.update_reachinf_def(&phi.local, &pred);
+            let reaching = self.reaching_def[&phi.local].unwrap_or(phi.local);
+            for (from, operand) in phi.operands.iter_mut() {
+                if *from == pred {
+                    *operand = Operand::Copy(Place::from(reaching));
                 }
             }
+            body.basic_blocks_mut()[bb].statements[i] = phi.to_statement(body.span);
         }
+        self.phi_functions.insert(bb, phis);
     }
 
-    /// 创建一个新的变量版本
-
-    // fn create_fresh_variable(&self, local: Local, body: &mut Body<'tcx>) -> Local {
-    //     let new_local = body.local_decls.push(body.local_decls[local].clone());
-    //     new_local
-    // }
-    pub fn rename_statement(&mut self, i: usize, bb: BasicBlock) {
-        // match self.body.try_borrow_mut() {
-        //     Ok(mut borrow2) => {
-        //         // *borrow2 += 1;
-        //         // println!("rename_statement Borrow succeeded: {:?}", borrow2);
-        //         println!("rename_statement Borrow succeeded: ");
-        //     }
-        //     Err(_) => {
-        //         println!("rename_statement Borrow failed: already mutably borrowed!");
-        //     }
-        // }
-        // for statement in self.body.clone().borrow_mut().basic_blocks_mut()[bb]
-        //     .statements
-        //     .iter_mut()
-        for statement in self.body.clone().borrow().basic_blocks[bb]
-            .statements
-            .iter()
+    /// 重命名后把 sigma 语句的左右两侧记回 `sigma_functions`
+    fn sync_sigma(&mut self, body: &Body<'tcx>, bb: BasicBlock, i: usize) {
+        let Some(sigma) = self.sigma_functions.get_mut(&bb).and_then(|s| s.get_mut(i)) else {
+            return;
+        };
+        let phi_count = self.phi_functions.get(&bb).map_or(0, Vec::len);
+        if let StatementKind::Assign(box (place, Rvalue::Use(operand))) =
+            &body.basic_blocks[bb].statements[phi_count + i].kind
         {
-            // match self.body.try_borrow_mut() {
-            //     Ok(mut borrow2) => {
-            //         // *borrow2 += 1;
-            //         // println!("rename_statement Borrow succeeded: {:?}", borrow2);
-            //         println!("rename_statement Borrow succeeded: ");
-            //     }
-            //     Err(_) => {
-            //         println!("rename_statement Borrow failed: already mutably borrowed!");
-            //     }
-            // }
-            // // let rc_stat = Rc::new(RefCell::new(statement));
-            let is_phi = Self::is_phi_statement(statement);
-            match &statement.kind {
-                // 1. 赋值语句: 变量使用（右值），变量定义（左值）
-                StatementKind::Assign(box (place, rvalue)) => {
-                    {
-                        if !is_phi {
-                            // match self.body.try_borrow() {
-                            //     Ok(mut borrow2) => {
-                            //         // *borrow2 += 1;
-                            //         // println!("rename_statement Borrow succeeded: {:?}", borrow2);
-                            //         println!("rename_statement Borrow succeeded: ");
-                            //     }
-                            //     Err(_) => {
-                            //         println!(
-                            //             "rename_statement Borrow failed: already mutably borrowed!"
-                            //         );
-                            //     }
-                            // }
-
-                            self.update_reachinf_def(&place.local, &bb);
-                            // self.replace_rvalue(rvalue);
-                            self.rename_def(RefCell::new(*place));
-                        } else {
-                            self.update_reachinf_def(&place.local, &bb);
-                            self.rename_def(RefCell::new(*place));
-
-                            //每个定义生成的变量
-                            // self.replace_place(place,rc_stat.clone());
-                        }
-                    }
-                }
-                // 2. FakeRead: 变量使用
-                // StatementKind::FakeRead(_, place)
-                StatementKind::Deinit(place) | StatementKind::SetDiscriminant { place, .. } => {
-                    // let place_mut = unsafe { &mut *(place as *const _ as *mut _) };
-
-                    // self.replace_place(place.as_mut());
-                }
-                // 3. StorageLive: 变量定义
-                StatementKind::StorageLive(local) => {
-                    // self.rename_local_def(*local);
-                }
-                // 4. StorageDead: 变量使用
-                StatementKind::StorageDead(local) => {
-                    // self.replace_local(local);
-                }
-                _ => {}
-            }
-        }
-    }
-
-    fn rename_terminator(&mut self, terminator: &mut Terminator<'tcx>) {
-        // match &mut terminator.kind {
-        //     // 1. 函数调用: 参数使用，返回值定义
-        //     TerminatorKind::Call { args, destination, .. } => {
-        //         for operand in args {
-        //             self.replace_operand(operand);
-        //         }
-        //         if let Some((place, _)) = destination {
-        //             self.rename_def(place);
-        //         }
-        //     }
-        //     // 2. 断言: 变量使用
-        //     TerminatorKind::Assert { cond, .. } => {
-        //         self.replace_operand(cond);
-        //     }
-        //     // 3. Drop: 变量使用
-        //     TerminatorKind::Drop { place, .. } => {
-        //         self.replace_place(place);
-        //     }
-        //     // 4. SwitchInt: 变量使用
-        //     TerminatorKind::SwitchInt { discr, .. } => {
-        //         self.replace_operand(discr);
-        //     }
-        //     _ => {}
-        // }
-    }
-
-    fn replace_rvalue(&mut self, rvalue: &mut Rvalue<'tcx>) {
-        match rvalue {
-            Rvalue::Use(operand)
-            | Rvalue::Repeat(operand, _)
-            | Rvalue::UnaryOp(_, operand)
-            | Rvalue::Cast(_, operand, _)
-            | Rvalue::ShallowInitBox(operand, _) => {
-                self.replace_operand(operand);
-            }
-            Rvalue::BinaryOp(_, box (lhs, rhs)) | Rvalue::BinaryOp(_, box (lhs, rhs)) => {
-                self.replace_operand(lhs);
-                self.replace_operand(rhs);
-            }
-            Rvalue::Aggregate(_, operands) => {
-                for operand in operands {
-                    self.replace_operand(operand);
-                }
+            sigma.dest = place.local;
+            if let Some(source) = operand.place() {
+                sigma.source = source.local;
             }
-            _ => {}
         }
     }
 
-    fn replace_operand(&mut self, operand: &mut Operand<'tcx>) {
-        if let Operand::Copy(mut place) | Operand::Move(mut place) = operand {
-            self.replace_place(&mut place);
+    /// 为 phi 的左值创建新版本
+    fn rename_phi_def(&mut self, body: &mut Body<'tcx>, bb: BasicBlock, i: usize) {
+        let local = self.phi_functions[&bb][i].local;
+        let location = Location {
+            block: bb,
+            statement_index: i,
+        };
+        let new_local = self.rename_def(body, local, bb, location);
+        let phi = &mut self.phi_functions.get_mut(&bb).unwrap()[i];
+        phi.dest = new_local;
+        body.basic_blocks_mut()[bb].statements[i] = phi.to_statement(body.span);
+    }
+
+    pub fn rename_statement(&mut self, body: &mut Body<'tcx>, i: usize, bb: BasicBlock) {
+        let location = Location {
+            block: bb,
+            statement_index: i,
+        };
+        // 1. 先重命名所有使用（右值、投影中的下标、StorageLive/StorageDead 等）
+        let renames = {
+            let statement = &body.basic_blocks[bb].statements[i];
+            let mut collector = UseCollector::default();
+            collector.visit_statement(statement, location);
+            self.reaching_defs_for(&collector.uses, bb)
+        };
+        let statement = &mut body.basic_blocks_mut()[bb].statements[i];
+        UseRenamer {
+            tcx: self.tcx,
+            renames: &renames,
+        }
+        .visit_statement(statement, location);
+
+        // 2. 再为整体赋值的左值创建新版本；带投影的左值只是部分写入，
+        //    这样的变量在 `partial_writes` 中，不会被重命名
+        let def_local = match &statement.kind {
+            StatementKind::Assign(box (place, _)) => place.as_local(),
+            _ => None,
+        };
+        if let Some(local) = def_local {
+            let new_local = self.rename_def(body, local, bb, location);
+            if let StatementKind::Assign(box (place, _)) =
+                &mut body.basic_blocks_mut()[bb].statements[i].kind
+            {
+                *place = Place::from(new_local);
+            }
         }
     }
-    fn replace_place(&mut self, place: &mut Place<'tcx>) {
-        if let Some(reaching_local) = self.reaching_def.get(&place.local) {
-            if let Some(local) = reaching_local {
-                place.local = *local;
+
+    /// 返回已经在这里填写过 phi 操作数的清理块
+    fn rename_terminator(&mut self, body: &mut Body<'tcx>, bb: BasicBlock) -> Option<BasicBlock> {
+        let location = body.terminator_loc(bb);
+        // SwitchInt 的判别值、Assert 的条件与消息、Call 的函数与参数、Drop 的位置都是使用
+        let renames = {
+            let terminator = body.basic_blocks[bb].terminator();
+            let mut collector = UseCollector::default();
+            collector.visit_terminator(terminator, location);
+            self.reaching_defs_for(&collector.uses, bb)
+        };
+        let terminator = body.basic_blocks_mut()[bb].terminator_mut();
+        UseRenamer {
+            tcx: self.tcx,
+            renames: &renames,
+        }
+        .visit_terminator(terminator, location);
+
+        // 函数调用的返回值是定义，但只在正常返回边上成立
+        let (def_local, cleanup) = match &terminator.kind {
+            TerminatorKind::Call {
+                destination,
+                unwind,
+                ..
+            } => {
+                let cleanup = match unwind {
+                    UnwindAction::Cleanup(cleanup) => Some(*cleanup),
+                    _ => None,
+                };
+                (destination.as_local(), cleanup)
             }
+            _ => (None, None),
+        };
+        let local = def_local?;
+        // 展开边上看到的是调用之前的值，清理块的 phi 要在创建新版本之前填写
+        if let Some(cleanup) = cleanup {
+            self.process_phi_functions(body, bb, cleanup);
         }
+        let def_bb = Self::call_return_block(body, bb);
+        let new_local = self.rename_def(body, local, def_bb, location);
+        if let TerminatorKind::Call { destination, .. } =
+            &mut body.basic_blocks_mut()[bb].terminator_mut().kind
+        {
+            *destination = Place::from(new_local);
+        }
+        cleanup
     }
 
-    // fn rename_def(&mut self, place: UnsafeCell<&rustc_middle::mir::Place<'_>>) {
+    /// 调用的正常返回块有多个前驱时，在调用和返回块之间插入一个只有 `Goto` 的新块
+    ///
+    /// 返回值的定义放在新块上，只在这一条返回边上成立：既不支配清理块，
+    /// 也不会被返回块的其他前驱看到，返回块需要时由 phi 合并。
+    fn split_call_return_edges(body: &mut Body<'tcx>) {
+        let predecessors = body.basic_blocks.predecessors();
+        let edges: Vec<(BasicBlock, BasicBlock)> = body
+            .basic_blocks
+            .iter_enumerated()
+            .filter_map(|(bb, data)| match data.terminator().kind {
+                TerminatorKind::Call {
+                    target: Some(target),
+                    ..
+                } if predecessors[target].len() > 1 => Some((bb, target)),
+                _ => None,
+            })
+            .collect();
+        for (bb, target) in edges {
+            let new_block = Self::insert_goto_block(body, bb, target);
+            if let TerminatorKind::Call {
+                target: Some(target),
+                ..
+            } = &mut body.basic_blocks_mut()[bb].terminator_mut().kind
+            {
+                *target = new_block;
+            }
+        }
+    }
 
-    //     if let Some(local) = place.as_local() {
-    //         let new_local = self.create_fresh_variable(local);
+    /// 比较驱动的 SwitchInt 指向有多个前驱的块时，为这条分支边插入一个只有 `Goto` 的新块
+    ///
+    /// sigma 放在新块上，只在这一条分支边上成立；同一个块由多个判别值到达时每条边各拆一次。
+    fn split_sigma_edges(body: &mut Body<'tcx>) {
+        let predecessors = body.basic_blocks.predecessors();
+        let mut edges: Vec<(BasicBlock, usize, BasicBlock)> = Vec::new();
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            let TerminatorKind::SwitchInt { discr, targets } = &data.terminator().kind else {
+                continue;
+            };
+            let Some(discr) = discr.place().and_then(|place| place.as_local()) else {
+                continue;
+            };
+            if Self::compared_locals(data, discr).is_empty() {
+                continue;
+            }
+            for (i, &target) in targets.all_targets().iter().enumerate() {
+                if predecessors[target].len() > 1 {
+                    edges.push((bb, i, target));
+                }
+            }
+        }
+        for (bb, i, target) in edges {
+            let new_block = Self::insert_goto_block(body, bb, target);
+            if let TerminatorKind::SwitchInt { targets, .. } =
+                &mut body.basic_blocks_mut()[bb].terminator_mut().kind
+            {
+                targets.all_targets_mut()[i] = new_block;
+            }
+        }
+    }
 
-    //         // 获取 local 之前的值，并赋给 new_local
-    //         if let Some(&value) = self.reaching_def.get(&local) {
-    //             self.reaching_def.insert(new_local, value);
-    //         }
+    /// 新建一个跳转到 `target` 的空块，由调用者把 `bb` 的对应出边改指向它
+    fn insert_goto_block(body: &mut Body<'tcx>, bb: BasicBlock, target: BasicBlock) -> BasicBlock {
+        let source_info = body.basic_blocks[bb].terminator().source_info;
+        let is_cleanup = body.basic_blocks[target].is_cleanup;
+        body.basic_blocks_mut().push(BasicBlockData {
+            statements: Vec::new(),
+            terminator: Some(Terminator {
+                source_info,
+                kind: TerminatorKind::Goto { target },
+            }),
+            is_cleanup,
+        })
+    }
 
-    //         // 更新 local 的值为 new_local
-    //         self.reaching_def.insert(local, Some(new_local));
+    /// 调用返回值的定义块：正常返回的后继，没有后继的发散调用取调用所在块
+    ///
+    /// `split_call_return_edges` 之后返回块只有调用这一个前驱，
+    /// 把定义放在后继上，清理块中的使用就不会被认为受它支配。
+    fn call_return_block(body: &Body<'tcx>, bb: BasicBlock) -> BasicBlock {
+        match body.basic_blocks[bb].terminator().kind {
+            TerminatorKind::Call {
+                target: Some(target),
+                ..
+            } => target,
+            _ => bb,
+        }
+    }
 
-    //         // 更新 place
-    //         // *place = Place::from(new_local);
-    //     }
-    // }
-    fn rename_def(&mut self, place: RefCell<Place<'_>>) {
-        match self.body.try_borrow() {
-            Ok(mut borrow2) => {
-                // *borrow2 += 1;
-                // println!("rename_statement Borrow succeeded: {:?}", borrow2);
-                println!("rename_def Borrow succeeded: ");
+    /// 计算一组使用在 `bb` 处的到达定义，只返回需要改名的变量
+    fn reaching_defs_for(&mut self, uses: &[Local], bb: BasicBlock) -> HashMap<Local, Local> {
+        let mut renames = HashMap::new();
+        for &local in uses {
+            if !self.is_renamable(local) {
+                continue;
             }
-            Err(_) => {
-                println!("rename_def Borrow failed: already mutably borrowed!");
+            self.update_reachinf_def(&local, &bb);
+            if let Some(reaching) = self.reaching_def[&local] {
+                renames.insert(local, reaching);
             }
         }
-        let mut place_mut = place.borrow_mut(); // 获取可变借用
-        if let Some(local) = place_mut.as_local() {
-            let new_local = self.create_fresh_variable(local);
+        renames
+    }
 
-            // 获取 local 之前的值，并赋给 new_local
-            if let Some(&value) = self.reaching_def.get(&local) {
-                self.reaching_def.insert(new_local, value);
-            }
+    /// 为 `local` 在 `bb` 中的一次定义创建新版本，并更新 reachingDef 链，返回新变量
+    ///
+    /// `location` 是定义语句的位置，调用返回值的 `bb` 可能与它不同。
+    fn rename_def(
+        &mut self,
+        body: &mut Body<'tcx>,
+        local: Local,
+        bb: BasicBlock,
+        location: Location,
+    ) -> Local {
+        if !self.is_renamable(local) {
+            return local;
+        }
+        self.update_reachinf_def(&local, &bb);
+        let new_local = self.create_fresh_variable(body, local, bb, location);
 
-            // 更新 local 的值为 new_local
-            self.reaching_def.insert(local, Some(new_local));
+        // 获取 local 之前的值，并赋给 new_local
+        let value = self.reaching_def[&local];
+        self.reaching_def.insert(new_local, value);
 
-            // 更新 place
-            *place_mut = Place::from(new_local);
+        // 更新 local 的值为 new_local
+        self.reaching_def.insert(local, Some(new_local));
+        new_local
+    }
+
+    /// 创建一个新的变量版本，并记录它对应的源码变量
+    fn create_fresh_variable(
+        &mut self,
+        body: &mut Body<'tcx>,
+        local: Local,
+        bb: BasicBlock,
+        location: Location,
+    ) -> Local {
+        let new_local_decl = body.local_decls[local].clone();
+        let new_local = body.local_decls.push(new_local_decl);
+        self.local_defination_block.insert(new_local, bb);
+
+        let counter = self.version_counts.entry(local).or_insert(0);
+        *counter += 1;
+        let counter = *counter;
+        let span = body.source_info(location).span;
+        let version = SSAVersion {
+            local,
+            version: counter,
+            name: self.debug_names.get(&local).cloned(),
+            def: Some(location),
+            span,
+            position: self.source_position(span),
+        };
+        self.versions.insert(new_local, version);
+        self.version_defs.insert(location, new_local);
+        new_local
+    }
+
+    /// 支配树编号下的常数时间支配查询，块自身视为支配自身
+    pub fn dominates_(&self, def_bb: &BasicBlock, bb: &BasicBlock) -> bool {
+        // 同一个块内，定义先于之后的语句被处理
+        if def_bb == bb {
+            return true;
+        }
+        match (self.dom_numbering.get(def_bb), self.dom_numbering.get(bb)) {
+            (Some(&(def_pre, def_post)), Some(&(pre, post))) => def_pre <= pre && post <= def_post,
+            _ => false,
         }
     }
+    /// 沿 reachingDef 链回退，直到找到支配 `bb` 的版本
+    fn update_reachinf_def(&mut self, local: &Local, bb: &BasicBlock) {
+        let mut r = self.reaching_def[local];
+        while let Some(version) = r {
+            if self.dominates_(&self.local_defination_block[&version], bb) {
+                break;
+            }
+            r = self.reaching_def[&version];
+        }
+        if let Some(entry) = self.reaching_def.get_mut(local) {
+            *entry = r;
+        }
+    }
+}
+
+/// 定义点（整体赋值的左值、调用的返回值）不属于使用
+fn is_def_context(context: PlaceContext) -> bool {
+    matches!(
+        context,
+        PlaceContext::MutatingUse(MutatingUseContext::Store | MutatingUseContext::Call)
+    )
+}
 
-    fn rename_local_def(&mut self, place: &mut Place<'tcx>) {
-        let old_local = place.as_local().unwrap();
-        let new_local = self.create_fresh_variable(old_local);
-        print!("fuck!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
-        *place = Place::from(new_local);
+/// 收集经由投影或 SetDiscriminant/Deinit 被就地写入的变量
+#[derive(Default)]
+struct PartialWriteCollector {
+    locals: HashSet<Local>,
+}
 
-        // self.reaching_def
-        //     .entry(old_local)
-        //     .or_default()
-        //     .replace(Some(old_local));
+impl<'tcx> Visitor<'tcx> for PartialWriteCollector {
+    fn visit_place(&mut self, place: &Place<'tcx>, context: PlaceContext, location: Location) {
+        let is_partial = match context {
+            PlaceContext::MutatingUse(
+                MutatingUseContext::Store
+                | MutatingUseContext::Call
+                | MutatingUseContext::AsmOutput
+                | MutatingUseContext::Yield,
+            ) => !place.projection.is_empty(),
+            PlaceContext::MutatingUse(
+                MutatingUseContext::SetDiscriminant | MutatingUseContext::Deinit,
+            ) => true,
+            _ => false,
+        };
+        if is_partial {
+            self.locals.insert(place.local);
+        }
+        self.super_place(place, context, location);
     }
+}
+
+/// 收集语句或终结符中使用到的变量
+#[derive(Default)]
+struct UseCollector {
+    uses: Vec<Local>,
+}
 
-    fn replace_local(&self, local: &mut Local) {
-        if let Some(reaching_local) = self.reaching_def.get(local) {
-            // if let Some(latest) = stack {
-            //     *local = latest;
-            // }
+impl<'tcx> Visitor<'tcx> for UseCollector {
+    fn visit_local(&mut self, local: Local, context: PlaceContext, _location: Location) {
+        if !is_def_context(context) {
+            self.uses.push(local);
         }
     }
+}
 
-    fn create_fresh_variable(&mut self, local: Local) -> Local {
-        println!("Reference count: {}", Rc::strong_count(&self.body));
-        match self.body.try_borrow_mut() {
-            Ok(mut borrow2) => {
-                // *borrow2 += 1;
-                println!("create_fresh_variable Borrow succeeded: {:?}", borrow2);
-            }
-            Err(_) => {
-                println!("create_fresh_variable Borrow failed: already mutably borrowed!");
-            }
+/// 分别收集一条语句或终结符中的使用和整体定义，用于活跃变量分析
+#[derive(Default)]
+struct LiveCollector {
+    uses: Vec<Local>,
+    defs: Vec<Local>,
+}
+
+impl<'tcx> Visitor<'tcx> for LiveCollector {
+    fn visit_local(&mut self, local: Local, context: PlaceContext, _location: Location) {
+        if is_def_context(context) {
+            self.defs.push(local);
+        } else if context.is_use() {
+            self.uses.push(local);
         }
-        let mut binding = self.body.borrow_mut();
-        let new_local_decl = binding.local_decls[local].clone();
-        let new_local = binding.local_decls.push(new_local_decl);
-        new_local
     }
-    pub fn dominates_(&self, def_bb: &BasicBlock, bb: &BasicBlock) -> bool { 
-        // 使用一个集合来追踪所有被 def_bb 支配的基本块
-        let mut visited = HashSet::new();
+}
 
-        // 从 def_bb 出发，遍历其子树
-        let mut stack = self.dom_tree.get(def_bb).unwrap().clone();
-        while let Some(block) = stack.pop() {
-            if !visited.insert(block) {
-                continue;
-            }
+/// 把使用点的变量替换为 `renames` 中对应的版本
+struct UseRenamer<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    renames: &'a HashMap<Local, Local>,
+}
 
-            // 如果当前块是 bb，说明 def_bb 支配了 bb
-            if block == *bb {
-                return true;
-            }
+impl<'tcx> MutVisitor<'tcx> for UseRenamer<'_, 'tcx> {
+    fn tcx(&self) -> TyCtxt<'tcx> {
+        self.tcx
+    }
 
-            // 将所有子节点加入栈中，继续遍历
-            if let Some(children) = self.dom_tree.get(&block) {
-                stack.extend(children);
-            }
+    fn visit_local(&mut self, local: &mut Local, context: PlaceContext, _location: Location) {
+        if is_def_context(context) {
+            return;
+        }
+        if let Some(&new_local) = self.renames.get(local) {
+            *local = new_local;
         }
+    }
+}
 
-        false
+/// 把拷贝等价类中的变量替换为代表变量
+struct CopyClassReplacer<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    copy_classes: &'a IndexSlice<Local, Local>,
+    /// 所有非平凡等价类中的变量（包括代表变量）
+    merged: &'a HashSet<Local>,
+}
+
+impl<'tcx> MutVisitor<'tcx> for CopyClassReplacer<'_, 'tcx> {
+    fn tcx(&self) -> TyCtxt<'tcx> {
+        self.tcx
     }
-    fn update_reachinf_def(&mut self, local: &Local, bb: &BasicBlock) {
-        let def_bb = self.local_defination_block[local];
-        let mut r = self.reaching_def[local];
-        while !(self.dominates_(&def_bb, bb) || r == None) {
-            print!("r1:{:?}", r);
 
-            r = self.reaching_def[&r.unwrap()];
-            print!("r2:{:?}", r);
+    fn visit_local(&mut self, local: &mut Local, _context: PlaceContext, _location: Location) {
+        *local = self.copy_classes[*local];
+    }
+
+    fn visit_operand(&mut self, operand: &mut Operand<'tcx>, location: Location) {
+        // 代表变量之后可能还会被使用，不能再被 move 走
+        if let Operand::Move(place) = *operand
+            && !place.is_indirect_first_projection()
+            && self.merged.contains(&place.local)
+        {
+            *operand = Operand::Copy(place);
         }
-        if let Some(entry) = self.reaching_def.get_mut(local) {
-            *entry = r.clone();
+        self.super_operand(operand, location);
+    }
+
+    fn visit_statement(&mut self, statement: &mut Statement<'tcx>, location: Location) {
+        if let StatementKind::StorageLive(local) | StatementKind::StorageDead(local) =
+            statement.kind
+            && self.copy_classes[local] != local
+        {
+            statement.make_nop();
+            return;
+        }
+        self.super_statement(statement, location);
+        if let StatementKind::Assign(box (lhs, Rvalue::Use(Operand::Copy(rhs) | Operand::Move(rhs)))) =
+            &statement.kind
+            && lhs == rhs
+        {
+            statement.make_nop();
         }
     }
 }
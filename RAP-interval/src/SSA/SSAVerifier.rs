@@ -0,0 +1,471 @@
+use rustc_data_structures::graph::dominators::Dominators;
+use rustc_middle::mir::visit::{MutatingUseContext, PlaceContext, Visitor};
+use rustc_middle::mir::*;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::SSATransformer::PhiNode;
+
+/// 变量的定义位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefSite {
+    /// 函数参数，在入口之前定义
+    Argument,
+    /// 语句中的整体赋值，或 phi / sigma
+    Statement(Location),
+    /// 函数调用的返回值，只在正常返回的 `target` 边上有定义
+    CallReturn {
+        call: BasicBlock,
+        target: Option<BasicBlock>,
+    },
+}
+
+/// 校验 SSA 形式时发现的问题，均带有出错的块和语句下标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SSAViolation {
+    /// 同一个变量被赋值了多次
+    MultipleAssignment {
+        local: Local,
+        first: DefSite,
+        second: Location,
+    },
+    /// 定义没有支配某个使用
+    UseNotDominated {
+        local: Local,
+        def: DefSite,
+        location: Location,
+    },
+    /// 使用了一个从未被定义的版本
+    UndefinedUse { local: Local, location: Location },
+    /// phi 的操作数与基本块的前驱不一一对应
+    PhiArityMismatch {
+        location: Location,
+        dest: Local,
+        operands: Vec<BasicBlock>,
+        predecessors: Vec<BasicBlock>,
+    },
+}
+
+impl fmt::Display for SSAViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SSAViolation::MultipleAssignment {
+                local,
+                first,
+                second,
+            } => write!(
+                f,
+                "{:?}: {:?} is assigned more than once (first definition: {:?})",
+                second, local, first
+            ),
+            SSAViolation::UseNotDominated {
+                local,
+                def,
+                location,
+            } => write!(
+                f,
+                "{:?}: use of {:?} is not dominated by its definition {:?}",
+                location, local, def
+            ),
+            SSAViolation::UndefinedUse { local, location } => {
+                write!(f, "{:?}: use of undefined version {:?}", location, local)
+            }
+            SSAViolation::PhiArityMismatch {
+                location,
+                dest,
+                operands,
+                predecessors,
+            } => write!(
+                f,
+                "{:?}: phi for {:?} has operands from {:?} but the predecessors are {:?}",
+                location, dest, operands, predecessors
+            ),
+        }
+    }
+}
+
+/// 检查 `SSATransformer` 输出的 body 是否满足 SSA 形式：
+/// 1. 每个变量只被赋值一次；
+/// 2. 定义支配它的所有使用（phi 的操作数在对应前驱的末尾被使用）；
+/// 3. 每个 phi 对每个前驱恰有一个操作数，顺序与 `predecessors()` 一致；
+/// 4. 不读取未定义的版本。
+///
+/// 经由投影或 SetDiscriminant/Deinit 的就地写入也算作对基变量的一次赋值。
+/// 返回值 `_0` 和 `unrenamed` 中的变量（取地址、被就地部分写入）不参与重命名，不做检查。
+pub struct SSAVerifier<'a, 'tcx> {
+    body: &'a Body<'tcx>,
+    phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+    unrenamed: HashSet<Local>,
+    defs: HashMap<Local, DefSite>,
+    violations: Vec<SSAViolation>,
+}
+
+impl<'a, 'tcx> SSAVerifier<'a, 'tcx> {
+    pub fn new(
+        body: &'a Body<'tcx>,
+        phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+        unrenamed: HashSet<Local>,
+    ) -> Self {
+        Self {
+            body,
+            phi_functions,
+            unrenamed,
+            defs: HashMap::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn verify(mut self) -> Vec<SSAViolation> {
+        self.collect_defs();
+        self.check_phis();
+        self.check_uses();
+        self.violations
+    }
+
+    fn phi_count(&self, bb: BasicBlock) -> usize {
+        self.phi_functions.get(&bb).map_or(0, Vec::len)
+    }
+
+    fn is_checked(&self, local: Local) -> bool {
+        local != RETURN_PLACE && !self.unrenamed.contains(&local)
+    }
+
+    fn record_def(&mut self, local: Local, site: DefSite, location: Location) {
+        if !self.is_checked(local) {
+            return;
+        }
+        if let Some(&first) = self.defs.get(&local) {
+            self.violations.push(SSAViolation::MultipleAssignment {
+                local,
+                first,
+                second: location,
+            });
+        } else {
+            self.defs.insert(local, site);
+        }
+    }
+
+    fn collect_defs(&mut self) {
+        let body = self.body;
+        for local in body.args_iter() {
+            self.defs.insert(local, DefSite::Argument);
+        }
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            for (statement_index, statement) in data.statements.iter().enumerate() {
+                let location = Location {
+                    block: bb,
+                    statement_index,
+                };
+                // 带投影的左值只写入一部分，但同样改变了基变量
+                let place = match &statement.kind {
+                    StatementKind::Assign(box (place, _)) => Some(place),
+                    StatementKind::SetDiscriminant { place, .. } | StatementKind::Deinit(place) => {
+                        Some(&**place)
+                    }
+                    _ => None,
+                };
+                if let Some(place) = place {
+                    self.record_def(place.local, DefSite::Statement(location), location);
+                }
+            }
+            if let TerminatorKind::Call {
+                destination,
+                target,
+                ..
+            } = &data.terminator().kind
+            {
+                let site = DefSite::CallReturn {
+                    call: bb,
+                    target: *target,
+                };
+                self.record_def(destination.local, site, body.terminator_loc(bb));
+            }
+        }
+    }
+
+    fn check_phis(&mut self) {
+        let phi_functions = self.phi_functions;
+        let predecessors = self.body.basic_blocks.predecessors();
+        let mut blocks: Vec<&BasicBlock> = phi_functions.keys().collect();
+        blocks.sort();
+        for &bb in blocks {
+            let preds: Vec<BasicBlock> = predecessors[bb].iter().copied().collect();
+            for (statement_index, phi) in phi_functions[&bb].iter().enumerate() {
+                let operands: Vec<BasicBlock> =
+                    phi.operands.iter().map(|(pred, _)| *pred).collect();
+                if operands != preds {
+                    self.violations.push(SSAViolation::PhiArityMismatch {
+                        location: Location {
+                            block: bb,
+                            statement_index,
+                        },
+                        dest: phi.dest,
+                        operands,
+                        predecessors: preds.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_uses(&mut self) {
+        let body = self.body;
+        let phi_functions = self.phi_functions;
+        let dominators = body.basic_blocks.dominators();
+        let mut uses = UseCollector::default();
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            if !dominators.is_reachable(bb) {
+                continue;
+            }
+            // phi 占位语句不是块内的使用，操作数在前驱的末尾检查
+            for (statement_index, statement) in
+                data.statements.iter().enumerate().skip(self.phi_count(bb))
+            {
+                uses.visit_statement(
+                    statement,
+                    Location {
+                        block: bb,
+                        statement_index,
+                    },
+                );
+            }
+            uses.visit_terminator(data.terminator(), body.terminator_loc(bb));
+        }
+        for (local, location) in uses.uses {
+            self.check_use(dominators, local, location, None);
+        }
+
+        let mut blocks: Vec<&BasicBlock> = phi_functions.keys().collect();
+        blocks.sort();
+        for &bb in blocks {
+            for phi in &phi_functions[&bb] {
+                for (pred, operand) in &phi.operands {
+                    if !dominators.is_reachable(*pred) {
+                        continue;
+                    }
+                    if let Some(place) = operand.place() {
+                        let location = body.terminator_loc(*pred);
+                        self.check_use(dominators, place.local, location, Some(bb));
+                    }
+                }
+            }
+        }
+    }
+
+    /// `edge_to` 不为空时，这是沿 `location.block -> edge_to` 这条边对 phi 操作数的使用
+    fn check_use(
+        &mut self,
+        dominators: &Dominators<BasicBlock>,
+        local: Local,
+        location: Location,
+        edge_to: Option<BasicBlock>,
+    ) {
+        if !self.is_checked(local) {
+            return;
+        }
+        let Some(&def) = self.defs.get(&local) else {
+            self.violations
+                .push(SSAViolation::UndefinedUse { local, location });
+            return;
+        };
+        let dominates = match def {
+            DefSite::Argument => true,
+            DefSite::Statement(def_loc) => {
+                if def_loc.block == location.block {
+                    def_loc.statement_index < location.statement_index
+                } else {
+                    dominators.dominates(def_loc.block, location.block)
+                }
+            }
+            DefSite::CallReturn { call, target } => match target {
+                // 返回值只在正常返回边上有定义
+                Some(target) if call == location.block => edge_to == Some(target),
+                Some(target) => dominators.dominates(target, location.block),
+                None => false,
+            },
+        };
+        if !dominates {
+            self.violations.push(SSAViolation::UseNotDominated {
+                local,
+                def,
+                location,
+            });
+        }
+    }
+}
+
+/// 消去 phi 之后的 MIR 中发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutOfSSAViolation {
+    /// phi 的占位语句没有被删除
+    PhiNotRemoved { location: Location, dest: Local },
+    /// phi 的拷贝不在只通向 phi 所在块的边上，会覆盖其他后继仍然需要的值
+    CopyNotOnEdge {
+        location: Location,
+        dest: Local,
+        phi_block: BasicBlock,
+    },
+    /// 普通边进入了清理块，或者从清理块回到了普通块
+    CleanupEdge { from: BasicBlock, to: BasicBlock },
+    /// 拷贝两端变量的类型不同
+    CopyTypeMismatch {
+        location: Location,
+        dest: Local,
+        source: Local,
+    },
+}
+
+impl fmt::Display for OutOfSSAViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutOfSSAViolation::PhiNotRemoved { location, dest } => {
+                write!(f, "{:?}: phi for {:?} was not removed", location, dest)
+            }
+            OutOfSSAViolation::CopyNotOnEdge {
+                location,
+                dest,
+                phi_block,
+            } => write!(
+                f,
+                "{:?}: copy into {:?} is not on an edge that leads only to {:?}",
+                location, dest, phi_block
+            ),
+            OutOfSSAViolation::CleanupEdge { from, to } => {
+                write!(
+                    f,
+                    "{:?} -> {:?} mixes cleanup and non-cleanup blocks",
+                    from, to
+                )
+            }
+            OutOfSSAViolation::CopyTypeMismatch {
+                location,
+                dest,
+                source,
+            } => write!(
+                f,
+                "{:?}: copy from {:?} into {:?} changes the type",
+                location, source, dest
+            ),
+        }
+    }
+}
+
+/// 检查 `SSADestructor` 输出的 MIR：
+/// 1. phi 都已删除，phi 定义的变量只由拷贝赋值；
+/// 2. 这些拷贝位于以 `Goto` 跳到 phi 所在块的前驱（或拆分边得到的中间块）末尾；
+/// 3. 清理块只经由展开边进入，且不会回到普通块；
+/// 4. 变量之间的拷贝不改变类型。
+pub struct OutOfSSAVerifier<'a, 'tcx> {
+    body: &'a Body<'tcx>,
+    phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+}
+
+impl<'a, 'tcx> OutOfSSAVerifier<'a, 'tcx> {
+    pub fn new(
+        body: &'a Body<'tcx>,
+        phi_functions: &'a HashMap<BasicBlock, Vec<PhiNode<'tcx>>>,
+    ) -> Self {
+        Self {
+            body,
+            phi_functions,
+        }
+    }
+
+    pub fn verify(self) -> Vec<OutOfSSAViolation> {
+        let mut violations = Vec::new();
+        self.check_copies(&mut violations);
+        self.check_cleanup_edges(&mut violations);
+        violations
+    }
+
+    fn check_copies(&self, violations: &mut Vec<OutOfSSAViolation>) {
+        let phi_blocks: HashMap<Local, BasicBlock> = self
+            .phi_functions
+            .iter()
+            .flat_map(|(&bb, phis)| phis.iter().map(move |phi| (phi.dest, bb)))
+            .collect();
+        for (bb, data) in self.body.basic_blocks.iter_enumerated() {
+            let goto_target = match data.terminator().kind {
+                TerminatorKind::Goto { target } => Some(target),
+                _ => None,
+            };
+            for (statement_index, statement) in data.statements.iter().enumerate() {
+                let location = Location {
+                    block: bb,
+                    statement_index,
+                };
+                let StatementKind::Assign(box (place, rvalue)) = &statement.kind else {
+                    continue;
+                };
+                let Some(dest) = place.as_local() else {
+                    continue;
+                };
+                let source = match rvalue {
+                    Rvalue::Use(operand) => operand.place().and_then(|place| place.as_local()),
+                    _ => None,
+                };
+                if let Some(source) = source
+                    && self.body.local_decls[dest].ty != self.body.local_decls[source].ty
+                {
+                    violations.push(OutOfSSAViolation::CopyTypeMismatch {
+                        location,
+                        dest,
+                        source,
+                    });
+                }
+                let Some(&phi_block) = phi_blocks.get(&dest) else {
+                    continue;
+                };
+                if !matches!(rvalue, Rvalue::Use(_)) {
+                    violations.push(OutOfSSAViolation::PhiNotRemoved { location, dest });
+                } else if goto_target != Some(phi_block) {
+                    violations.push(OutOfSSAViolation::CopyNotOnEdge {
+                        location,
+                        dest,
+                        phi_block,
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_cleanup_edges(&self, violations: &mut Vec<OutOfSSAViolation>) {
+        let blocks = &self.body.basic_blocks;
+        for (bb, data) in blocks.iter_enumerated() {
+            let terminator = data.terminator();
+            let unwind = match terminator.kind.unwind() {
+                Some(UnwindAction::Cleanup(cleanup)) => Some(*cleanup),
+                _ => None,
+            };
+            for succ in terminator.successors() {
+                let valid = if data.is_cleanup {
+                    blocks[succ].is_cleanup
+                } else {
+                    !blocks[succ].is_cleanup || unwind == Some(succ)
+                };
+                if !valid {
+                    violations.push(OutOfSSAViolation::CleanupEdge { from: bb, to: succ });
+                }
+            }
+        }
+    }
+}
+
+/// 收集所有真正的使用，跳过定义点以及 StorageLive/StorageDead、调试信息
+#[derive(Default)]
+struct UseCollector {
+    uses: Vec<(Local, Location)>,
+}
+
+impl<'tcx> Visitor<'tcx> for UseCollector {
+    fn visit_local(&mut self, local: Local, context: PlaceContext, location: Location) {
+        let is_def = matches!(
+            context,
+            PlaceContext::MutatingUse(MutatingUseContext::Store | MutatingUseContext::Call)
+        );
+        if context.is_use() && !is_def {
+            self.uses.push((local, location));
+        }
+    }
+}
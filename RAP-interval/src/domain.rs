@@ -1,3 +1,8 @@
 pub mod ConstraintGraph;
+pub mod abstract_domain;
+pub mod congruence;
 pub mod domain;
+pub mod float;
 pub mod range;
+pub mod sign;
+pub mod wrapped;
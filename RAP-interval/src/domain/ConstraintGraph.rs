@@ -1,36 +1,41 @@
-use super::{domain::*, range::RangeType, range::*};
-
-use num_traits::Bounded;
-use rand::Rng;
-use rustc_middle::{
-    mir::*,
-    ty::{self, Const, ScalarInt, TyCtxt},
+use super::{abstract_domain::AbstractDomain, domain::*, float::FloatRange, range::*};
+use crate::ir::ir::{
+    BinOp, BlockId, CmpOp, Function, InstKind, Instruction, Operand, ScalarTy, Terminator, UnOp,
+    ValueId,
 };
-use rustc_mir_transform::*;
-use rustc_span::sym::var;
 
-use std::collections::{HashMap, HashSet};
-pub struct ConstraintGraph<'tcx, T: PartialOrd + Clone + Bounded> {
+use std::collections::{HashMap, HashSet, VecDeque};
+pub struct ConstraintGraph<D: AbstractDomain> {
     // Protected fields
-    pub tcx: TyCtxt<'tcx>,
-    pub vars: VarNodes<'tcx, T>, // The variables of the source program
-    pub oprs: GenOprs<'tcx, T>,  // The operations of the source program
+    pub vars: VarNodes<D>, // The variables of the source program
+    pub oprs: GenOprs<D>,  // The operations of the source program
 
     // Private fields
     // func: Option<Function>,             // Save the last Function analyzed
-    pub defmap: DefMap<'tcx, T>, // Map from variables to the operations that define them
-    pub usemap: UseMap<'tcx, T>, // Map from variables to operations where variables are used
-    pub symbmap: SymbMap<'tcx, T>, // Map from variables to operations where they appear as bounds
-    pub values_branchmap: ValuesBranchMap<'tcx, T>, // Store intervals, basic blocks, and branches
-    // values_switchmap: ValuesSwitchMap<'tcx, T>, // Store intervals for switch branches
-    constant_vector: Vec<T>, // Vector for constants from an SCC
+    pub defmap: DefMap,   // Map from variables to the operations that define them
+    pub usemap: UseMap,   // Map from variables to operations where variables are used
+    pub symbmap: SymbMap, // Map from variables to operations where they appear as bounds
+    pub values_branchmap: ValuesBranchMap<D>, // Store intervals, basic blocks, and branches
+    // values_switchmap: ValuesSwitchMap<T>, // Store intervals for switch branches
+    constant_vector: Vec<i128>, // Vector for constants from an SCC
+    overflow_checks: bool,      // Whether checked arithmetic is trusted not to overflow
+    pub floats: HashMap<ValueId, FloatRange>, // Ranges of the floating-point values
+}
+
+impl<D> Default for ConstraintGraph<D>
+where
+    D: AbstractDomain,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<'tcx, T> ConstraintGraph<'tcx, T>
+impl<D> ConstraintGraph<D>
 where
-    T: PartialOrd + Clone + Bounded + From<ScalarInt>,
+    D: AbstractDomain,
 {
-    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+    pub fn new() -> Self {
         Self {
             vars: VarNodes::new(),
             oprs: GenOprs::new(),
@@ -41,45 +46,28 @@ where
             values_branchmap: ValuesBranchMap::new(),
             // values_switchmap: ValuesSwitchMap::new(),
             constant_vector: Vec::new(),
-            tcx: tcx,
-        }
-    }
-    //     fn create_random_place() -> Place<'tcx> {
-    //     // 随机生成一个新的 Local 值
-    //     let mut rng = rand::rng();
-    //     let random_local = Local::from_usize(rng.random_range(0..100));
-
-    //     // 创建一个新的 Place 值，使用随机生成的 Local 和空的投影列表
-    //     let place = Place {
-    //         local: random_local,
-    //         projection: ,
-    //     };
-
-    //     place
-    // }
-    pub fn create_random_place(tcx: TyCtxt<'tcx>) -> Place<'tcx> {
-        // 随机生成一个新的 Local 值
-        let mut rng = rand::rng();
-        let random_local = Local::from_usize(rng.random_range(10000..1000000)); // 假设 Local 的范围是 0 到 99
-
-        // 创建一个新的 Place 值，使用随机生成的 Local 和空的投影列表
-        Place {
-            local: random_local,
-            projection: ty::List::empty(),
+            overflow_checks: true,
+            floats: HashMap::new(),
         }
     }
-    pub fn add_varnode(&mut self, v: &'tcx Place<'tcx>) -> &VarNode<'tcx, T> {
-        // 如果变量已存在，则直接返回
 
-        // 插入新的 VarNode
-        let node = VarNode::new(v);
-        // 先进行不可变借用，确保没有冲突
-        let node_ref = self.vars.entry(v).or_insert(node);
+    /// Without overflow checks every operation is analyzed as wrapping around, as in a
+    /// release build. Must be set before `build_graph`.
+    pub fn set_overflow_checks(&mut self, overflow_checks: bool) {
+        self.overflow_checks = overflow_checks;
+    }
 
+    pub fn add_varnode(&mut self, v: ValueId, ty: Option<IntType>) -> &VarNode<D> {
         // 确保 usemap 也更新
-        self.usemap.entry(v).or_insert(HashSet::new());
+        self.usemap.entry(v).or_default();
+
+        // 如果变量已存在，则直接返回
+        self.vars.entry(v).or_insert_with(|| VarNode::new(v, ty))
+    }
 
-        node_ref
+    /// The integer type of `v`, if it has one; only those values carry ranges.
+    fn int_type(function: &Function, v: ValueId) -> Option<IntType> {
+        IntType::of(&function.value(v).ty)
     }
 
     // pub fn get_oprs(&self) -> &GenOprs {
@@ -94,390 +82,677 @@ where
     //     &self.usemap
     // }
 
-    // pub fn build_graph(&self, body: &Body) -> ConstraintGraph {
-    //     let mut graph = ConstraintGraph::new();
-    //     let basic_blocks = &body.basic_blocks;
-    //     for basic_block_data in basic_blocks.iter() {
-    //         for statement in basic_block_data.statements.iter() {
-    //             graph.add_stat_to_graph(&statement.kind);
-    //         }
-    //         if let Some(terminator) = &basic_block_data.terminator {
-    //             graph.add_terminator_to_graph(&terminator.kind);
-    //         }
-    //     }
-    //     graph
-    // }
-    pub fn build_graph(&mut self, body: &'tcx Body<'tcx>) {
-        self.build_value_maps(body);
-        for block in body.basic_blocks.indices() {
-            let block_data = &body[block];
-            // Traverse statements
-            for statement in block_data.statements.iter() {
-                // self.build_operations(statement);
+    pub fn build_graph(&mut self, function: &Function) {
+        self.build_value_maps(function);
+        self.build_float_ranges(function);
+        for block in function.block_ids() {
+            for (index, inst) in function.block(block).insts.iter().enumerate() {
+                self.build_operations(function, block, index, inst);
             }
         }
     }
-    pub fn build_value_maps(&mut self, body: &'tcx Body<'tcx>) {
-        for (block_index, block) in body.basic_blocks.iter_enumerated() {
-            if let Some(terminator) = &block.terminator {
-                match &terminator.kind {
-                    TerminatorKind::SwitchInt { discr, targets } => {
-                        self.build_value_branch_map(body, discr, targets, block);
-                    }
-                    TerminatorKind::Goto { target } => {
-                        // self.build_value_goto_map(block_index, *target);
-                    }
-                    _ => {
-                        // println!(
-                        //     "BasicBlock {:?} has an unsupported terminator: {:?}",
-                        //     block_index, terminator.kind
-                        // );
-                    }
-                }
+
+    pub fn build_value_maps(&mut self, function: &Function) {
+        for block in function.block_ids() {
+            if let Terminator::Branch {
+                cond: Operand::Value(cond),
+                then_bb,
+                else_bb,
+            } = &function.block(block).term
+            {
+                self.build_value_branch_map(function, block, *cond, *then_bb, *else_bb);
             }
         }
     }
 
+    /// Records the intervals a branch on `cond` in `block` implies for the compared values.
+    ///
+    /// The compared operand is often a temporary copy (`%3 = copy %0; %1 = lt %3, 10`) while
+    /// the sigmas rename the source. The map is keyed by every value on the copy chain inside
+    /// `block`, the same chain `SSATransformer::compared_locals` follows; they all hold the
+    /// same value, so each may be refined.
     pub fn build_value_branch_map(
         &mut self,
-        body: &Body<'tcx>,
-        discr: &'tcx Operand<'tcx>,
-        targets: &'tcx SwitchTargets,
-        block: &'tcx BasicBlockData<'tcx>,
+        function: &Function,
+        block: BlockId,
+        cond: ValueId,
+        bb_true: BlockId,
+        bb_false: BlockId,
     ) {
-        // let place1: &Place<'tcx>;
         // 确保分支条件是二元比较
-        if let Operand::Copy(place) | Operand::Move(place) = discr {
-            if let Some((op1, op2, cmp_op)) = self.extract_condition(place, block) {
-                let const_op1 = op1.constant();
-                let const_op2 = op2.constant();
-
-                match (const_op1, const_op2) {
-                    (Some(c1), Some(c2)) => {}
-                    (Some(c), None) | (None, Some(c)) => {
-                        let const_in_left: bool;
-                        let variable: &Place<'tcx>;
-                        if const_op1.is_some() {
-                            const_in_left = true;
-                            variable = match op2 {
-                                Operand::Copy(p) | Operand::Move(p) => p,
-                                _ => panic!("Expected a place"),
-                            };
-                        } else {
-                            const_in_left = false;
-                            variable = match op1 {
-                                Operand::Copy(p) | Operand::Move(p) => p,
-                                _ => panic!("Expected a place"),
-                            };
-                        }
-                        // 此处应根据T进行选取，设定为scalarInt
-                        self.add_varnode(variable);
-                        print!("{:?}\n", variable);
-                        let scalar = c.const_.try_to_scalar_int().unwrap();
-                        let scalar_value: T = scalar.into();
-
-                        let const_range = Range::new(
-                            scalar_value.clone(),
-                            scalar_value.clone(),
-                            RangeType::Regular,
-                        );
-
-                        let true_range = self.apply_comparison(
-                            scalar_value.clone(),
-                            cmp_op,
-                            true,
-                            const_in_left,
-                        );
-                        let false_range = self.apply_comparison(
-                            scalar_value.clone(),
-                            cmp_op,
-                            false,
-                            const_in_left,
-                        );
-                        let target_vec = targets.all_targets();
-                        let vbm = ValueBranchMap::new(
-                            variable,
-                            &target_vec[0],
-                            &target_vec[1],
-                            IntervalType::Basic(BasicInterval::new(true_range)),
-                            IntervalType::Basic(BasicInterval::new(false_range)),
-                        );
-                        self.values_branchmap.insert(place, vbm);
-                    }
-                    (None, None) => {
-                        // 两个变量之间的比较
-
-                        let CR = Range::new(T::min_value(), T::max_value(), RangeType::Regular);
-
-                        let p1 = match op1 {
-                            Operand::Copy(p) | Operand::Move(p) => p,
-                            _ => panic!("Expected a place"),
-                        };
-                        let p2 = match op2 {
-                            Operand::Copy(p) | Operand::Move(p) => p,
-                            _ => panic!("Expected a place"),
-                        };
-                        let target_vec = targets.all_targets();
-                        self.add_varnode(p1);
-                        self.add_varnode(p2);
-                        let STOp1 = IntervalType::Symb(SymbInterval::new(CR.clone(), &p2, true));
-                        let SFOp1 = IntervalType::Symb(SymbInterval::new(CR.clone(), &p2, false));
-                        let STOp2 = IntervalType::Symb(SymbInterval::new(CR.clone(), &p1, true));
-                        let SFOp2 = IntervalType::Symb(SymbInterval::new(CR.clone(), &p1, false));
-                        let vbm_1 =
-                            ValueBranchMap::new(p1, &target_vec[0], &target_vec[1], STOp1, SFOp1);
-                        let vbm_2 =
-                            ValueBranchMap::new(p2, &target_vec[0], &target_vec[1], STOp2, SFOp2);
-                        self.values_branchmap.insert(p1, vbm_1);
-                        self.values_branchmap.insert(p2, vbm_2);
-                    }
+        let Some((op1, op2, cmp_op)) = self.extract_condition(function, cond) else {
+            return;
+        };
+        match (op1, op2) {
+            (Operand::Const(_), Operand::Const(_))
+            | (Operand::Float(_), _)
+            | (_, Operand::Float(_)) => {}
+            (Operand::Const(c), Operand::Value(variable))
+            | (Operand::Value(variable), Operand::Const(c)) => {
+                let Some(ty) = Self::int_type(function, variable) else {
+                    return;
+                };
+                let const_in_left = op1.constant().is_some();
+                self.add_varnode(variable, Some(ty));
+
+                let true_range = self.apply_comparison(c, cmp_op, true, const_in_left);
+                let false_range = self.apply_comparison(c, cmp_op, false, const_in_left);
+                for variable in Self::copy_chain(function, block, variable) {
+                    let vbm = ValueBranchMap::new(
+                        variable,
+                        bb_true,
+                        bb_false,
+                        IntervalType::Basic(BasicInterval::new(true_range.clone())),
+                        IntervalType::Basic(BasicInterval::new(false_range.clone())),
+                    );
+                    self.values_branchmap.insert(variable, vbm);
                 }
-            };
+            }
+            (Operand::Value(p1), Operand::Value(p2)) => {
+                // 两个变量之间的比较
+                let (Some(ty1), Some(ty2)) =
+                    (Self::int_type(function, p1), Self::int_type(function, p2))
+                else {
+                    return;
+                };
+                let unbounded = D::interval(None, None);
+
+                self.add_varnode(p1, Some(ty1));
+                self.add_varnode(p2, Some(ty2));
+                // `p1 op p2` on the true side; `p2` sees the swapped predicate
+                let symb = |bound, predicate| {
+                    IntervalType::Symb(SymbInterval::new(unbounded.clone(), bound, predicate))
+                };
+                let true_op1 = symb(p2, cmp_op);
+                let false_op1 = symb(p2, cmp_op.negate());
+                let true_op2 = symb(p1, cmp_op.swap());
+                let false_op2 = symb(p1, cmp_op.swap().negate());
+                for v1 in Self::copy_chain(function, block, p1) {
+                    let vbm_1 = ValueBranchMap::new(
+                        v1,
+                        bb_true,
+                        bb_false,
+                        true_op1.clone(),
+                        false_op1.clone(),
+                    );
+                    self.values_branchmap.insert(v1, vbm_1);
+                }
+                for v2 in Self::copy_chain(function, block, p2) {
+                    let vbm_2 = ValueBranchMap::new(
+                        v2,
+                        bb_true,
+                        bb_false,
+                        true_op2.clone(),
+                        false_op2.clone(),
+                    );
+                    self.values_branchmap.insert(v2, vbm_2);
+                }
+            }
+        }
+    }
+
+    /// `value` followed by the values it is copied from through `copy` instructions in `block`.
+    fn copy_chain(function: &Function, block: BlockId, value: ValueId) -> Vec<ValueId> {
+        let mut chain = vec![value];
+        let mut current = value;
+        while let Some(InstKind::Assign(Operand::Value(source))) = function
+            .block(block)
+            .insts
+            .iter()
+            .find(|inst| inst.dest == current)
+            .map(|inst| &inst.kind)
+        {
+            if chain.contains(source) {
+                break;
+            }
+            chain.push(*source);
+            current = *source;
         }
+        chain
     }
 
     fn extract_condition(
         &self,
-        place: &'tcx Place<'tcx>,
-        block: &'tcx BasicBlockData<'tcx>,
-    ) -> Option<(&'tcx Operand<'tcx>, &'tcx Operand<'tcx>, BinOp)> {
-        for stmt in &block.statements {
-            if let StatementKind::Assign(box (lhs, Rvalue::BinaryOp(bin_op, box (op1, op2)))) =
-                &stmt.kind
-            {
-                if lhs == place {
-                    print!("!!!!!!");
-                    print!("{:?}\n", bin_op);
-                    print!("{:?}\n", op1);
-                    print!("{:?}\n", op2);
-                    return Some((op1, op2, *bin_op));
-                }
-            }
+        function: &Function,
+        cond: ValueId,
+    ) -> Option<(Operand, Operand, CmpOp)> {
+        match function.def_of(cond).map(|inst| &inst.kind) {
+            Some(InstKind::Compare { op, lhs, rhs }) => Some((*lhs, *rhs, *op)),
+            _ => None,
         }
-        None
     }
-    // pub fn calculate_ranges(
-    //     &self,
-    //     op1: &Operand<'tcx>,
-    //     op2: &Operand<'tcx>,
-    //     cmp_op: BinOp,
-    // ) -> (Option<(i128, i128)>, Option<(i128, i128)>) {
-    //     // 检查操作数是否为常量
-    //     let const_op1 = op1.constant();
-    //     let const_op2 = op2.constant();
-
-    //     match (const_op1, const_op2) {
-    //         (Some(c1), Some(c2)) => {}
-    //         (Some(c), None) | (None, Some(c)) => {
-    //             let const_in_left: bool;
-    //             if const_op1.is_some() {
-    //                 const_in_left = true;
-    //             } else {
-    //                 const_in_left = false;
-    //             }
-    //             // 此处应根据T进行选取，设定为scalarInt
-    //             let const_range = Range::new(c.const_.try_to_scalar().unwrap());
-
-    //             let true_range = self.apply_comparison(const_range, cmp_op, true, const_in_left);
-    //             let false_range = self.apply_comparison(const_range, cmp_op, false, const_in_left);
-
-    //             (true_range, false_range)
-    //         }
-    //         (None, None) => {
-    //             // 两个变量之间的比较
-    //             let variable_range1 = Range::new(UserType::new());
-    //             let variable_range2 = Range::new(UserType::new());
-    //             let true_range =
-    //                 self.apply_comparison(variable_range1, variable_range2, cmp_op, true);
-    //             let false_range =
-    //                 self.apply_comparison(variable_range1, variable_range2, cmp_op, false);
-    //             (true_range, false_range)
-    //         }
-    //     }
-    // }
 
-    fn apply_comparison<U: PartialOrd + Clone + Bounded>(
+    /// The range of a variable `v` on one side of `v cmp_op c`
+    /// (or `c cmp_op v` when `const_in_left` is set).
+    fn apply_comparison(
         &self,
-        constant: U,
-        cmp_op: BinOp,
+        constant: i128,
+        cmp_op: CmpOp,
         is_true_branch: bool,
         const_in_left: bool,
-    ) -> Range<U> {
+    ) -> D {
+        let cmp_op = if const_in_left { cmp_op.swap() } else { cmp_op };
+        let cmp_op = if is_true_branch {
+            cmp_op
+        } else {
+            cmp_op.negate()
+        };
         match cmp_op {
-            BinOp::Lt => {
-                if is_true_branch ^ const_in_left {
-                    Range::new(U::min_value(), constant, RangeType::Regular)
-                } else {
-                    Range::new(constant, U::max_value(), RangeType::Regular)
-                }
+            CmpOp::Lt => match constant.checked_sub(1) {
+                Some(upper) => D::interval(None, Some(upper)),
+                None => D::bottom(),
+            },
+            CmpOp::Le => D::interval(None, Some(constant)),
+            CmpOp::Gt => match constant.checked_add(1) {
+                Some(lower) => D::interval(Some(lower), None),
+                None => D::bottom(),
+            },
+            CmpOp::Ge => D::interval(Some(constant), None),
+            CmpOp::Eq => D::interval(Some(constant), Some(constant)),
+            // A single excluded value cannot be expressed by an interval.
+            CmpOp::Ne => D::interval(None, None),
+        }
+    }
+
+    pub fn build_operations(
+        &mut self,
+        function: &Function,
+        block: BlockId,
+        index: usize,
+        inst: &Instruction,
+    ) {
+        // Only integer values carry ranges
+        let Some(ty) = Self::int_type(function, inst.dest) else {
+            return;
+        };
+        match &inst.kind {
+            InstKind::Sigma { source } => {
+                self.add_sigma_op(function, block, index, inst, ty, *source)
+            }
+            _ => {
+                let intersect = IntervalType::Basic(BasicInterval::new(D::interval(None, None)));
+                self.add_op(function, block, index, inst, ty, intersect);
             }
+        }
+    }
 
-            BinOp::Le => {
-                if is_true_branch ^ const_in_left {
-                    Range::new(U::min_value(), constant, RangeType::Regular)
+    fn add_op(
+        &mut self,
+        function: &Function,
+        block: BlockId,
+        index: usize,
+        inst: &Instruction,
+        ty: IntType,
+        intersect: IntervalType<D>,
+    ) -> usize {
+        self.add_varnode(inst.dest, Some(ty));
+        let op = self.oprs.len();
+        self.oprs.push(BasicOp::new(
+            intersect,
+            inst.dest,
+            (block, index),
+            self.op_kind(function, &inst.kind, ty),
+            ty,
+        ));
+        self.defmap.insert(inst.dest, op);
+        for source in inst.uses() {
+            self.add_varnode(source, Self::int_type(function, source));
+            self.usemap.entry(source).or_default().insert(op);
+        }
+        op
+    }
+
+    fn op_source(operand: &Operand) -> OpSource<D> {
+        match operand {
+            Operand::Value(v) => OpSource::Var(*v),
+            Operand::Const(c) => OpSource::Const(D::constant(*c)),
+            Operand::Float(_) => OpSource::Const(D::interval(None, None)),
+        }
+    }
+
+    fn op_kind(&self, function: &Function, kind: &InstKind, ty: IntType) -> OpKind<D> {
+        match kind {
+            // Casts and comparisons of floating-point values are evaluated on their ranges
+            InstKind::Cast { operand } if self.float_operand(function, operand).is_some() => {
+                let range: Range<i128> = self
+                    .float_operand(function, operand)
+                    .unwrap()
+                    .to_int_range(ty);
+                let range = if range.is_empty() {
+                    D::bottom()
                 } else {
-                    Range::new(constant, U::max_value(), RangeType::Regular)
-                }
+                    D::interval(Some(range.get_lower()), Some(range.get_upper()))
+                };
+                OpKind::Cast(OpSource::Const(range))
             }
+            InstKind::Compare { op, lhs, rhs } if self.float_operand(function, lhs).is_some() => {
+                let lhs = self.float_operand(function, lhs).unwrap();
+                let rhs = self
+                    .float_operand(function, rhs)
+                    .unwrap_or(FloatRange::top(lhs.bits));
+                let (may_true, may_false) = lhs.compare(*op, &rhs);
+                let range = match (may_false, may_true) {
+                    (false, false) => D::bottom(),
+                    (may_false, may_true) => D::interval(
+                        Some(if may_false { 0 } else { 1 }),
+                        Some(if may_true { 1 } else { 0 }),
+                    ),
+                };
+                OpKind::Copy(OpSource::Const(range))
+            }
+            InstKind::Assign(operand) => OpKind::Copy(Self::op_source(operand)),
+            InstKind::Unary { op, operand } => OpKind::Unary(*op, Self::op_source(operand)),
+            InstKind::Binary {
+                op,
+                lhs,
+                rhs,
+                checked,
+            } => OpKind::Binary(
+                *op,
+                Self::op_source(lhs),
+                Self::op_source(rhs),
+                *checked && self.overflow_checks,
+            ),
+            InstKind::Cast { operand } => OpKind::Cast(Self::op_source(operand)),
+            InstKind::Phi { operands } => OpKind::Phi(
+                operands
+                    .iter()
+                    .map(|(_, operand)| Self::op_source(operand))
+                    .collect(),
+            ),
+            InstKind::Sigma { source } => OpKind::Sigma(*source),
+            InstKind::Compare { .. } | InstKind::Call { .. } | InstKind::Opaque => OpKind::Unknown,
+        }
+    }
 
-            BinOp::Gt => {
-                if is_true_branch ^ const_in_left {
-                    Range::new(U::min_value(), constant, RangeType::Regular)
-                } else {
-                    Range::new(constant, U::max_value(), RangeType::Regular)
+    /// The range of a floating-point operand, or `None` if it is not one.
+    fn float_operand(&self, function: &Function, operand: &Operand) -> Option<FloatRange> {
+        match *operand {
+            Operand::Value(v) => match function.value(v).ty {
+                ScalarTy::Float { bits } => Some(
+                    self.floats
+                        .get(&v)
+                        .copied()
+                        .unwrap_or(FloatRange::top(bits)),
+                ),
+                _ => None,
+            },
+            Operand::Float(x) => Some(FloatRange::constant(64, f64::from_bits(x))),
+            Operand::Const(_) => None,
+        }
+    }
+
+    /// Computes the ranges of the floating-point values by round-robin iteration, joining
+    /// for a few rounds and widening afterwards, followed by two rounds of narrowing.
+    /// Integer values converted to floating point may be any value of their type.
+    pub fn build_float_ranges(&mut self, function: &Function) {
+        const WIDENING_DELAY: usize = 3;
+        let predecessors = function.predecessors();
+        let mut insts = Vec::new();
+        for block in function.block_ids() {
+            for inst in &function.block(block).insts {
+                if let ScalarTy::Float { bits } = function.value(inst.dest).ty {
+                    insts.push((block, inst, bits));
+                    self.floats.insert(inst.dest, FloatRange::bottom(bits));
                 }
             }
-
-            BinOp::Ge => {
-                if is_true_branch ^ const_in_left {
-                    Range::new(U::min_value(), constant, RangeType::Regular)
+        }
+        let mut round = 0;
+        loop {
+            let mut changed = false;
+            for &(block, inst, bits) in &insts {
+                let new = self.eval_float(function, &predecessors, block, inst, bits);
+                let old = self.floats[&inst.dest];
+                let next = if round < WIDENING_DELAY {
+                    old.join(&new)
                 } else {
-                    Range::new(constant, U::max_value(), RangeType::Regular)
+                    old.widen(&new)
+                };
+                if next != old {
+                    self.floats.insert(inst.dest, next);
+                    changed = true;
                 }
             }
+            if !changed {
+                break;
+            }
+            round += 1;
+        }
+        for _ in 0..2 {
+            for &(block, inst, bits) in &insts {
+                let new = self.eval_float(function, &predecessors, block, inst, bits);
+                let next = self.floats[&inst.dest].meet(&new);
+                self.floats.insert(inst.dest, next);
+            }
+        }
+    }
 
-            BinOp::Eq => {
-                if is_true_branch ^ const_in_left {
-                    Range::new(U::min_value(), constant, RangeType::Regular)
+    fn eval_float(
+        &self,
+        function: &Function,
+        predecessors: &HashMap<BlockId, Vec<BlockId>>,
+        block: BlockId,
+        inst: &Instruction,
+        bits: u16,
+    ) -> FloatRange {
+        let operand = |operand: &Operand| {
+            self.float_operand(function, operand)
+                .unwrap_or(FloatRange::top(bits))
+        };
+        match &inst.kind {
+            InstKind::Assign(source) => operand(source),
+            InstKind::Unary {
+                op: UnOp::Neg,
+                operand: source,
+            } => operand(source).neg(),
+            InstKind::Binary { op, lhs, rhs, .. } => {
+                let (lhs, rhs) = (operand(lhs), operand(rhs));
+                match op {
+                    BinOp::Add => lhs.add(&rhs),
+                    BinOp::Sub => lhs.sub(&rhs),
+                    BinOp::Mul => lhs.mul(&rhs),
+                    BinOp::Div => lhs.div(&rhs),
+                    BinOp::Rem => lhs.rem(&rhs),
+                    _ => FloatRange::top(bits),
+                }
+            }
+            InstKind::Cast { operand: source } => {
+                if let Some(float) = self.float_operand(function, source) {
+                    return float.cast(bits);
+                }
+                let range = match source {
+                    Operand::Const(c) => Range::new(*c, *c, RangeType::Regular),
+                    Operand::Value(v) => match IntType::of(&function.value(*v).ty) {
+                        Some(ty) => Range::of_type(ty),
+                        None => return FloatRange::top(bits),
+                    },
+                    Operand::Float(_) => unreachable!(),
+                };
+                FloatRange::from_int_range::<i128>(&range, bits)
+            }
+            InstKind::Phi { operands } => operands
+                .iter()
+                .fold(FloatRange::bottom(bits), |acc, (_, source)| {
+                    acc.join(&operand(source))
+                }),
+            InstKind::Sigma { source } => {
+                let range = operand(&Operand::Value(*source));
+                // The comparison of the branch that leads here, if any
+                let [pred] = predecessors[&block].as_slice() else {
+                    return range;
+                };
+                let Terminator::Branch {
+                    cond: Operand::Value(cond),
+                    then_bb,
+                    else_bb,
+                } = &function.block(*pred).term
+                else {
+                    return range;
+                };
+                let Some(InstKind::Compare { op, lhs, rhs }) =
+                    function.def_of(*cond).map(|inst| &inst.kind)
+                else {
+                    return range;
+                };
+                let holds = block == *then_bb;
+                if then_bb == else_bb {
+                    range
+                } else if *lhs == Operand::Value(*source) {
+                    range.restrict(*op, &operand(rhs), holds)
+                } else if *rhs == Operand::Value(*source) {
+                    range.restrict(op.swap(), &operand(lhs), holds)
                 } else {
-                    Range::new(constant, U::max_value(), RangeType::Regular)
+                    range
                 }
             }
-
-            _ => Range::new(constant.clone(), constant.clone(), RangeType::Empty),
+            _ => FloatRange::top(bits),
         }
     }
 
-    fn build_value_goto_map(&self, block_index: BasicBlock, target: BasicBlock) {
-        println!(
-            "Building value map for Goto in block {:?} targeting block {:?}",
-            block_index, target
-        );
-        // 在这里实现具体的 Goto 处理逻辑
-    }
-    pub fn build_varnodes(&mut self) {
-        // Builds VarNodes
-        for (name, node) in self.vars.iter_mut() {
-            let is_undefined = !self.defmap.contains_key(name);
-            node.init(is_undefined);
+    fn add_sigma_op(
+        &mut self,
+        function: &Function,
+        block: BlockId,
+        index: usize,
+        inst: &Instruction,
+        ty: IntType,
+        source: ValueId,
+    ) {
+        // The sigma on the true (false) side of a branch is constrained by the true (false) interval
+        let itv = self.values_branchmap.get(&source).and_then(|vbm| {
+            if vbm.get_bb_true() == block {
+                Some(vbm.get_itv_t())
+            } else if vbm.get_bb_false() == block {
+                Some(vbm.get_itv_f())
+            } else {
+                None
+            }
+        });
+        let intersect = match itv {
+            Some(itv) => itv.clone(),
+            None => IntervalType::Basic(BasicInterval::new(D::interval(None, None))),
+        };
+        let bound = match &intersect {
+            IntervalType::Symb(symb) => Some(symb.get_bound()),
+            IntervalType::Basic(_) => None,
+        };
+        let op = self.add_op(function, block, index, inst, ty, intersect);
+        if let Some(bound) = bound {
+            self.add_varnode(bound, Self::int_type(function, bound));
+            self.symbmap.entry(bound).or_default().insert(op);
         }
     }
-    pub fn build_operations(&mut self, inst: &'tcx Statement<'tcx>) {
-        // Handle binary instructions
-        if let StatementKind::Assign(box (place, rvalue)) = &inst.kind {
-            match rvalue {
-                Rvalue::BinaryOp(op, box (op1, op2)) => {
-                    let p1 = match op1 {
-                        Operand::Copy(p) | Operand::Move(p) => p,
-                        _ => panic!("Expected a place"),
-                    };
-
-                    self.add_varnode(p1);
-                    let p2 = match op2 {
-                        Operand::Copy(p) | Operand::Move(p) => p,
-                        _ => panic!("Expected a place"),
-                    };
-                    self.add_varnode(p1);
-                    self.add_varnode(p2);
-                    // self.add_varnode(place);
-                    self.add_binary_op(inst);
-                }
-                Rvalue::UnaryOp(op, op1) => {
-                    let p = match op1 {
-                        Operand::Copy(p) | Operand::Move(p) => p,
-                        _ => panic!("Expected a place"),
-                    };
-
-                    self.add_varnode(p);
-                    // self.add_varnode(place);
-                    self.add_unary_op(inst);
+}
+
+/// How `update` combines the old range of a variable with the next iterate.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Meet {
+    Widen,
+    Crop,
+    Narrow,
+}
+
+impl<D> ConstraintGraph<D>
+where
+    D: AbstractDomain,
+{
+    /// Solves the constraints. Strongly connected components are visited in topological
+    /// order; inside each one the ranges are widened until stable, recording which bounds
+    /// widening moved, and the symbolic intersects are fixed with the widened bounds. The
+    /// moved bounds are then cropped, and the bounds of the types that no widening step
+    /// produced, such as those of the variables that stayed unknown, are narrowed.
+    pub fn find_intervals(&mut self) {
+        // Variables without a defining operation (arguments, undefined values) may hold
+        // anything; the others start out unknown.
+        for (v, node) in self.vars.iter_mut() {
+            let range = if self.defmap.contains_key(v) {
+                D::unknown()
+            } else {
+                node.full_range()
+            };
+            node.set_range(range);
+        }
+        for component in self.strongly_connected_components() {
+            self.constant_vector = self.component_constants(&component);
+            self.fix_intersects(&component);
+            self.update(&component, Meet::Widen);
+            self.fix_intersects(&component);
+            for v in &component {
+                let node = self.vars.get_mut(v).unwrap();
+                if node.get_range().is_unknown() {
+                    node.set_range(node.full_range());
                 }
-                _ => {}
             }
+            self.update(&component, Meet::Crop);
+            self.update(&component, Meet::Narrow);
         }
     }
-    fn add_unary_op(&mut self, stmt: &'tcx Statement<'tcx>) {
-        let rand_place: Place<'tcx> = Self::create_random_place(self.tcx);
-        let stmt_varnode = self.add_varnode(Box::leak(Box::new(rand_place)));
+
+    /// Variables depend on the sources of their defining operation and, for sigmas, on the
+    /// symbolic bound of the intersect.
+    fn dependents(&self, v: ValueId) -> Vec<ValueId> {
+        let mut sinks: Vec<ValueId> = self
+            .usemap
+            .get(&v)
+            .into_iter()
+            .chain(self.symbmap.get(&v))
+            .flatten()
+            .map(|&op| self.oprs[op].get_sink())
+            .collect();
+        sinks.sort();
+        sinks.dedup();
+        sinks
     }
 
-    fn add_binary_op(&mut self, inst: &'tcx Statement<'tcx>) {
-        // Implementation for adding binary operation
-        // ...
+    /// Tarjan's algorithm, iteratively; components come out in topological order.
+    fn strongly_connected_components(&self) -> Vec<Vec<ValueId>> {
+        let mut nodes: Vec<ValueId> = self.vars.keys().copied().collect();
+        nodes.sort();
+
+        let mut index: HashMap<ValueId, usize> = HashMap::new();
+        let mut lowlink: HashMap<ValueId, usize> = HashMap::new();
+        let mut on_stack: HashSet<ValueId> = HashSet::new();
+        let mut stack: Vec<ValueId> = Vec::new();
+        let mut components = Vec::new();
+        for root in nodes {
+            if index.contains_key(&root) {
+                continue;
+            }
+            // Each frame holds a node and the dependents still to visit
+            let mut frames: Vec<(ValueId, Vec<ValueId>)> = Vec::new();
+            let mut next = Some(root);
+            loop {
+                if let Some(v) = next.take() {
+                    index.insert(v, index.len());
+                    lowlink.insert(v, index[&v]);
+                    stack.push(v);
+                    on_stack.insert(v);
+                    frames.push((v, self.dependents(v)));
+                }
+                let Some((v, dependents)) = frames.last_mut() else {
+                    break;
+                };
+                let v = *v;
+                if let Some(w) = dependents.pop() {
+                    if !index.contains_key(&w) {
+                        next = Some(w);
+                    } else if on_stack.contains(&w) {
+                        lowlink.insert(v, lowlink[&v].min(index[&w]));
+                    }
+                    continue;
+                }
+                frames.pop();
+                if let Some((parent, _)) = frames.last() {
+                    lowlink.insert(*parent, lowlink[parent].min(lowlink[&v]));
+                }
+                if lowlink[&v] == index[&v] {
+                    let mut component = Vec::new();
+                    while let Some(w) = stack.pop() {
+                        on_stack.remove(&w);
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+        components.reverse();
+        components
     }
 
-    // fn add_phi_op(&mut self, phi: &'tcx PHINode<'tcx>) {
-    //     // Implementation for adding phi operation
-    //     // ...
-    // }
+    /// The jump-set for widening: the constants of the operations in `component`, the
+    /// bounds of their intersects and the bounds of the types of the variables.
+    fn component_constants(&self, component: &[ValueId]) -> Vec<i128> {
+        let mut constants = Vec::new();
+        for v in component {
+            if let Some(ty) = self.vars[v].get_type() {
+                for bound in [ty.min_value::<i128>(), ty.max_value()] {
+                    if bound != i128::MIN && bound != i128::MAX {
+                        constants.push(bound);
+                    }
+                }
+            }
+            let Some(&op) = self.defmap.get(v) else {
+                continue;
+            };
+            let op = &self.oprs[op];
+            constants.extend(op.intersect.get_range().thresholds());
+            let sources: Vec<&OpSource<D>> = match &op.kind {
+                OpKind::Copy(source) | OpKind::Unary(_, source) | OpKind::Cast(source) => {
+                    vec![source]
+                }
+                OpKind::Binary(_, lhs, rhs, _) => vec![lhs, rhs],
+                OpKind::Phi(sources) => sources.iter().collect(),
+                OpKind::Sigma(_) | OpKind::Unknown => Vec::new(),
+            };
+            for source in sources {
+                if let OpSource::Const(range) = source {
+                    constants.extend(range.thresholds());
+                }
+            }
+        }
+        constants.sort();
+        constants.dedup();
+        constants
+    }
 
-    // fn add_sigma_op(&mut self, phi: &'tcx PHINode<'tcx>) {
-    //     // Implementation for adding sigma operation
-    //     // ...
-    // }
-    // pub fn find_intervals(&mut self) {
-    //     // 构建符号交集映射
-    //     self.build_symbolic_intersect_map();
-
-    //     // 查找强连通分量（SCC）
-    //     let scc_list = Nuutila::new(&self.vars, &self.usemap, &self.symbmap);
-    //     self.num_sccs += scc_list.worklist.len();
-
-    //     // 遍历每个 SCC
-    //     for component in scc_list.components() {
-    //         if component.len() == 1 {
-    //             // 处理单节点的 SCC
-    //             self.num_alone_sccs += 1;
-    //             self.fix_intersects(&component);
-
-    //             let var = component.iter().next().unwrap();
-    //             if var.get_range().is_unknown() {
-    //                 var.set_range(allue {
-    //                     min: i32::MIN,
-    //                     max: i32::MAX,
-    //                 });
-    //             }
-    //         } else {
-    //             // 更新最大 SCC 大小
-    //             if component.len() > self.size_max_scc {
-    //                 self.size_max_scc = component.len();
-    //             }
-
-    //             // 为该 SCC 构建使用映射
-    //             let comp_use_map = self.build_use_map(&component);
-
-    //             // 获取 SCC 的入口点
-    //             let mut entry_points = HashSet::new();
-    //             self.generate_entry_points(&component, &mut entry_points);
-
-    //             // 固定点迭代，更新范围
-    //             self.pre_update(&comp_use_map, &entry_points);
-    //             self.fix_intersects(&component);
-
-    //             // 为未知范围的变量设置默认范围
-    //             for var in &component {
-    //                 if var.get_range().is_unknown() {
-    //                     var.set_range(Range {
-    //                         min: i32::MIN,
-    //                         max: i32::MAX,
-    //                     });
-    //                 }
-    //             }
-
-    //             // 二次迭代，更新活动变量
-    //             let mut active_vars = HashSet::new();
-    //             self.generate_active_vars(&component, &mut active_vars);
-    //             self.pos_update(&comp_use_map, &active_vars, &component);
-    //         }
-
-    //         // 将结果传播到下一个 SCC
-    //         self.propagate_to_next_scc(&component);
-    //     }
-    // }
+    /// Narrows the symbolic intersects of the sigmas in `component` by their bounds.
+    fn fix_intersects(&mut self, component: &[ValueId]) {
+        for v in component {
+            let Some(&op) = self.defmap.get(v) else {
+                continue;
+            };
+            if let IntervalType::Symb(symb) = &self.oprs[op].intersect {
+                let bound = symb.get_bound();
+                if let Some(bound) = self.vars.get(&bound) {
+                    self.oprs[op].fix_intersects(bound);
+                }
+            }
+        }
+    }
 
-    // 假设的辅助方法定义
-    fn build_symbolic_intersect_map(&self) {
-        // 构建符号交集映射
+    /// Re-evaluates the operations defining `component` until their ranges are stable,
+    /// combining old and new ranges with `meet`. Widening marks the bounds it moves in the
+    /// abstract state, and a cropped bound is no longer marked, so each bound is cropped at
+    /// most once.
+    fn update(&mut self, component: &[ValueId], meet: Meet) {
+        let members: HashSet<ValueId> = component.iter().copied().collect();
+        let mut worklist: VecDeque<usize> = component
+            .iter()
+            .filter_map(|v| self.defmap.get(v).copied())
+            .collect();
+        let mut queued: HashSet<usize> = worklist.iter().copied().collect();
+        while let Some(op) = worklist.pop_front() {
+            queued.remove(&op);
+            let sink = self.oprs[op].get_sink();
+            let new = self.oprs[op].eval(&self.vars);
+            let node = self.vars.get_mut(&sink).unwrap();
+            let old = node.get_range().clone();
+            let next = match meet {
+                Meet::Widen => old.widen(&new, &self.constant_vector),
+                Meet::Crop => old.crop(&new, node.get_abstract_state()),
+                // Only variables with an integer type have operations
+                Meet::Narrow => old.narrow(&new, node.get_type().unwrap()),
+            };
+            if next == old {
+                continue;
+            }
+            let state = node.get_abstract_state();
+            match meet {
+                Meet::Widen => node.store_abstract_state(state.join(old.growth(&next))),
+                Meet::Crop => node.store_abstract_state(state.without(next.growth(&old))),
+                Meet::Narrow => {}
+            }
+            node.set_range(next);
+            for &user in self.usemap.get(&sink).into_iter().flatten() {
+                if members.contains(&self.oprs[user].get_sink()) && queued.insert(user) {
+                    worklist.push_back(user);
+                }
+            }
+        }
     }
 }
 
@@ -0,0 +1,78 @@
+//! The abstract domains the constraint graph is solved over.
+//!
+//! The graph and its solver only combine abstract values through this trait: operations are
+//! evaluated with the transfer functions, branches refine the values that flow into sigmas,
+//! and strongly connected components are solved by widening, cropping and narrowing. Any
+//! domain implementing it, such as `Range<T>`, `Congruence` or their product
+//! `StridedRange<T>`, can be solved with the same code.
+
+use std::fmt;
+
+use super::range::IntType;
+use super::sign::Sign;
+use crate::ir::ir::{BinOp, CmpOp, UnOp};
+
+pub trait AbstractDomain: Clone + PartialEq + fmt::Debug {
+    /// Every value of `ty`.
+    fn top(ty: IntType) -> Self;
+
+    /// No value: the operation is never executed.
+    fn bottom() -> Self;
+
+    /// Not computed yet, the identity of `join` and `meet`. Domains without such an element
+    /// use bottom, which is the identity of `join`.
+    fn unknown() -> Self {
+        Self::bottom()
+    }
+
+    fn is_bottom(&self) -> bool;
+
+    fn is_unknown(&self) -> bool {
+        false
+    }
+
+    /// The single value `value`, or any value if the domain cannot represent it.
+    fn constant(value: i128) -> Self;
+
+    /// The values between `lower` and `upper`, where `None` leaves that side unbounded.
+    fn interval(lower: Option<i128>, upper: Option<i128>) -> Self;
+
+    /// The values that satisfy `x op y` for some `y` in `bound`, which refine a variable on
+    /// one side of a branch.
+    fn satisfying(op: CmpOp, bound: &Self) -> Self;
+
+    fn join(&self, other: &Self) -> Self;
+
+    fn meet(&self, other: &Self) -> Self;
+
+    /// Widens `self` by the next iterate `new`. `thresholds` holds the sorted constants of
+    /// the strongly connected component, which bounds may jump to.
+    fn widen(&self, new: &Self, thresholds: &[i128]) -> Self;
+
+    /// Narrows `self`, the widened value of a variable of type `ty`, by the next iterate `new`.
+    fn narrow(&self, new: &Self, ty: IntType) -> Self;
+
+    /// The direction in which `new`, the next value of a variable, grows past `self`: the
+    /// bounds of `new` beyond those of `self`. Domains without bounds always mark both.
+    fn growth(&self, _new: &Self) -> Sign {
+        Sign::Unknown
+    }
+
+    /// The crop meet: narrows `self` by the next iterate `new`, refining only the bounds
+    /// that `state` marks.
+    fn crop(&self, new: &Self, state: Sign) -> Self;
+
+    /// The constants of `self` that widening may jump to.
+    fn thresholds(&self) -> Vec<i128> {
+        Vec::new()
+    }
+
+    /// `op self` of type `ty`.
+    fn unary(&self, op: UnOp, ty: IntType) -> Self;
+
+    /// `self op other` of type `ty`. A `checked` operation panics instead of overflowing.
+    fn binary(&self, op: BinOp, other: &Self, ty: IntType, checked: bool) -> Self;
+
+    /// `self as to`, where `from` is the type of `self` if it has one.
+    fn cast(&self, from: Option<IntType>, to: IntType) -> Self;
+}
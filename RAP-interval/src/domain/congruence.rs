@@ -0,0 +1,770 @@
+//! Congruences, after Granger, "Static Analysis of Arithmetical Congruences" (1989).
+//!
+//! A congruence `m·Z + r` holds the integers equal to `r` modulo `m`, such as the values of
+//! `i` in `for i in (0..n).step_by(4)` (`4·Z + 0`) or of `2 * k + 1` (`2·Z + 1`). The modulus
+//! 0 holds the single integer `r` and the modulus 1 every integer. Integers are those of
+//! `i128`; a congruence modulo `2^128` is kept modulo `2^127`, which it implies.
+//!
+//! `StridedRange` is the reduced product with `Range`: the bounds of the range are moved
+//! inward to the nearest members of the congruence, and a range of one value makes the
+//! congruence that constant.
+
+use num_traits::{Bounded, FromPrimitive, PrimInt};
+use std::fmt;
+
+use super::abstract_domain::AbstractDomain;
+use super::range::{IntType, Range, RangeType};
+use super::sign::Sign;
+use crate::ir::ir::{BinOp, CmpOp, UnOp};
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Congruence {
+    Bottom,
+    /// `modulus·Z + residue`, with `0 <= residue < modulus` unless the modulus is 0.
+    Class {
+        modulus: u128,
+        residue: i128,
+    },
+}
+
+/// The largest modulus kept.
+const MAX_MODULUS: u128 = 1 << 127;
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// `2^bits`, or `2^127` which divides it.
+fn pow2(bits: u32) -> u128 {
+    1 << bits.min(127)
+}
+
+/// `x` modulo `m`, in `0..m`.
+fn mod_of(x: i128, m: u128) -> u128 {
+    let r = x.unsigned_abs() % m;
+    if x >= 0 || r == 0 {
+        r
+    } else {
+        m - r
+    }
+}
+
+/// `a + b` modulo `m`, for `a, b < m`.
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// `a * b` modulo `m`, for `a, b < m`.
+fn mul_mod(mut a: u128, mut b: u128, m: u128) -> u128 {
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    result
+}
+
+/// The inverse of `a` modulo `m`, for coprime `a < m`.
+fn inverse_mod(a: u128, m: u128) -> u128 {
+    // Extended Euclid on the coefficients of `a`, kept modulo `m`
+    let (mut r0, mut r1) = (m, a);
+    let (mut t0, mut t1) = (0, 1 % m);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, add_mod(t0, m - mul_mod(q % m, t1, m), m) % m);
+    }
+    t0
+}
+
+impl Congruence {
+    /// `modulus·Z + residue`, with a modulus above `2^127` replaced by its largest power of
+    /// two divisor.
+    pub fn new(modulus: u128, residue: i128) -> Self {
+        let modulus = if modulus > MAX_MODULUS {
+            1 << modulus.trailing_zeros()
+        } else {
+            modulus
+        };
+        if modulus == 0 {
+            return Self::constant(residue);
+        }
+        Congruence::Class {
+            modulus,
+            residue: mod_of(residue, modulus) as i128,
+        }
+    }
+
+    pub fn bottom() -> Self {
+        Congruence::Bottom
+    }
+
+    pub fn top() -> Self {
+        Self::new(1, 0)
+    }
+
+    pub fn constant(value: i128) -> Self {
+        Congruence::Class {
+            modulus: 0,
+            residue: value,
+        }
+    }
+
+    pub fn is_bottom(&self) -> bool {
+        *self == Congruence::Bottom
+    }
+
+    pub fn is_top(&self) -> bool {
+        matches!(self, Congruence::Class { modulus: 1, .. })
+    }
+
+    /// The single member, if there is one.
+    pub fn as_constant(&self) -> Option<i128> {
+        match *self {
+            Congruence::Class {
+                modulus: 0,
+                residue,
+            } => Some(residue),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, x: i128) -> bool {
+        match *self {
+            Congruence::Bottom => false,
+            Congruence::Class {
+                modulus: 0,
+                residue,
+            } => x == residue,
+            Congruence::Class { modulus, residue } => mod_of(x, modulus) == residue as u128,
+        }
+    }
+
+    /// Whether every member of `self` is in `other`.
+    pub fn leq(&self, other: &Self) -> bool {
+        match (*self, *other) {
+            (Congruence::Bottom, _) => true,
+            (_, Congruence::Bottom) => false,
+            (
+                Congruence::Class {
+                    modulus: m,
+                    residue,
+                },
+                Congruence::Class { modulus: n, .. },
+            ) => (n == 0 && m == 0 || n != 0 && m % n == 0) && other.contains(residue),
+        }
+    }
+
+    /// The modulus and residue, the latter modulo `modulus` when it is not 0.
+    fn class(&self) -> Option<(u128, i128)> {
+        match *self {
+            Congruence::Bottom => None,
+            Congruence::Class { modulus, residue } => Some((modulus, residue)),
+        }
+    }
+
+    /// The smallest congruence containing both: the modulus divides both moduli and the
+    /// difference of the residues.
+    pub fn join(&self, other: &Self) -> Self {
+        let (Some((m, r)), Some((n, s))) = (self.class(), other.class()) else {
+            return if self.is_bottom() { *other } else { *self };
+        };
+        Self::new(gcd(gcd(m, n), r.abs_diff(s)), r)
+    }
+
+    /// The integers in both, by the Chinese remainder theorem. When the least common
+    /// multiple of the moduli exceeds `2^127`, the operand with the larger modulus is kept.
+    pub fn meet(&self, other: &Self) -> Self {
+        let (Some((m, r)), Some((n, s))) = (self.class(), other.class()) else {
+            return Self::bottom();
+        };
+        if m == 0 || n == 0 {
+            let (constant, class) = if m == 0 { (r, other) } else { (s, self) };
+            return if class.contains(constant) {
+                Self::constant(constant)
+            } else {
+                Self::bottom()
+            };
+        }
+        let g = gcd(m, n);
+        if r.abs_diff(s) % g != 0 {
+            return Self::bottom();
+        }
+        let Some(lcm) = (m / g).checked_mul(n).filter(|&lcm| lcm <= MAX_MODULUS) else {
+            return if m >= n { *self } else { *other };
+        };
+        // x = r + m·k with m·k = s - r modulo n, i.e. k = (s - r)/g · (m/g)^-1 modulo n/g
+        let (m_g, n_g) = (m / g, n / g);
+        if n_g == 1 {
+            return *self;
+        }
+        let d = mod_of(s - r, n) / g % n_g;
+        let k = mul_mod(d, inverse_mod(m_g % n_g, n_g), n_g);
+        let x = add_mod(mod_of(r, lcm), mul_mod(m, k, lcm), lcm);
+        Self::new(lcm, x as i128)
+    }
+
+    /// A congruence for an exact result that is computed as `wrapped` when it overflows,
+    /// which is equal modulo `2^128`.
+    fn exact(result: Option<i128>, wrapped: i128) -> Self {
+        match result {
+            Some(value) => Self::constant(value),
+            None => Self::new(MAX_MODULUS, wrapped),
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let (Some((m, r)), Some((n, s))) = (self.class(), other.class()) else {
+            return Self::bottom();
+        };
+        if m == 0 && n == 0 {
+            return Self::exact(r.checked_add(s), r.wrapping_add(s));
+        }
+        let g = gcd(m, n);
+        Self::new(g, add_mod(mod_of(r, g), mod_of(s, g), g) as i128)
+    }
+
+    pub fn neg(&self) -> Self {
+        match self.class() {
+            None => Self::bottom(),
+            Some((0, r)) => Self::exact(r.checked_neg(), r.wrapping_neg()),
+            Some((m, r)) => Self::new(m, (m - r as u128) as i128),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    /// `(m·Z + r)(n·Z + s)` is in `gcd(m·n, m·s, n·r)·Z + r·s`.
+    pub fn mul(&self, other: &Self) -> Self {
+        let (Some((m, r)), Some((n, s))) = (self.class(), other.class()) else {
+            return Self::bottom();
+        };
+        if m == 0 && n == 0 {
+            return Self::exact(r.checked_mul(s), r.wrapping_mul(s));
+        }
+        // A product that overflows is replaced by one of its factors, which divides it
+        let term = |a: u128, b: u128| a.checked_mul(b).unwrap_or(a);
+        let modulus = gcd(
+            gcd(term(m, n), term(m, s.unsigned_abs())),
+            term(n, r.unsigned_abs()),
+        );
+        if modulus == 0 {
+            // One operand is the constant 0
+            return Self::constant(0);
+        }
+        let residue = mul_mod(mod_of(r, modulus), mod_of(s, modulus), modulus);
+        Self::new(modulus, residue as i128)
+    }
+
+    /// Division rounding toward zero. Only exact divisions by a constant are tracked.
+    pub fn div(&self, other: &Self) -> Self {
+        let (Some((m, r)), Some((n, d))) = (self.class(), other.class()) else {
+            return Self::bottom();
+        };
+        if n != 0 {
+            return Self::top();
+        }
+        if m == 0 {
+            // Division by zero and `MIN / -1` panic
+            return r.checked_div(d).map_or(Self::bottom(), Self::constant);
+        }
+        if d == 0 {
+            return Self::bottom();
+        }
+        let divisor = d.unsigned_abs();
+        if m % divisor == 0 && r.unsigned_abs() % divisor == 0 {
+            Self::new(m / divisor, r / d)
+        } else {
+            Self::top()
+        }
+    }
+
+    /// `x % y` is `x - y·q`, so it is equal to `x` modulo any divisor of every `y`.
+    pub fn rem(&self, other: &Self) -> Self {
+        let (Some((m, r)), Some((n, s))) = (self.class(), other.class()) else {
+            return Self::bottom();
+        };
+        if n == 0 && s == 0 {
+            return Self::bottom();
+        }
+        if m == 0 && n == 0 {
+            return r.checked_rem(s).map_or(Self::bottom(), Self::constant);
+        }
+        Self::new(gcd(m, gcd(n, s.unsigned_abs())), r)
+    }
+
+    /// The number of low bits known and their value.
+    fn low_bits(&self) -> Option<(u32, u128)> {
+        let (m, r) = self.class()?;
+        if m == 0 {
+            return Some((128, r as u128));
+        }
+        let known = m.trailing_zeros();
+        Some((known, r as u128 & (pow2(known) - 1)))
+    }
+
+    /// A bitwise operation on the low bits known in both operands.
+    fn bitwise(&self, other: &Self, op: impl Fn(i128, i128) -> i128) -> Self {
+        let (Some((known, a)), Some((other_known, b))) = (self.low_bits(), other.low_bits()) else {
+            return Self::bottom();
+        };
+        if known == 128 && other_known == 128 {
+            return Self::constant(op(a as i128, b as i128));
+        }
+        let known = known.min(other_known);
+        Self::new(pow2(known), op(a as i128, b as i128))
+    }
+
+    /// The number of trailing zeros of every member.
+    fn trailing_zeros(&self) -> u32 {
+        match self.class() {
+            None => 128,
+            Some((0, r)) => r.trailing_zeros(),
+            Some((m, r)) => m.trailing_zeros().min(r.trailing_zeros()),
+        }
+    }
+
+    pub fn bitand(&self, other: &Self) -> Self {
+        let result = self.bitwise(other, |a, b| a & b);
+        // Each trailing zero of either operand is a zero of the result
+        let zeros = self.trailing_zeros().max(other.trailing_zeros());
+        if result.is_bottom() || result.as_constant().is_some() || zeros == 0 {
+            return result;
+        }
+        if zeros == 128 {
+            return Self::constant(0);
+        }
+        result.meet(&Self::new(pow2(zeros), 0))
+    }
+
+    pub fn bitor(&self, other: &Self) -> Self {
+        self.bitwise(other, |a, b| a | b)
+    }
+
+    pub fn bitxor(&self, other: &Self) -> Self {
+        self.bitwise(other, |a, b| a ^ b)
+    }
+
+    /// `self << amount` in a type of `bits` bits, with the amount masked unless it is
+    /// `checked`. Only constant amounts are tracked.
+    pub fn shl(&self, amount: &Self, bits: u16, checked: bool) -> Self {
+        if self.is_bottom() || amount.is_bottom() {
+            return Self::bottom();
+        }
+        let Some(amount) = amount.as_constant() else {
+            return Self::top();
+        };
+        let amount = if (0..bits as i128).contains(&amount) {
+            amount as u32
+        } else if checked {
+            return Self::bottom();
+        } else {
+            (amount & (bits as i128 - 1)) as u32
+        };
+        if amount >= 127 {
+            return Self::top();
+        }
+        self.mul(&Self::constant(1 << amount))
+    }
+
+    /// `!self` in `ty`: `-x - 1` for signed and `MAX - x` for unsigned types.
+    pub fn not_in(&self, ty: IntType) -> Self {
+        if ty.signed {
+            return self.neg().sub(&Self::constant(1));
+        }
+        Self::constant(ty.max_value()).sub(self).fit(ty, false)
+    }
+
+    /// The result of an operation of type `ty`. Results outside of the type wrap around
+    /// modulo `2^bits`, unless the operation is `checked` and never produces them.
+    pub fn fit(&self, ty: IntType, checked: bool) -> Self {
+        let Some((m, r)) = self.class() else {
+            return *self;
+        };
+        if checked {
+            return *self;
+        }
+        let (min, max) = (ty.min_value::<i128>(), ty.max_value::<i128>());
+        if m == 0 && min <= r && r <= max {
+            return *self;
+        }
+        if m == 0 && ty.bits < 128 {
+            // The constant wrapped into the type
+            let value = mod_of(r, pow2(ty.bits as u32)) as i128;
+            return Self::constant(if value > max {
+                value - (max - min) - 1
+            } else {
+                value
+            });
+        }
+        Self::new(gcd(m, pow2(ty.bits as u32)), r)
+    }
+
+    /// A cast from `from` to `to`, which keeps the value if `to` holds every value of
+    /// `from` and the value modulo `2^bits` of the narrower type otherwise.
+    pub fn cast(&self, from: IntType, to: IntType) -> Self {
+        if to.min_value::<i128>() <= from.min_value() && from.max_value::<i128>() <= to.max_value()
+        {
+            return *self;
+        }
+        match self.class() {
+            Some((0, _)) => self.fit(to, false),
+            Some((m, r)) => Self::new(gcd(m, pow2(from.bits.min(to.bits) as u32)), r),
+            None => *self,
+        }
+    }
+
+    /// The constant of a range of one value, or top.
+    pub fn from_range<T: PrimInt>(range: &Range<T>) -> Self {
+        if range.is_empty() {
+            return Self::bottom();
+        }
+        match range.get_lower().to_i128() {
+            Some(value) if range.is_regular() && range.get_lower() == range.get_upper() => {
+                Self::constant(value)
+            }
+            _ => Self::top(),
+        }
+    }
+}
+
+/// A range and a congruence that hold the same values.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StridedRange<T: PartialOrd + Clone + Bounded> {
+    pub range: Range<T>,
+    pub congruence: Congruence,
+}
+
+impl<T> StridedRange<T>
+where
+    T: PrimInt + FromPrimitive,
+{
+    /// The values in both `range` and `congruence`, with each reduced by the other.
+    pub fn new(range: Range<T>, congruence: Congruence) -> Self {
+        let empty = Self {
+            range: Range::new(T::min_value(), T::max_value(), RangeType::Empty),
+            congruence: Congruence::bottom(),
+        };
+        let Some((m, r)) = congruence.class() else {
+            return empty;
+        };
+        if range.is_empty() {
+            return empty;
+        }
+        let bounds = range.get_lower().to_i128().zip(range.get_upper().to_i128());
+        let (Some((lower, upper)), true) = (bounds, range.is_regular()) else {
+            return Self { range, congruence };
+        };
+        // The first member at or above `lower` and the last one at or below `upper`
+        let (lower, upper) = if m == 0 {
+            (r.max(lower), r.min(upper))
+        } else {
+            let up = add_mod(mod_of(r, m), (m - mod_of(lower, m)) % m, m);
+            let down = add_mod(mod_of(upper, m), (m - mod_of(r, m)) % m, m);
+            match (
+                lower.checked_add_unsigned(up),
+                upper.checked_sub_unsigned(down),
+            ) {
+                (Some(lower), Some(upper)) => (lower, upper),
+                _ => return empty,
+            }
+        };
+        if lower > upper {
+            return empty;
+        }
+        let congruence = if lower == upper {
+            Congruence::constant(lower)
+        } else {
+            congruence
+        };
+        Self {
+            range: Range::new(
+                T::from_i128(lower).unwrap(),
+                T::from_i128(upper).unwrap(),
+                RangeType::Regular,
+            ),
+            congruence,
+        }
+    }
+
+    /// The range with the bounds that reduction moved in from the bounds of `ty` put back,
+    /// so that narrowing still sees where widening jumped to.
+    fn unreduced(&self, ty: IntType) -> Range<T> {
+        let full = Range::of_type(ty);
+        let reduced = Self::new(full.clone(), self.congruence).range;
+        let mut range = self.range.clone();
+        if range.is_regular() && reduced.is_regular() {
+            if range.get_lower() == reduced.get_lower() {
+                range.set_lower(full.get_lower());
+            }
+            if range.get_upper() == reduced.get_upper() {
+                range.set_upper(full.get_upper());
+            }
+        }
+        range
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self::new(
+            self.range.add(&other.range),
+            self.congruence.add(&other.congruence),
+        )
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        Self::new(
+            self.range.sub(&other.range),
+            self.congruence.sub(&other.congruence),
+        )
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::new(
+            self.range.mul(&other.range),
+            self.congruence.mul(&other.congruence),
+        )
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::new(self.range.neg(), self.congruence.neg())
+    }
+
+    pub fn fit(&self, ty: IntType, checked: bool) -> Self {
+        Self::new(
+            self.range.fit(ty, checked),
+            self.congruence.fit(ty, checked),
+        )
+    }
+}
+
+impl AbstractDomain for Congruence {
+    fn top(_ty: IntType) -> Self {
+        Self::top()
+    }
+
+    fn bottom() -> Self {
+        Self::bottom()
+    }
+
+    fn is_bottom(&self) -> bool {
+        Congruence::is_bottom(self)
+    }
+
+    fn constant(value: i128) -> Self {
+        Self::constant(value)
+    }
+
+    fn interval(lower: Option<i128>, upper: Option<i128>) -> Self {
+        match (lower, upper) {
+            (Some(lower), Some(upper)) if lower > upper => Self::bottom(),
+            (Some(lower), Some(upper)) if lower == upper => Self::constant(lower),
+            _ => Self::top(),
+        }
+    }
+
+    fn satisfying(op: CmpOp, bound: &Self) -> Self {
+        match op {
+            _ if bound.is_bottom() => Self::bottom(),
+            CmpOp::Eq => *bound,
+            _ => Self::top(),
+        }
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        Congruence::join(self, other)
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        Congruence::meet(self, other)
+    }
+
+    // Chains of congruences are finite: each step up divides the modulus
+    fn widen(&self, new: &Self, _thresholds: &[i128]) -> Self {
+        Congruence::join(self, new)
+    }
+
+    fn narrow(&self, new: &Self, _ty: IntType) -> Self {
+        Congruence::meet(self, new)
+    }
+
+    fn crop(&self, new: &Self, _state: Sign) -> Self {
+        Congruence::meet(self, new)
+    }
+
+    fn unary(&self, op: UnOp, ty: IntType) -> Self {
+        match op {
+            UnOp::Neg => self.neg().fit(ty, false),
+            UnOp::Not => self.not_in(ty),
+        }
+    }
+
+    fn binary(&self, op: BinOp, rhs: &Self, ty: IntType, checked: bool) -> Self {
+        match op {
+            BinOp::Add => self.add(rhs).fit(ty, checked),
+            BinOp::Sub => self.sub(rhs).fit(ty, checked),
+            BinOp::Mul => self.mul(rhs).fit(ty, checked),
+            BinOp::Div => self.div(rhs).fit(ty, true),
+            BinOp::Rem => self.rem(rhs).fit(ty, true),
+            BinOp::BitAnd => self.bitand(rhs).fit(ty, false),
+            BinOp::BitOr => self.bitor(rhs).fit(ty, false),
+            BinOp::BitXor => self.bitxor(rhs).fit(ty, false),
+            BinOp::Shl => self.shl(rhs, ty.bits, checked).fit(ty, false),
+            BinOp::Shr if self.is_bottom() || rhs.is_bottom() => Self::bottom(),
+            BinOp::Shr => Self::top(),
+        }
+    }
+
+    // A value of unknown type is taken to wrap around
+    fn cast(&self, from: Option<IntType>, to: IntType) -> Self {
+        match from {
+            Some(from) => Congruence::cast(self, from, to),
+            None => self.fit(to, false),
+        }
+    }
+}
+
+// The product is kept reduced by the transfer functions. Joins need no reduction, as the
+// bounds of both ranges are members of the joined congruence.
+impl<T> AbstractDomain for StridedRange<T>
+where
+    T: PrimInt + FromPrimitive + fmt::Debug,
+{
+    fn top(ty: IntType) -> Self {
+        Self {
+            range: Range::of_type(ty),
+            congruence: Congruence::top(),
+        }
+    }
+
+    fn bottom() -> Self {
+        Self {
+            range: AbstractDomain::bottom(),
+            congruence: Congruence::bottom(),
+        }
+    }
+
+    fn unknown() -> Self {
+        Self {
+            range: AbstractDomain::unknown(),
+            congruence: Congruence::top(),
+        }
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.range.is_empty() || self.congruence.is_bottom()
+    }
+
+    fn is_unknown(&self) -> bool {
+        self.range.is_unknown()
+    }
+
+    fn constant(value: i128) -> Self {
+        let range: Range<T> = AbstractDomain::constant(value);
+        let congruence = Congruence::from_range(&range);
+        Self::new(range, congruence)
+    }
+
+    fn interval(lower: Option<i128>, upper: Option<i128>) -> Self {
+        let range: Range<T> = AbstractDomain::interval(lower, upper);
+        let congruence = Congruence::from_range(&range);
+        Self::new(range, congruence)
+    }
+
+    fn satisfying(op: CmpOp, bound: &Self) -> Self {
+        Self::new(
+            AbstractDomain::satisfying(op, &bound.range),
+            AbstractDomain::satisfying(op, &bound.congruence),
+        )
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        if self.is_unknown() || self.is_bottom() {
+            return other.clone();
+        }
+        if other.is_unknown() || other.is_bottom() {
+            return self.clone();
+        }
+        Self {
+            range: self.range.join(&other.range),
+            congruence: self.congruence.join(&other.congruence),
+        }
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        if self.is_unknown() {
+            return other.clone();
+        }
+        if other.is_unknown() {
+            return self.clone();
+        }
+        Self::new(
+            self.range.meet(&other.range),
+            self.congruence.meet(&other.congruence),
+        )
+    }
+
+    fn widen(&self, new: &Self, thresholds: &[i128]) -> Self {
+        if self.is_unknown() || self.is_bottom() {
+            return new.clone();
+        }
+        Self {
+            range: AbstractDomain::widen(&self.range, &new.range, thresholds),
+            congruence: self.congruence.join(&new.congruence),
+        }
+    }
+
+    fn narrow(&self, new: &Self, ty: IntType) -> Self {
+        Self::new(
+            self.unreduced(ty).narrow_in(&new.range, ty),
+            self.congruence.meet(&new.congruence),
+        )
+    }
+
+    fn growth(&self, new: &Self) -> Sign {
+        self.range.growth(&new.range)
+    }
+
+    fn crop(&self, new: &Self, state: Sign) -> Self {
+        Self::new(
+            self.range.crop(&new.range, state),
+            self.congruence.meet(&new.congruence),
+        )
+    }
+
+    fn thresholds(&self) -> Vec<i128> {
+        self.range.thresholds()
+    }
+
+    fn unary(&self, op: UnOp, ty: IntType) -> Self {
+        Self::new(self.range.unary(op, ty), self.congruence.unary(op, ty))
+    }
+
+    fn binary(&self, op: BinOp, rhs: &Self, ty: IntType, checked: bool) -> Self {
+        Self::new(
+            self.range.binary(op, &rhs.range, ty, checked),
+            self.congruence.binary(op, &rhs.congruence, ty, checked),
+        )
+    }
+
+    fn cast(&self, from: Option<IntType>, to: IntType) -> Self {
+        Self::new(
+            AbstractDomain::cast(&self.range, from, to),
+            AbstractDomain::cast(&self.congruence, from, to),
+        )
+    }
+}
@@ -1,213 +1,283 @@
-use num_traits::Bounded;
-use rustc_middle::mir::{BasicBlock, Local, LocalDecl, Place, Statement};
 use std::cmp::PartialEq;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
-use super::range::Range;
+use super::abstract_domain::AbstractDomain;
+use super::range::IntType;
+use super::sign::Sign;
+use crate::ir::ir::{BinOp, BlockId, CmpOp, UnOp, ValueId};
 
-#[derive(Debug)]
-pub enum IntervalType<'tcx, T: PartialOrd + Clone + Bounded> {
-    Basic(BasicInterval<T>),
-    Symb(SymbInterval<'tcx, T>), // Using 'static for simplicity, adjust lifetime as needed
+#[derive(Debug, Clone)]
+pub enum IntervalType<D: AbstractDomain> {
+    Basic(BasicInterval<D>),
+    Symb(SymbInterval<D>),
+}
+
+impl<D: AbstractDomain> IntervalType<D> {
+    pub fn get_range(&self) -> &D {
+        match self {
+            IntervalType::Basic(basic) => basic.get_range(),
+            IntervalType::Symb(symb) => symb.get_range(),
+        }
+    }
+
+    pub fn set_range(&mut self, new_range: D) {
+        match self {
+            IntervalType::Basic(basic) => basic.set_range(new_range),
+            IntervalType::Symb(symb) => symb.set_range(new_range),
+        }
+    }
 }
 
-trait BasicIntervalTrait<T: PartialOrd + Clone + Bounded> {
+pub trait BasicIntervalTrait<D: AbstractDomain> {
     // fn get_value_id(&self) -> IntervalId;
-    fn get_range(&self) -> &Range<T>;
-    fn set_range(&mut self, new_range: Range<T>);
+    fn get_range(&self) -> &D;
+    fn set_range(&mut self, new_range: D);
 }
 
 #[derive(Debug, Clone)]
-pub struct BasicInterval<T: PartialOrd + Clone> {
-    range: Range<T>,
+pub struct BasicInterval<D: AbstractDomain> {
+    range: D,
 }
 
-impl<T: PartialOrd + Clone> BasicInterval<T> {
-    pub fn new(range: Range<T>) -> Self {
+impl<D: AbstractDomain> BasicInterval<D> {
+    pub fn new(range: D) -> Self {
         Self { range }
     }
 }
 
-impl<T: PartialOrd + Clone + Bounded> BasicIntervalTrait<T> for BasicInterval<T> {
+impl<D: AbstractDomain> BasicIntervalTrait<D> for BasicInterval<D> {
     // fn get_value_id(&self) -> IntervalId {
     //     IntervalId::BasicIntervalId
     // }
 
-    fn get_range(&self) -> &Range<T> {
+    fn get_range(&self) -> &D {
         &self.range
     }
 
-    fn set_range(&mut self, new_range: Range<T>) {
+    fn set_range(&mut self, new_range: D) {
         self.range = new_range;
-        if self.range.get_lower() > self.range.get_upper() {
-            self.range.set_empty();
-        }
     }
 }
 
-#[derive(Debug)]
-pub struct SymbInterval<'tcx, T: PartialOrd + Clone + Bounded> {
-    range: Range<T>,
-    symbound: &'tcx Place<'tcx>,
-    predicate: bool,
+#[derive(Debug, Clone)]
+pub struct SymbInterval<D: AbstractDomain> {
+    range: D,
+    symbound: ValueId,
+    // The constrained variable satisfies `v predicate symbound`
+    predicate: CmpOp,
 }
 
-impl<'tcx, T: PartialOrd + Clone + Bounded> SymbInterval<'tcx, T> {
-    pub fn new(range: Range<T>, symbound: &'tcx Place<'tcx>, predicate: bool) -> Self {
+impl<D: AbstractDomain> SymbInterval<D> {
+    pub fn new(range: D, symbound: ValueId, predicate: CmpOp) -> Self {
         Self {
-            range: range,
+            range,
             symbound,
             predicate,
         }
     }
 
-    pub fn get_operation(&self) -> &bool {
-        &self.predicate
+    pub fn get_operation(&self) -> CmpOp {
+        self.predicate
     }
 
-    pub fn get_bound(&self) -> &Place<'tcx> {
-        &self.symbound
+    pub fn get_bound(&self) -> ValueId {
+        self.symbound
     }
 
-    pub fn fix_intersects(&self, symbound: &Place, sink: &Place) {
-        println!(
-            "Fixing intersects with bound {:?} and sink {:?}",
-            symbound, sink
-        );
+    /// The values the constrained variable is limited to, given the current value of the bound.
+    pub fn fix_intersects(&self, bound: &D) -> D {
+        D::satisfying(self.predicate, bound)
     }
 }
 
-impl<'tcx, T: PartialOrd + Clone + Bounded> BasicIntervalTrait<T> for SymbInterval<'tcx, T> {
+impl<D: AbstractDomain> BasicIntervalTrait<D> for SymbInterval<D> {
     // fn get_value_id(&self) -> IntervalId {
     //     IntervalId::SymbIntervalId
     // }
 
-    fn get_range(&self) -> &Range<T> {
+    fn get_range(&self) -> &D {
         &self.range
     }
 
-    fn set_range(&mut self, new_range: Range<T>) {
+    fn set_range(&mut self, new_range: D) {
         self.range = new_range;
     }
 }
 
 // Define the basic operation trait
-pub trait Operation<T: PartialOrd + Clone + Bounded> {
+pub trait Operation<D: AbstractDomain> {
     fn get_value_id(&self) -> u32; // Placeholder for an operation identifier
-    fn eval(&self) -> Range<T>; // Method to evaluate the result of the operation
-    fn print(&self, os: &mut dyn fmt::Write);
+    fn eval(&self, vars: &VarNodes<D>) -> D; // Method to evaluate the result of the operation
+    fn print(&self, os: &mut dyn fmt::Write) -> fmt::Result;
+}
+
+// An input of an operation
+#[derive(Debug, Clone)]
+pub enum OpSource<D: AbstractDomain> {
+    Var(ValueId),
+    Const(D),
+}
+
+impl<D: AbstractDomain> OpSource<D> {
+    // The current range of the source; variables without a node are unknown
+    pub fn get_range(&self, vars: &VarNodes<D>) -> D {
+        match self {
+            OpSource::Var(v) => match vars.get(v) {
+                Some(node) => node.get_range().clone(),
+                None => D::unknown(),
+            },
+            OpSource::Const(range) => range.clone(),
+        }
+    }
+}
+
+// What an operation computes from its sources
+#[derive(Debug, Clone)]
+pub enum OpKind<D: AbstractDomain> {
+    Copy(OpSource<D>),
+    Unary(UnOp, OpSource<D>),
+    // The last field is `InstKind::Binary::checked`
+    Binary(BinOp, OpSource<D>, OpSource<D>, bool),
+    Cast(OpSource<D>),
+    Phi(Vec<OpSource<D>>),
+    Sigma(ValueId),
+    // Calls and anything else the analysis does not model
+    Unknown,
 }
 
 // Define the BasicOp struct
-pub struct BasicOp<'tcx, T: PartialOrd + Clone + Bounded> {
-    pub intersect: &'tcx mut BasicInterval<T>, // The range associated with the operation
-    pub sink: &'tcx mut VarNode<'tcx, T>,      // The target node storing the result
-    pub inst: &'tcx Statement<'tcx>,           // The instruction that originated this operation
+pub struct BasicOp<D: AbstractDomain> {
+    pub intersect: IntervalType<D>, // The range associated with the operation
+    pub sink: ValueId,              // The target node storing the result
+    pub inst: (BlockId, usize),     // The instruction that originated this operation
+    pub kind: OpKind<D>,            // How the result is computed from the sources
+    pub ty: IntType,                // The type of the sink
 }
 
-impl<'tcx, T: PartialOrd + Clone + Bounded> BasicOp<'tcx, T> {
+impl<D: AbstractDomain> BasicOp<D> {
     // Constructor for creating a new BasicOp
     pub fn new(
-        intersect: &'tcx mut BasicInterval<T>,
-        sink: &'tcx mut VarNode<'tcx, T>,
-        inst: &'tcx Statement<'tcx>,
+        intersect: IntervalType<D>,
+        sink: ValueId,
+        inst: (BlockId, usize),
+        kind: OpKind<D>,
+        ty: IntType,
     ) -> Self {
         BasicOp {
             intersect,
             sink,
             inst,
+            kind,
+            ty,
         }
     }
 
-    pub fn get_instruction(&self) -> Option<&Statement<'tcx>> {
-        Some(self.inst)
+    // Returns the block and index of the instruction that originated this operation
+    pub fn get_instruction(&self) -> (BlockId, usize) {
+        self.inst
     }
 
-    pub fn fix_intersects(&mut self, _v: &VarNode<T>) {}
-
-    pub fn set_intersect(&mut self, new_intersect: Range<T>) {
+    pub fn set_intersect(&mut self, new_intersect: D) {
         self.intersect.set_range(new_intersect);
     }
 
-    pub fn get_sink(&self) -> &VarNode<'tcx, T> {
+    // Returns the target of the operation (sink)
+    pub fn get_sink(&self) -> ValueId {
         self.sink
     }
-    // Returns the instruction that originated this operation
 
-    // Returns the target of the operation (sink), mutable version
-    pub fn get_sink_mut(&mut self) -> &mut VarNode<'tcx, T> {
-        &mut self.sink
+    // Narrows a symbolic intersect by the current value of its bound
+    pub fn fix_intersects(&mut self, bound: &VarNode<D>) {
+        if let IntervalType::Symb(symb) = &self.intersect {
+            let range = symb.fix_intersects(bound.get_range());
+            self.intersect.set_range(range);
+        }
     }
 }
 
 // Implement the Operation trait for BasicOp
-impl<'tcx, T: PartialOrd + Clone + Bounded> Operation<T> for BasicOp<'tcx, T> {
+impl<D: AbstractDomain> Operation<D> for BasicOp<D> {
     fn get_value_id(&self) -> u32 {
         0 // Placeholder implementation
     }
 
-    fn eval(&self) -> Range<T> {
-        // Placeholder for evaluating the range
-        Range::default() // Assuming Range<T> implements Default
+    fn eval(&self, vars: &VarNodes<D>) -> D {
+        match &self.kind {
+            OpKind::Copy(source) => source.get_range(vars),
+            OpKind::Unary(op, source) => source.get_range(vars).unary(*op, self.ty),
+            OpKind::Binary(op, lhs, rhs, checked) => {
+                let (lhs, rhs) = (lhs.get_range(vars), rhs.get_range(vars));
+                lhs.binary(*op, &rhs, self.ty, *checked)
+            }
+            OpKind::Phi(sources) => sources
+                .iter()
+                .fold(D::bottom(), |acc, source| acc.join(&source.get_range(vars))),
+            OpKind::Sigma(source) => {
+                let source = OpSource::Var(*source).get_range(vars);
+                if source.is_unknown() {
+                    return source;
+                }
+                source.meet(self.intersect.get_range())
+            }
+            OpKind::Cast(source) => {
+                let from = match source {
+                    OpSource::Var(v) => vars.get(v).and_then(|node| node.get_type()),
+                    OpSource::Const(_) => None,
+                };
+                source.get_range(vars).cast(from, self.ty)
+            }
+            OpKind::Unknown => D::top(self.ty),
+        }
     }
 
-    fn print(&self, os: &mut dyn fmt::Write) {}
+    fn print(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        write!(os, "{:?} = {:?}", self.sink, self.kind)?;
+        if let IntervalType::Symb(symb) = &self.intersect {
+            write!(os, " ∩ ({:?} {:?})", symb.get_operation(), symb.get_bound())?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct VarNode<'tcx, T: PartialOrd + Clone + Bounded> {
+pub struct VarNode<D: AbstractDomain> {
     // The program variable which is represented.
-    v: &'tcx Place<'tcx>,
+    v: ValueId,
+    // Its integer type, if it has one.
+    ty: Option<IntType>,
     // A Range associated to the variable.
-    interval: Range<T>,
-    // Used by the crop meet operator.
-    abstract_state: char,
+    interval: D,
+    // The bounds that widening moved, which the crop meet operator refines.
+    abstract_state: Sign,
 }
-impl<'tcx, T: PartialOrd + Clone + Bounded> VarNode<'tcx, T> {
-    pub fn new(v: &'tcx Place<'tcx>) -> Self {
+impl<D: AbstractDomain> VarNode<D> {
+    pub fn new(v: ValueId, ty: Option<IntType>) -> Self {
         Self {
             v,
-            interval: Range::default(),
-            abstract_state: '?',
+            ty,
+            interval: D::unknown(),
+            abstract_state: Sign::Zero,
         }
     }
 
-    /// Initializes the value of the node.
-    pub fn init(&mut self, outside: bool) {
-        let value = self.get_value();
-
-        // if let Some(ci) = value.as_constant_int() {
-        //     let tmp = ci.get_value();
-        //     if tmp.bits() < MAX_BIT_INT {
-        //         self.set_range(Range::new(
-        //             tmp.extend_bits(MAX_BIT_INT),
-        //             tmp.extend_bits(MAX_BIT_INT),
-        //         ));
-        //     } else {
-        //         self.set_range(Range::new(tmp, tmp));
-        //     }
-        // } else {
-        //     if !outside {
-        //         self.set_range(Range::new(MIN, MAX));
-        //     } else {
-        //         self.set_range(Range::new(MIN, MAX));
-        //     }
-        // }
-    }
-
     /// Returns the range of the variable represented by this node.
-    pub fn get_range(&self) -> &Range<T> {
+    pub fn get_range(&self) -> &D {
         &self.interval
     }
 
     /// Returns the variable represented by this node.
-    pub fn get_value(&self) -> &Place<'tcx> {
-        &self.v
+    pub fn get_value(&self) -> ValueId {
+        self.v
+    }
+
+    /// Returns the integer type of the variable, if it has one.
+    pub fn get_type(&self) -> Option<IntType> {
+        self.ty
     }
 
     /// Changes the status of the variable represented by this node.
-    pub fn set_range(&mut self, new_interval: Range<T>) {
+    pub fn set_range(&mut self, new_interval: D) {
         self.interval = new_interval;
 
         // Check if lower bound is greater than upper bound. If it is,
@@ -217,35 +287,42 @@ impl<'tcx, T: PartialOrd + Clone + Bounded> VarNode<'tcx, T> {
         // }
     }
 
-    /// Pretty print.
-    pub fn print(&self, os: &mut dyn std::io::Write) {
-        // Implementation of pretty printing using the `os` writer.
+    /// Pretty print as `%3: [0, 9]`.
+    pub fn print(&self, os: &mut dyn std::io::Write) -> std::io::Result<()> {
+        writeln!(os, "{:?}: {:?}", self.v, self.interval)
+    }
+
+    /// Every value the variable can hold; values without an integer type may hold any
+    /// integer.
+    pub fn full_range(&self) -> D {
+        D::top(self.ty.unwrap_or(IntType::new(128, true)))
     }
 
-    pub fn get_abstract_state(&self) -> char {
+    pub fn get_abstract_state(&self) -> Sign {
         self.abstract_state
     }
 
-    /// The possible states are '0', '+', '-', and '?'.
-    pub fn store_abstract_state(&mut self) {
-        // Implementation of storing the abstract state.
+    /// The possible states are '0', '+', '-', and '?': which bounds of the range widening
+    /// moved, and may be cropped.
+    pub fn store_abstract_state(&mut self, state: Sign) {
+        self.abstract_state = state;
     }
 }
 #[derive(Debug)]
-pub struct ValueBranchMap<'tcx, T: PartialOrd + Clone + Bounded> {
-    v: &'tcx Place<'tcx>,         // The value associated with the branch
-    bb_true: &'tcx BasicBlock,    // True side of the branch
-    bb_false: &'tcx BasicBlock,   // False side of the branch
-    itv_t: IntervalType<'tcx, T>, // Interval for the true side
-    itv_f: IntervalType<'tcx, T>,
+pub struct ValueBranchMap<D: AbstractDomain> {
+    v: ValueId,             // The value associated with the branch
+    bb_true: BlockId,       // True side of the branch
+    bb_false: BlockId,      // False side of the branch
+    itv_t: IntervalType<D>, // Interval for the true side
+    itv_f: IntervalType<D>,
 }
-impl<'tcx, T: PartialOrd + Clone + Bounded> ValueBranchMap<'tcx, T> {
+impl<D: AbstractDomain> ValueBranchMap<D> {
     pub fn new(
-        v: &'tcx Place<'tcx>,
-        bb_true: &'tcx BasicBlock,
-        bb_false: &'tcx BasicBlock,
-        itv_t: IntervalType<'tcx, T>,
-        itv_f: IntervalType<'tcx, T>,
+        v: ValueId,
+        bb_true: BlockId,
+        bb_false: BlockId,
+        itv_t: IntervalType<D>,
+        itv_f: IntervalType<D>,
     ) -> Self {
         Self {
             v,
@@ -257,31 +334,31 @@ impl<'tcx, T: PartialOrd + Clone + Bounded> ValueBranchMap<'tcx, T> {
     }
 
     /// Get the "false side" of the branch
-    pub fn get_bb_false(&self) -> &BasicBlock {
+    pub fn get_bb_false(&self) -> BlockId {
         self.bb_false
     }
 
     /// Get the "true side" of the branch
-    pub fn get_bb_true(&self) -> &BasicBlock {
+    pub fn get_bb_true(&self) -> BlockId {
         self.bb_true
     }
 
     /// Get the interval associated with the true side of the branch
-    pub fn get_itv_t(&self) -> &IntervalType<'tcx, T> {
+    pub fn get_itv_t(&self) -> &IntervalType<D> {
         &self.itv_t
     }
 
     /// Get the interval associated with the false side of the branch
-    pub fn get_itv_f(&self) -> &IntervalType<'tcx, T> {
+    pub fn get_itv_f(&self) -> &IntervalType<D> {
         &self.itv_f
     }
 
     /// Get the value associated with the branch
-    pub fn get_v(&self) -> &Place<'tcx> {
+    pub fn get_v(&self) -> ValueId {
         self.v
     }
 
-    // pub fn set_itv_t(&mut self, itv: &IntervalType<'tcx, T>) {
+    // pub fn set_itv_t(&mut self, itv: &IntervalType<D>) {
     //     self.itv_t = itv;
     // }
 
@@ -301,16 +378,16 @@ impl<'tcx, T: PartialOrd + Clone + Bounded> ValueBranchMap<'tcx, T> {
 //     Place(Place<'tcx>),
 // }
 
-pub type VarNodes<'tcx, T> = HashMap<&'tcx Place<'tcx>, VarNode<'tcx, T>>;
-// pub type VarNodes<'a, T> = HashMap<&'a  Place<'a>, VarNode<'a,  T>>;
+pub type VarNodes<D> = HashMap<ValueId, VarNode<D>>;
 
-pub type GenOprs<'tcx, T> = HashSet<&'tcx BasicOp<'tcx, T>>;
-pub type UseMap<'tcx, T> = HashMap<&'tcx Place<'tcx>, HashSet<&'tcx BasicOp<'tcx, T>>>;
-pub type SymbMap<'tcx, T> = HashMap<&'tcx Place<'tcx>, HashSet<&'tcx BasicOp<'tcx, T>>>;
-pub type DefMap<'tcx, T> = HashMap<&'tcx Place<'tcx>, &'tcx BasicOp<'tcx, T>>;
-pub type ValuesBranchMap<'tcx, T> = HashMap<&'tcx Place<'tcx>, ValueBranchMap<'tcx, T>>;
-// pub type ValuesSwitchMap<'tcx, T> = HashMap<&'tcx Place<'tcx>, ValueSwitchMap<'tcx, T>>;
-// impl<'tcx, T: fmt::Debug + PartialOrd + Clone + Bounded> fmt::Debug for ValueBranchMap<'tcx, T> {
+// Operations are stored in `GenOprs` and referred to by their index.
+pub type GenOprs<D> = Vec<BasicOp<D>>;
+pub type UseMap = HashMap<ValueId, HashSet<usize>>;
+pub type SymbMap = HashMap<ValueId, HashSet<usize>>;
+pub type DefMap = HashMap<ValueId, usize>;
+pub type ValuesBranchMap<D> = HashMap<ValueId, ValueBranchMap<D>>;
+// pub type ValuesSwitchMap<D> = HashMap<ValueId, ValueSwitchMap<D>>;
+// impl<T: fmt::Debug + PartialOrd + Clone + Bounded> fmt::Debug for ValueBranchMap<T> {
 //     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 //         f.debug_struct("ValueBranchMap")
 //             .field("v", &self.v)
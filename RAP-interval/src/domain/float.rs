@@ -0,0 +1,418 @@
+//! Intervals of `f32` and `f64` values.
+//!
+//! A `FloatRange` holds the numbers of `[lower, upper]`, whose bounds may be infinite, and
+//! possibly NaN. Both precisions are stored as `f64`, which holds every `f32` exactly.
+//!
+//! Bounds are computed with the operation the program performs, in its precision. Rounding
+//! to nearest is monotone and IEEE arithmetic is monotone in each operand away from its
+//! poles, so the results at the corners bound every result. Bounds that are not the result
+//! of such an operation are rounded outward to the next representable value.
+
+use num_traits::{FromPrimitive, PrimInt};
+
+use super::range::{IntType, Range, RangeType};
+use crate::ir::ir::CmpOp;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FloatRange {
+    /// 32 or 64.
+    pub bits: u16,
+    /// The numbers, if there are any.
+    pub bounds: Option<(f64, f64)>,
+    /// Whether NaN is possible.
+    pub nan: bool,
+}
+
+/// `x` rounded to the precision of `bits`.
+fn round(x: f64, bits: u16) -> f64 {
+    if bits == 32 {
+        x as f32 as f64
+    } else {
+        x
+    }
+}
+
+/// The next value above `x` in the precision of `bits`.
+fn next_up(x: f64, bits: u16) -> f64 {
+    if x.is_nan() || x == f64::INFINITY {
+        return x;
+    }
+    if bits == 32 {
+        let x = x as f32;
+        if x == f32::INFINITY {
+            return f64::INFINITY;
+        }
+        let next = if x == 0.0 {
+            f32::from_bits(1)
+        } else if x > 0.0 {
+            f32::from_bits(x.to_bits() + 1)
+        } else {
+            f32::from_bits(x.to_bits() - 1)
+        };
+        return next as f64;
+    }
+    if x == 0.0 {
+        f64::from_bits(1)
+    } else if x > 0.0 {
+        f64::from_bits(x.to_bits() + 1)
+    } else {
+        f64::from_bits(x.to_bits() - 1)
+    }
+}
+
+/// The next value below `x` in the precision of `bits`.
+fn next_down(x: f64, bits: u16) -> f64 {
+    -next_up(-x, bits)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arith {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Arith {
+    /// The operation as the program performs it.
+    fn apply(self, x: f64, y: f64, bits: u16) -> f64 {
+        if bits == 32 {
+            let (x, y) = (x as f32, y as f32);
+            let z = match self {
+                Arith::Add => x + y,
+                Arith::Sub => x - y,
+                Arith::Mul => x * y,
+                Arith::Div => x / y,
+            };
+            return z as f64;
+        }
+        match self {
+            Arith::Add => x + y,
+            Arith::Sub => x - y,
+            Arith::Mul => x * y,
+            Arith::Div => x / y,
+        }
+    }
+}
+
+impl FloatRange {
+    /// The numbers of `[lower, upper]` rounded outward to the precision of `bits`, and NaN
+    /// if `nan` is set.
+    pub fn new(bits: u16, lower: f64, upper: f64, nan: bool) -> Self {
+        let bounds = if lower <= upper {
+            let (lo, hi) = (round(lower, bits), round(upper, bits));
+            let lo = if lo > lower { next_down(lo, bits) } else { lo };
+            let hi = if hi < upper { next_up(hi, bits) } else { hi };
+            Some((lo, hi))
+        } else {
+            None
+        };
+        Self { bits, bounds, nan }
+    }
+
+    pub fn bottom(bits: u16) -> Self {
+        Self {
+            bits,
+            bounds: None,
+            nan: false,
+        }
+    }
+
+    pub fn top(bits: u16) -> Self {
+        Self::new(bits, f64::NEG_INFINITY, f64::INFINITY, true)
+    }
+
+    pub fn constant(bits: u16, x: f64) -> Self {
+        if x.is_nan() {
+            return Self {
+                bits,
+                bounds: None,
+                nan: true,
+            };
+        }
+        Self::new(bits, x, x, false)
+    }
+
+    pub fn is_bottom(&self) -> bool {
+        self.bounds.is_none() && !self.nan
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        match self.bounds {
+            _ if x.is_nan() => self.nan,
+            Some((lo, hi)) => lo <= x && x <= hi,
+            None => false,
+        }
+    }
+
+    fn contains_infinity(&self) -> bool {
+        self.contains(f64::INFINITY) || self.contains(f64::NEG_INFINITY)
+    }
+
+    pub fn leq(&self, other: &Self) -> bool {
+        let bounds = match (self.bounds, other.bounds) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some((a, b)), Some((c, d))) => c <= a && b <= d,
+        };
+        bounds && (!self.nan || other.nan)
+    }
+
+    pub fn join(&self, other: &Self) -> Self {
+        let bounds = match (self.bounds, other.bounds) {
+            (None, bounds) | (bounds, None) => bounds,
+            (Some((a, b)), Some((c, d))) => Some((a.min(c), b.max(d))),
+        };
+        Self {
+            bits: self.bits,
+            bounds,
+            nan: self.nan || other.nan,
+        }
+    }
+
+    pub fn meet(&self, other: &Self) -> Self {
+        let bounds = match (self.bounds, other.bounds) {
+            (Some((a, b)), Some((c, d))) if a.max(c) <= b.min(d) => Some((a.max(c), b.min(d))),
+            _ => None,
+        };
+        Self {
+            bits: self.bits,
+            bounds,
+            nan: self.nan && other.nan,
+        }
+    }
+
+    /// Bounds that grow are moved to infinity.
+    pub fn widen(&self, new: &Self) -> Self {
+        let bounds = match (self.bounds, new.bounds) {
+            (None, bounds) | (bounds, None) => bounds,
+            (Some((a, b)), Some((c, d))) => Some((
+                if c < a { f64::NEG_INFINITY } else { a },
+                if d > b { f64::INFINITY } else { b },
+            )),
+        };
+        Self {
+            bits: self.bits,
+            bounds,
+            nan: self.nan || new.nan,
+        }
+    }
+
+    /// The bounds cut into pieces that are infinite values or have finite bounds, so that an
+    /// operation is continuous on each piece.
+    fn pieces(&self) -> Vec<(f64, f64)> {
+        let Some((lo, hi)) = self.bounds else {
+            return Vec::new();
+        };
+        let mut pieces = Vec::new();
+        if lo == f64::NEG_INFINITY {
+            pieces.push((lo, lo));
+        }
+        let largest = round(f64::MAX, self.bits);
+        let largest = if largest.is_infinite() {
+            f32::MAX as f64
+        } else {
+            largest
+        };
+        let (finite_lo, finite_hi) = (lo.max(-largest), hi.min(largest));
+        if finite_lo <= finite_hi {
+            pieces.push((finite_lo, finite_hi));
+        }
+        if hi == f64::INFINITY {
+            pieces.push((hi, hi));
+        }
+        pieces
+    }
+
+    /// The results of `op` at the corners of every pair of pieces. NaN results make NaN
+    /// possible; NaN results inside a piece must be added by the caller.
+    fn corners(&self, other: &Self, op: Arith) -> Self {
+        let mut result = Self {
+            bits: self.bits,
+            bounds: None,
+            nan: self.nan || other.nan,
+        };
+        for (a, b) in self.pieces() {
+            for &(c, d) in &other.pieces() {
+                for (x, y) in [(a, c), (a, d), (b, c), (b, d)] {
+                    result = result.join(&Self::constant(self.bits, op.apply(x, y, self.bits)));
+                }
+            }
+        }
+        result
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        self.corners(other, Arith::Add)
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.corners(other, Arith::Sub)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut result = self.corners(other, Arith::Mul);
+        // 0 * inf
+        if (self.contains(0.0) && other.contains_infinity())
+            || (self.contains_infinity() && other.contains(0.0))
+        {
+            result.nan = true;
+        }
+        result
+    }
+
+    pub fn div(&self, other: &Self) -> Self {
+        let mut result = self.corners(other, Arith::Div);
+        // 0 / 0 and inf / inf
+        if (self.contains(0.0) && other.contains(0.0))
+            || (self.contains_infinity() && other.contains_infinity())
+        {
+            result.nan = true;
+        }
+        // Division by numbers close to 0 gives anything, with a sign that depends on the sign
+        // of 0, which is not tracked
+        if other.contains(0.0) && self.bounds.is_some() {
+            result = result.join(&Self::new(
+                self.bits,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                false,
+            ));
+        }
+        result
+    }
+
+    /// The remainder has the sign of the dividend and is smaller than the divisor.
+    pub fn rem(&self, other: &Self) -> Self {
+        let nan = self.nan || other.nan || other.contains(0.0) || self.contains_infinity();
+        let (Some((a, b)), Some((c, d))) = (self.bounds, other.bounds) else {
+            return Self {
+                bits: self.bits,
+                bounds: None,
+                nan,
+            };
+        };
+        let largest = c.abs().max(d.abs());
+        let lower = if a >= 0.0 { 0.0 } else { a.max(-largest) };
+        let upper = if b <= 0.0 { 0.0 } else { b.min(largest) };
+        Self::new(self.bits, lower, upper, nan)
+    }
+
+    pub fn neg(&self) -> Self {
+        Self {
+            bits: self.bits,
+            bounds: self.bounds.map(|(lo, hi)| (-hi, -lo)),
+            nan: self.nan,
+        }
+    }
+
+    /// Whether `self op other` can be true and whether it can be false. Every comparison
+    /// with NaN is false, except `!=`.
+    pub fn compare(&self, op: CmpOp, other: &Self) -> (bool, bool) {
+        let (may_true, may_false) = match (self.bounds, other.bounds) {
+            (Some((a, b)), Some((c, d))) => match op {
+                CmpOp::Lt => (a < d, b >= c),
+                CmpOp::Le => (a <= d, b > c),
+                CmpOp::Gt => (b > c, a <= d),
+                CmpOp::Ge => (b >= c, a < d),
+                CmpOp::Eq => (a <= d && c <= b, !(a == b && b == c && c == d)),
+                CmpOp::Ne => (!(a == b && b == c && c == d), a <= d && c <= b),
+            },
+            _ => (false, false),
+        };
+        let nan = self.nan || other.nan;
+        if op == CmpOp::Ne {
+            (may_true || nan, may_false)
+        } else {
+            (may_true, may_false || nan)
+        }
+    }
+
+    /// The values of `self` for which `self op other` is `holds`.
+    pub fn restrict(&self, op: CmpOp, other: &Self, holds: bool) -> Self {
+        // A comparison with NaN is false, except `!=`
+        let nan_holds = (op == CmpOp::Ne) == holds;
+        let op = if holds { op } else { op.negate() };
+        let mut result = *self;
+        result.nan = self.nan && nan_holds;
+        if other.nan && nan_holds {
+            // NaN in `other` decides the comparison, whatever the number in `self` is
+            return result;
+        }
+        let Some((c, d)) = other.bounds else {
+            result.bounds = None;
+            return result;
+        };
+        let limit = match op {
+            CmpOp::Lt => (f64::NEG_INFINITY, next_down(d, self.bits)),
+            CmpOp::Le => (f64::NEG_INFINITY, d),
+            CmpOp::Gt => (next_up(c, self.bits), f64::INFINITY),
+            CmpOp::Ge => (c, f64::INFINITY),
+            CmpOp::Eq => (c, d),
+            CmpOp::Ne => (f64::NEG_INFINITY, f64::INFINITY),
+        };
+        result.bounds = match self.bounds {
+            Some((a, b)) if a.max(limit.0) <= b.min(limit.1) => {
+                Some((a.max(limit.0), b.min(limit.1)))
+            }
+            _ => None,
+        };
+        result
+    }
+
+    /// `self as f32` or `self as f64`.
+    pub fn cast(&self, bits: u16) -> Self {
+        Self {
+            bits,
+            bounds: self
+                .bounds
+                .map(|(lo, hi)| (round(lo, bits), round(hi, bits))),
+            nan: self.nan,
+        }
+    }
+
+    /// The values of integers of `range` as floats of `bits` bits.
+    pub fn from_int_range<T: PrimInt>(range: &Range<T>, bits: u16) -> Self {
+        if range.is_empty() {
+            return Self::bottom(bits);
+        }
+        if !range.is_regular() {
+            return Self::new(bits, f64::NEG_INFINITY, f64::INFINITY, false);
+        }
+        let convert = |x: T| {
+            if bits == 32 {
+                x.to_f32().map(|x| x as f64)
+            } else {
+                x.to_f64()
+            }
+        };
+        match (convert(range.get_lower()), convert(range.get_upper())) {
+            (Some(lo), Some(hi)) => Self::new(bits, lo, hi, false),
+            _ => Self::new(bits, f64::NEG_INFINITY, f64::INFINITY, false),
+        }
+    }
+
+    /// `self as ty`: the value is truncated towards zero and saturates at the bounds of the
+    /// type, and NaN gives 0.
+    pub fn to_int_range<T: PrimInt + FromPrimitive>(&self, ty: IntType) -> Range<T> {
+        let convert = |x: f64| -> Option<T> {
+            if ty.signed {
+                let (min, max) = (ty.min_value::<i128>(), ty.max_value::<i128>());
+                T::from_i128((x as i128).clamp(min, max))
+            } else {
+                T::from_u128((x as u128).min(ty.max_value::<u128>()))
+            }
+        };
+        let mut result = Range::new(T::min_value(), T::max_value(), RangeType::Empty);
+        if let Some((lo, hi)) = self.bounds {
+            result = match (convert(lo), convert(hi)) {
+                (Some(lo), Some(hi)) => Range::new(lo, hi, RangeType::Regular),
+                _ => Range::of_type(ty),
+            };
+        }
+        if self.nan {
+            result = result.join(&Range::new(T::zero(), T::zero(), RangeType::Regular));
+        }
+        result
+    }
+}
@@ -1,12 +1,12 @@
-use bounds::Bound;
+use super::abstract_domain::AbstractDomain;
+use super::sign::Sign;
+use super::wrapped::WrappedRange;
+use crate::ir::ir::{BinOp, CmpOp, ScalarTy, UnOp};
 use intervals::*;
-use num_traits::{Bounded, Num, Zero};
-use z3::ast::Int;
+use num_traits::{Bounded, FromPrimitive, PrimInt};
+use std::fmt;
 // use std::ops::Range;
 
-const MIN: i64 = i64::MIN;
-const MAX: i64 = i64::MAX;
-
 // #[derive(PartialEq, Debug)]
 // pub struct MyStruct<T>
 // where T: PartialOrd+Clone
@@ -41,27 +41,56 @@ where
     pub range: Closed<T>,
 }
 
+/// The integer type of a program value. `bool` is an unsigned 1-bit integer and `char` an
+/// unsigned 21-bit one, which covers every Unicode scalar value.
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
-enum UserType {
-    Unknown,
-    I32(i32),
-    I64(i64),
-    Usize(usize),
-    Empty,
+pub struct IntType {
+    pub bits: u16,
+    pub signed: bool,
 }
 
-impl<T> Range<T>
+impl IntType {
+    pub const BOOL: IntType = IntType::new(1, false);
+    pub const CHAR: IntType = IntType::new(21, false);
+
+    pub const fn new(bits: u16, signed: bool) -> Self {
+        Self { bits, signed }
+    }
+
+    /// The integer type of a scalar, if it is one.
+    pub fn of(ty: &ScalarTy) -> Option<Self> {
+        match *ty {
+            ScalarTy::Bool => Some(Self::BOOL),
+            ScalarTy::Char => Some(Self::CHAR),
+            ScalarTy::Int { bits, signed } => Some(Self::new(bits, signed)),
+            ScalarTy::Float { .. } | ScalarTy::Opaque => None,
+        }
+    }
+
+    /// The smallest value of the type, or of `T` if that is larger.
+    pub fn min_value<T: Bounded + FromPrimitive>(&self) -> T {
+        if !self.signed {
+            return T::from_u8(0).unwrap_or_else(T::min_value);
+        }
+        T::from_i128(i128::MIN >> (128 - self.bits)).unwrap_or_else(T::min_value)
+    }
+
+    /// The largest value of the type, or of `T` if that is smaller.
+    pub fn max_value<T: Bounded + FromPrimitive>(&self) -> T {
+        if self.signed {
+            T::from_i128(i128::MAX >> (128 - self.bits)).unwrap_or_else(T::max_value)
+        } else {
+            T::from_u128(u128::MAX >> (128 - self.bits)).unwrap_or_else(T::max_value)
+        }
+    }
+}
+
+// Every value of `T`
+impl<T> Default for Range<T>
 where
     T: PartialOrd + Clone + Bounded,
 {
-    // Parameterized constructor
-    pub fn new(lb: T, ub: T, rtype: RangeType) -> Self {
-        Self {
-            rtype,
-            range: Interval::new_unchecked(bounds::Closed(lb), bounds::Closed(ub)),
-        }
-    }
-    pub fn default() -> Self {
+    fn default() -> Self {
         Self {
             rtype: RangeType::Regular,
 
@@ -71,6 +100,19 @@ where
             ),
         }
     }
+}
+
+impl<T> Range<T>
+where
+    T: PartialOrd + Clone + Bounded,
+{
+    // Parameterized constructor
+    pub fn new(lb: T, ub: T, rtype: RangeType) -> Self {
+        Self {
+            rtype,
+            range: Interval::new_unchecked(bounds::Closed(lb), bounds::Closed(ub)),
+        }
+    }
     // Getter for lower bound
     pub fn get_lower(&self) -> T {
         self.range.left.0.clone()
@@ -130,13 +172,884 @@ where
     // pub fn print(&self) {
     //     println!("Range: [{} - {}]", self.get_lower(), self.get_upper());
     // }
+}
 
-    // // Arithmetic and bitwise operations (example for addition)
-    // pub fn add(&self, other: &Range<T>) -> Range<T> {
-    //     let lower = self.get_lower() + other.get_lower();
-    //     let upper = self.get_upper() + other.get_upper();
-    //     Range::with_bounds(lower, upper, RangeType::Regular)
-    // }
+// Lattice operations.
+//
+// Empty is the bottom of the lattice. Unknown marks a variable that has not been computed
+// yet: joins and meets ignore it, and widening replaces it by the new range.
+impl<T> Range<T>
+where
+    T: PartialOrd + Clone + Bounded,
+{
+    fn min_of(a: T, b: T) -> T {
+        if b < a {
+            b
+        } else {
+            a
+        }
+    }
+
+    fn max_of(a: T, b: T) -> T {
+        if b > a {
+            b
+        } else {
+            a
+        }
+    }
+
+    /// The smallest range containing both ranges.
+    pub fn join(&self, other: &Self) -> Self {
+        if self.is_empty() || self.is_unknown() {
+            return other.clone();
+        }
+        if other.is_empty() || other.is_unknown() {
+            return self.clone();
+        }
+        Self::new(
+            Self::min_of(self.get_lower(), other.get_lower()),
+            Self::max_of(self.get_upper(), other.get_upper()),
+            RangeType::Regular,
+        )
+    }
+
+    /// The intersection of both ranges, empty if they are disjoint.
+    pub fn meet(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return Self::new(T::min_value(), T::max_value(), RangeType::Empty);
+        }
+        if self.is_unknown() {
+            return other.clone();
+        }
+        if other.is_unknown() {
+            return self.clone();
+        }
+        let lower = Self::max_of(self.get_lower(), other.get_lower());
+        let upper = Self::min_of(self.get_upper(), other.get_upper());
+        if lower > upper {
+            return Self::new(T::min_value(), T::max_value(), RangeType::Empty);
+        }
+        Self::new(lower, upper, RangeType::Regular)
+    }
+
+    /// Widens `self` by the next iterate `new`: a bound that grows jumps to the next
+    /// threshold beyond it, or to the bound of `T`. `thresholds` must be sorted.
+    pub fn widen(&self, new: &Self, thresholds: &[T]) -> Self {
+        if self.is_empty() || self.is_unknown() {
+            return new.clone();
+        }
+        if new.is_empty() || new.is_unknown() {
+            return self.clone();
+        }
+        let mut lower = self.get_lower();
+        if new.get_lower() < lower {
+            lower = thresholds
+                .iter()
+                .rev()
+                .find(|&t| *t <= new.get_lower())
+                .cloned()
+                .unwrap_or_else(T::min_value);
+        }
+        let mut upper = self.get_upper();
+        if new.get_upper() > upper {
+            upper = thresholds
+                .iter()
+                .find(|&t| *t >= new.get_upper())
+                .cloned()
+                .unwrap_or_else(T::max_value);
+        }
+        Self::new(lower, upper, RangeType::Regular)
+    }
+
+    /// Narrows `self` by the next iterate `new`: only bounds that widening sent to the
+    /// bounds of `T` are refined, which guarantees termination.
+    pub fn narrow(&self, new: &Self) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        if self.is_unknown() {
+            return new.clone();
+        }
+        if new.is_unknown() {
+            return self.clone();
+        }
+        if new.is_empty() {
+            return new.clone();
+        }
+        let mut lower = self.get_lower();
+        if lower == T::min_value() {
+            lower = new.get_lower();
+        }
+        let mut upper = self.get_upper();
+        if upper == T::max_value() {
+            upper = new.get_upper();
+        }
+        if lower > upper {
+            return Self::new(T::min_value(), T::max_value(), RangeType::Empty);
+        }
+        Self::new(lower, upper, RangeType::Regular)
+    }
 }
 
 // Implement the comparison operators
+
+// Arithmetic transfer functions.
+//
+// Each bound is computed exactly and saturates at the bounds of `T` when it overflows, so the
+// result over-approximates every non-overflowing concrete result. An empty operand gives an
+// empty result (the operation is never executed); otherwise an unknown operand gives an
+// unknown result.
+impl<T> Range<T>
+where
+    T: PrimInt,
+{
+    fn empty() -> Self {
+        Self::new(T::min_value(), T::max_value(), RangeType::Empty)
+    }
+
+    fn unknown() -> Self {
+        Self::new(T::min_value(), T::max_value(), RangeType::Unknown)
+    }
+
+    /// The result of combining two operands if either is not regular.
+    pub fn irregular(&self, other: &Self) -> Option<Self> {
+        if self.is_empty() || other.is_empty() {
+            Some(Self::empty())
+        } else if self.is_unknown() || other.is_unknown() {
+            Some(Self::unknown())
+        } else {
+            None
+        }
+    }
+
+    /// `[lb, ub]`, or empty if `lb > ub`.
+    fn regular(lb: T, ub: T) -> Self {
+        if lb > ub {
+            Self::empty()
+        } else {
+            Self::new(lb, ub, RangeType::Regular)
+        }
+    }
+
+    /// `a * b`, saturating towards the sign of the exact product.
+    fn saturating_mul(a: T, b: T) -> T {
+        a.checked_mul(&b).unwrap_or_else(|| {
+            if (a < T::zero()) == (b < T::zero()) {
+                T::max_value()
+            } else {
+                T::min_value()
+            }
+        })
+    }
+
+    /// `a / b` for `b != 0`; `MIN / -1` saturates to `MAX`.
+    fn saturating_div(a: T, b: T) -> T {
+        a.checked_div(&b).unwrap_or_else(T::max_value)
+    }
+
+    /// The smallest range containing all `values`.
+    fn hull(values: &[T]) -> Self {
+        let lb = values.iter().copied().fold(T::max_value(), T::min);
+        let ub = values.iter().copied().fold(T::min_value(), T::max);
+        Self::regular(lb, ub)
+    }
+
+    /// The regular parts of `self` below and above zero, i.e. the divisors that do not panic.
+    fn nonzero_parts(&self) -> Vec<(T, T)> {
+        let (c, d) = (self.get_lower(), self.get_upper());
+        let mut parts = Vec::new();
+        if c < T::zero() {
+            parts.push((c, d.min(T::zero() - T::one())));
+        }
+        if d > T::zero() {
+            parts.push((c.max(T::one()), d));
+        }
+        parts
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if let Some(range) = self.irregular(other) {
+            return range;
+        }
+        Self::regular(
+            self.get_lower().saturating_add(other.get_lower()),
+            self.get_upper().saturating_add(other.get_upper()),
+        )
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        if let Some(range) = self.irregular(other) {
+            return range;
+        }
+        Self::regular(
+            self.get_lower().saturating_sub(other.get_upper()),
+            self.get_upper().saturating_sub(other.get_lower()),
+        )
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        if let Some(range) = self.irregular(other) {
+            return range;
+        }
+        let (a, b) = (self.get_lower(), self.get_upper());
+        let (c, d) = (other.get_lower(), other.get_upper());
+        Self::hull(&[
+            Self::saturating_mul(a, c),
+            Self::saturating_mul(a, d),
+            Self::saturating_mul(b, c),
+            Self::saturating_mul(b, d),
+        ])
+    }
+
+    /// Truncating division. Division by zero panics, so zero is removed from the divisor; a
+    /// divisor of exactly `[0, 0]` gives an empty range.
+    pub fn div(&self, other: &Self) -> Self {
+        if let Some(range) = self.irregular(other) {
+            return range;
+        }
+        let (a, b) = (self.get_lower(), self.get_upper());
+        let mut quotients = Vec::new();
+        // On either side of zero the quotient is monotone in both operands.
+        for (c, d) in other.nonzero_parts() {
+            quotients.extend([
+                Self::saturating_div(a, c),
+                Self::saturating_div(a, d),
+                Self::saturating_div(b, c),
+                Self::saturating_div(b, d),
+            ]);
+        }
+        if quotients.is_empty() {
+            return Self::empty();
+        }
+        Self::hull(&quotients)
+    }
+
+    /// Remainder of truncating division: it has the sign of the dividend and is smaller in
+    /// magnitude than both the dividend and the divisor.
+    pub fn rem(&self, other: &Self) -> Self {
+        if let Some(range) = self.irregular(other) {
+            return range;
+        }
+        let parts = other.nonzero_parts();
+        if parts.is_empty() {
+            return Self::empty();
+        }
+        // The largest remainder magnitude, `|divisor| - 1`, computed without overflow.
+        let (c, d) = (parts[0].0, parts[parts.len() - 1].1);
+        let mut m = T::zero();
+        if c < T::zero() {
+            m = m.max(T::zero() - T::one() - c);
+        }
+        if d > T::zero() {
+            m = m.max(d - T::one());
+        }
+        let (a, b) = (self.get_lower(), self.get_upper());
+        let lb = if a >= T::zero() {
+            T::zero()
+        } else {
+            a.max(T::zero().saturating_sub(m))
+        };
+        let ub = if b <= T::zero() { T::zero() } else { b.min(m) };
+        Self::regular(lb, ub)
+    }
+
+    /// Negation; `-MIN` saturates to `MAX`. Unsigned ranges can only negate zero.
+    pub fn neg(&self) -> Self {
+        if self.is_empty() || self.is_unknown() {
+            return self.clone();
+        }
+        Self::regular(
+            T::zero().saturating_sub(self.get_upper()),
+            T::zero().saturating_sub(self.get_lower()),
+        )
+    }
+}
+
+// Bitwise and shift transfer functions.
+//
+// Bitwise operations work on the two's complement bit patterns of the bounds, held in a
+// `u128`. Each operand is split into its negative and non-negative part: inside one part the
+// pattern order is the value order, and the sign bit of the result is fixed, so the unsigned
+// bounds of Warren (Hacker's Delight, 4-3) carry over to values.
+impl<T> Range<T>
+where
+    T: PrimInt,
+{
+    fn bit_width() -> u32 {
+        T::zero().count_zeros()
+    }
+
+    fn is_signed() -> bool {
+        T::min_value() < T::zero()
+    }
+
+    fn pattern_mask() -> u128 {
+        u128::MAX >> (128 - Self::bit_width())
+    }
+
+    fn to_pattern(x: T) -> u128 {
+        match x.to_u128() {
+            Some(p) => p,
+            None => x.to_i128().unwrap() as u128 & Self::pattern_mask(),
+        }
+    }
+
+    fn from_pattern(p: u128) -> T {
+        if Self::is_signed() {
+            let shift = 128 - Self::bit_width();
+            T::from(((p << shift) as i128) >> shift).unwrap()
+        } else {
+            T::from(p).unwrap()
+        }
+    }
+
+    /// The negative and the non-negative part of a regular range.
+    fn sign_parts(&self) -> Vec<(T, T)> {
+        let (a, b) = (self.get_lower(), self.get_upper());
+        let mut parts = Vec::new();
+        if a < T::zero() {
+            parts.push((a, b.min(T::zero() - T::one())));
+        }
+        if b >= T::zero() {
+            parts.push((a.max(T::zero()), b));
+        }
+        parts
+    }
+
+    fn bitwise(
+        &self,
+        other: &Self,
+        min: fn(u128, u128, u128, u128, u128) -> u128,
+        max: fn(u128, u128, u128, u128, u128) -> u128,
+    ) -> Self {
+        if let Some(range) = self.irregular(other) {
+            return range;
+        }
+        let top = 1u128 << (Self::bit_width() - 1);
+        let mut bounds = Vec::new();
+        for (a, b) in self.sign_parts() {
+            for (c, d) in other.sign_parts() {
+                let (a, b) = (Self::to_pattern(a), Self::to_pattern(b));
+                let (c, d) = (Self::to_pattern(c), Self::to_pattern(d));
+                bounds.push(Self::from_pattern(min(a, b, c, d, top)));
+                bounds.push(Self::from_pattern(max(a, b, c, d, top)));
+            }
+        }
+        Self::hull(&bounds)
+    }
+
+    pub fn bitand(&self, other: &Self) -> Self {
+        self.bitwise(
+            other,
+            |a, b, c, d, top| min_and(a, b, c, d, top, mask_of(top)),
+            max_and,
+        )
+    }
+
+    pub fn bitor(&self, other: &Self) -> Self {
+        self.bitwise(
+            other,
+            |a, b, c, d, top| min_or(a, b, c, d, top, mask_of(top)),
+            max_or,
+        )
+    }
+
+    pub fn bitxor(&self, other: &Self) -> Self {
+        self.bitwise(
+            other,
+            |a, b, c, d, top| min_xor(a, b, c, d, top, mask_of(top)),
+            max_xor,
+        )
+    }
+
+    /// Bitwise not, `!x == -1 - x`.
+    pub fn not(&self) -> Self {
+        if self.is_empty() || self.is_unknown() {
+            return self.clone();
+        }
+        Self::regular(!self.get_upper(), !self.get_lower())
+    }
+
+    /// The shift amounts that are actually applied to a value of `bits` bits. A `checked`
+    /// shift panics (or is undefined behaviour) when the amount is not below the bit width;
+    /// otherwise the amount is masked.
+    pub fn shift_amount(&self, bits: u32, checked: bool) -> Self {
+        if !self.is_regular() {
+            return self.clone();
+        }
+        let width = bits as i128;
+        // Unsigned amounts above `i128::MAX` saturate; they are out of range either way
+        let (lower, upper) = (self.get_lower().to_i128(), self.get_upper().to_i128());
+        let (c, d) = match (lower, upper) {
+            _ if checked => (
+                lower.unwrap_or(i128::MAX).max(0),
+                upper.unwrap_or(i128::MAX).min(width - 1),
+            ),
+            // Masking keeps the amount modulo the width; it is monotone within one period.
+            (Some(c), Some(d)) if c.div_euclid(width) == d.div_euclid(width) => {
+                (c.rem_euclid(width), d.rem_euclid(width))
+            }
+            _ => (0, width - 1),
+        };
+        if c > d {
+            return Self::empty();
+        }
+        Self::regular(T::from(c).unwrap(), T::from(d).unwrap())
+    }
+
+    fn shift_amounts(&self, checked: bool) -> Option<(u32, u32)> {
+        let amount = self.shift_amount(Self::bit_width(), checked);
+        amount.is_regular().then(|| {
+            (
+                amount.get_lower().to_u32().unwrap(),
+                amount.get_upper().to_u32().unwrap(),
+            )
+        })
+    }
+
+    /// `self << amount`. Bits shifted out are lost, so when that can happen for some operands
+    /// the result is the whole range of `T`.
+    pub fn shl(&self, amount: &Self, checked: bool) -> Self {
+        if let Some(range) = self.irregular(amount) {
+            return range;
+        }
+        let Some((c, d)) = amount.shift_amounts(checked) else {
+            return Self::empty();
+        };
+        let (a, b) = (self.get_lower(), self.get_upper());
+        // `x << n` is `x * 2^n` as long as no bits are lost.
+        let exact = |x: T, n: u32| -> Option<T> {
+            let shifted = x << n as usize;
+            (shifted >> n as usize == x).then_some(shifted)
+        };
+        let corners = [exact(a, c), exact(a, d), exact(b, c), exact(b, d)];
+        if corners.iter().any(Option::is_none) {
+            return Self::new(T::min_value(), T::max_value(), RangeType::Regular);
+        }
+        Self::hull(&corners.map(Option::unwrap))
+    }
+
+    /// `self >> amount`, arithmetic for signed and logical for unsigned types.
+    pub fn shr(&self, amount: &Self, checked: bool) -> Self {
+        if let Some(range) = self.irregular(amount) {
+            return range;
+        }
+        let Some((c, d)) = amount.shift_amounts(checked) else {
+            return Self::empty();
+        };
+        let (a, b) = (self.get_lower(), self.get_upper());
+        Self::hull(&[
+            a >> c as usize,
+            a >> d as usize,
+            b >> c as usize,
+            b >> d as usize,
+        ])
+    }
+}
+
+// Values of a narrower integer type stored in `T`.
+//
+// Operations are evaluated in `T` and the result is then fitted into the type of the value
+// it is assigned to. Values of `u128` above the largest `T` are not representable and are
+// taken to be at most `T::max_value()`.
+impl<T> Range<T>
+where
+    T: PrimInt + FromPrimitive,
+{
+    /// Every value of `ty`.
+    pub fn of_type(ty: IntType) -> Self {
+        Self::new(ty.min_value(), ty.max_value(), RangeType::Regular)
+    }
+
+    /// The result of an operation of type `ty`. Results outside of the type wrap around,
+    /// unless the operation is `checked` and never produces them.
+    pub fn fit(&self, ty: IntType, checked: bool) -> Self {
+        let full = Self::of_type(ty);
+        if !self.is_regular()
+            || (full.get_lower() <= self.get_lower() && self.get_upper() <= full.get_upper())
+        {
+            return self.clone();
+        }
+        if checked {
+            self.meet(&full)
+        } else {
+            full
+        }
+    }
+
+    /// Narrowing for values of `ty`: widening jumps to the bounds of the type as it does
+    /// to those of `T`, so they are refined too.
+    pub fn narrow_in(&self, new: &Self, ty: IntType) -> Self {
+        if !self.is_regular() || !new.is_regular() {
+            return self.narrow(new);
+        }
+        let full = Self::of_type(ty);
+        let mut old = self.clone();
+        if old.get_lower() == full.get_lower() {
+            old.set_lower(T::min_value());
+        }
+        if old.get_upper() == full.get_upper() {
+            old.set_upper(T::max_value());
+        }
+        old.narrow(new)
+    }
+
+    /// The bounds of `new` that lie beyond those of `self`: '-' if its lower bound is lower,
+    /// '+' if its upper bound is higher. The first value of a variable is not a growth.
+    pub fn growth(&self, new: &Self) -> Sign {
+        if !self.is_regular() || !new.is_regular() {
+            return Sign::Zero;
+        }
+        Sign::of_bounds(
+            new.get_lower() < self.get_lower(),
+            new.get_upper() > self.get_upper(),
+        )
+    }
+
+    /// The crop meet: the bounds of `self` that `state` marks are replaced by those of the
+    /// next iterate `new` when they are tighter. The others are kept.
+    pub fn crop(&self, new: &Self, state: Sign) -> Self {
+        if self.is_empty() || new.is_unknown() {
+            return self.clone();
+        }
+        if self.is_unknown() || new.is_empty() {
+            return new.clone();
+        }
+        let mut lower = self.get_lower();
+        if state.lower() && new.get_lower() > lower {
+            lower = new.get_lower();
+        }
+        let mut upper = self.get_upper();
+        if state.upper() && new.get_upper() < upper {
+            upper = new.get_upper();
+        }
+        if lower > upper {
+            return Self::new(T::min_value(), T::max_value(), RangeType::Empty);
+        }
+        Self::new(lower, upper, RangeType::Regular)
+    }
+
+    /// `!self` in `ty`: the bitwise complement is `-x - 1` for signed and `MAX - x` for
+    /// unsigned types.
+    pub fn not_in(&self, ty: IntType) -> Self {
+        if ty.signed {
+            return self.not();
+        }
+        let max = ty.max_value();
+        Self::new(max, max, RangeType::Regular).sub(self)
+    }
+}
+
+impl<T> Range<T>
+where
+    T: PrimInt + FromPrimitive,
+{
+    // Evaluates wrapping arithmetic on wrapped intervals, which keep the values that wrap
+    // around instead of giving up on the whole type
+    fn wrapping(
+        &self,
+        rhs: &Self,
+        ty: IntType,
+        rhs_ty: IntType,
+        op: impl Fn(&WrappedRange, &WrappedRange) -> WrappedRange,
+    ) -> Self {
+        if let Some(range) = self.irregular(rhs) {
+            return range;
+        }
+        let lhs = WrappedRange::from_range(self, ty.bits);
+        let rhs = WrappedRange::from_range(rhs, rhs_ty.bits);
+        op(&lhs, &rhs).to_range(ty)
+    }
+}
+
+impl<T> AbstractDomain for Range<T>
+where
+    T: PrimInt + FromPrimitive + fmt::Debug,
+{
+    fn top(ty: IntType) -> Self {
+        Self::of_type(ty)
+    }
+
+    fn bottom() -> Self {
+        Self::new(T::min_value(), T::max_value(), RangeType::Empty)
+    }
+
+    fn unknown() -> Self {
+        Self::new(T::min_value(), T::max_value(), RangeType::Unknown)
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.rtype == RangeType::Empty
+    }
+
+    fn is_unknown(&self) -> bool {
+        self.rtype == RangeType::Unknown
+    }
+
+    // Constants that do not fit `T` are not known
+    fn constant(value: i128) -> Self {
+        match T::from_i128(value) {
+            Some(c) => Self::new(c, c, RangeType::Regular),
+            None => Self::default(),
+        }
+    }
+
+    /// `[lower, upper]` clamped to the bounds of `T`. Bounds entirely outside of `T` give an
+    /// empty range.
+    fn interval(lower: Option<i128>, upper: Option<i128>) -> Self {
+        let lower = match lower {
+            None => T::min_value(),
+            Some(l) => match T::from_i128(l) {
+                Some(l) => l,
+                None if l < 0 => T::min_value(),
+                None => return Self::bottom(),
+            },
+        };
+        let upper = match upper {
+            None => T::max_value(),
+            Some(u) => match T::from_i128(u) {
+                Some(u) => u,
+                None if u < 0 => return Self::bottom(),
+                None => T::max_value(),
+            },
+        };
+        if lower > upper {
+            return Self::bottom();
+        }
+        Self::new(lower, upper, RangeType::Regular)
+    }
+
+    fn satisfying(op: CmpOp, bound: &Self) -> Self {
+        if !bound.is_regular() {
+            return Self::default();
+        }
+        let (l, u) = (bound.get_lower(), bound.get_upper());
+        match op {
+            CmpOp::Lt => match u.checked_sub(&T::one()) {
+                Some(u) => Self::new(T::min_value(), u, RangeType::Regular),
+                None => Self::bottom(),
+            },
+            CmpOp::Le => Self::new(T::min_value(), u, RangeType::Regular),
+            CmpOp::Gt => match l.checked_add(&T::one()) {
+                Some(l) => Self::new(l, T::max_value(), RangeType::Regular),
+                None => Self::bottom(),
+            },
+            CmpOp::Ge => Self::new(l, T::max_value(), RangeType::Regular),
+            CmpOp::Eq => Self::new(l, u, RangeType::Regular),
+            // A single excluded value cannot be expressed by an interval
+            CmpOp::Ne => Self::default(),
+        }
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        Range::join(self, other)
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        Range::meet(self, other)
+    }
+
+    fn widen(&self, new: &Self, thresholds: &[i128]) -> Self {
+        let thresholds: Vec<T> = thresholds.iter().filter_map(|&t| T::from_i128(t)).collect();
+        Range::widen(self, new, &thresholds)
+    }
+
+    fn narrow(&self, new: &Self, ty: IntType) -> Self {
+        self.narrow_in(new, ty)
+    }
+
+    fn growth(&self, new: &Self) -> Sign {
+        Range::growth(self, new)
+    }
+
+    fn crop(&self, new: &Self, state: Sign) -> Self {
+        Range::crop(self, new, state)
+    }
+
+    // The bounds of `T` are where widening goes anyway
+    fn thresholds(&self) -> Vec<i128> {
+        if !self.is_regular() {
+            return Vec::new();
+        }
+        [self.get_lower(), self.get_upper()]
+            .into_iter()
+            .filter(|&bound| bound != T::min_value() && bound != T::max_value())
+            .filter_map(|bound| bound.to_i128())
+            .collect()
+    }
+
+    fn unary(&self, op: UnOp, ty: IntType) -> Self {
+        match op {
+            UnOp::Neg => self.wrapping(self, ty, ty, |source, _| source.neg()),
+            UnOp::Not => self.not_in(ty),
+        }
+    }
+
+    fn binary(&self, op: BinOp, rhs: &Self, ty: IntType, checked: bool) -> Self {
+        match op {
+            BinOp::Add if !checked => self.wrapping(rhs, ty, ty, WrappedRange::add),
+            BinOp::Sub if !checked => self.wrapping(rhs, ty, ty, WrappedRange::sub),
+            BinOp::Mul if !checked => self.wrapping(rhs, ty, ty, WrappedRange::mul),
+            BinOp::Add => self.add(rhs).fit(ty, true),
+            BinOp::Sub => self.sub(rhs).fit(ty, true),
+            BinOp::Mul => self.mul(rhs).fit(ty, true),
+            // Division overflow always panics
+            BinOp::Div => self.div(rhs).fit(ty, true),
+            BinOp::Rem => self.rem(rhs).fit(ty, true),
+            BinOp::BitAnd => self.bitand(rhs).fit(ty, false),
+            BinOp::BitOr => self.bitor(rhs).fit(ty, false),
+            BinOp::BitXor => self.bitxor(rhs).fit(ty, false),
+            // Bits shifted out are lost even when the amount is checked; the amount may have
+            // any type
+            BinOp::Shl => {
+                self.wrapping(rhs, ty, IntType::new(128, false), |l, r| l.shl(r, checked))
+            }
+            // Shifts are evaluated in `T` with the amounts the type actually applies
+            BinOp::Shr => {
+                let amount = rhs.shift_amount(ty.bits as u32, checked);
+                self.shr(&amount, true).fit(ty, false)
+            }
+        }
+    }
+
+    // Integer casts keep the values that fit the target type and wrap the others
+    fn cast(&self, _from: Option<IntType>, to: IntType) -> Self {
+        self.fit(to, false)
+    }
+}
+
+/// All bits below and including `top`.
+fn mask_of(top: u128) -> u128 {
+    top | (top - 1)
+}
+
+/// `-m` for a power of two `m`, i.e. `m` and all bits above it.
+fn neg_pow2(m: u128, mask: u128) -> u128 {
+    mask & !(m - 1)
+}
+
+// Warren's bounds of `x op y` for unsigned `x ∈ [a, b]`, `y ∈ [c, d]`, where `top` is the
+// highest bit of the width.
+
+fn min_or(mut a: u128, b: u128, mut c: u128, d: u128, top: u128, mask: u128) -> u128 {
+    let mut m = top;
+    while m != 0 {
+        if !a & c & m != 0 {
+            let temp = (a | m) & neg_pow2(m, mask);
+            if temp <= b {
+                a = temp;
+                break;
+            }
+        } else if a & !c & m != 0 {
+            let temp = (c | m) & neg_pow2(m, mask);
+            if temp <= d {
+                c = temp;
+                break;
+            }
+        }
+        m >>= 1;
+    }
+    a | c
+}
+
+fn max_or(a: u128, mut b: u128, c: u128, mut d: u128, top: u128) -> u128 {
+    let mut m = top;
+    while m != 0 {
+        if b & d & m != 0 {
+            let temp = (b - m) | (m - 1);
+            if temp >= a {
+                b = temp;
+                break;
+            }
+            let temp = (d - m) | (m - 1);
+            if temp >= c {
+                d = temp;
+                break;
+            }
+        }
+        m >>= 1;
+    }
+    b | d
+}
+
+fn min_and(mut a: u128, b: u128, mut c: u128, d: u128, top: u128, mask: u128) -> u128 {
+    let mut m = top;
+    while m != 0 {
+        if !a & !c & m != 0 {
+            let temp = (a | m) & neg_pow2(m, mask);
+            if temp <= b {
+                a = temp;
+                break;
+            }
+            let temp = (c | m) & neg_pow2(m, mask);
+            if temp <= d {
+                c = temp;
+                break;
+            }
+        }
+        m >>= 1;
+    }
+    a & c
+}
+
+fn max_and(a: u128, mut b: u128, c: u128, mut d: u128, top: u128) -> u128 {
+    let mut m = top;
+    while m != 0 {
+        if b & !d & m != 0 {
+            let temp = (b & !m) | (m - 1);
+            if temp >= a {
+                b = temp;
+                break;
+            }
+        } else if !b & d & m != 0 {
+            let temp = (d & !m) | (m - 1);
+            if temp >= c {
+                d = temp;
+                break;
+            }
+        }
+        m >>= 1;
+    }
+    b & d
+}
+
+// Unlike `or`/`and`, a bit of `x ^ y` can be won back by lower bits, so the xor bounds
+// keep scanning after an adjustment.
+
+fn min_xor(mut a: u128, b: u128, mut c: u128, d: u128, top: u128, mask: u128) -> u128 {
+    let mut m = top;
+    while m != 0 {
+        if !a & c & m != 0 {
+            let temp = (a | m) & neg_pow2(m, mask);
+            if temp <= b {
+                a = temp;
+            }
+        } else if a & !c & m != 0 {
+            let temp = (c | m) & neg_pow2(m, mask);
+            if temp <= d {
+                c = temp;
+            }
+        }
+        m >>= 1;
+    }
+    a ^ c
+}
+
+fn max_xor(a: u128, mut b: u128, c: u128, mut d: u128, top: u128) -> u128 {
+    let mut m = top;
+    while m != 0 {
+        if b & d & m != 0 {
+            let temp = (b - m) | (m - 1);
+            if temp >= a {
+                b = temp;
+            } else {
+                let temp = (d - m) | (m - 1);
+                if temp >= c {
+                    d = temp;
+                }
+            }
+        }
+        m >>= 1;
+    }
+    b ^ d
+}
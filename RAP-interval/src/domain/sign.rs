@@ -0,0 +1,77 @@
+//! The sign domain of the crop meet, after Rodrigues, Campos and Pereira, "A Fast and
+//! Low-Overhead Technique to Secure Programs Against Integer Overflows" (CGO 2013).
+//!
+//! Widening only moves the lower bound of a value down and its upper bound up, so the
+//! direction in which it moved a variable is a sign: `Zero` if neither bound moved,
+//! `Negative` if the lower one did, `Positive` if the upper one did and `Unknown` if both
+//! did. Signs are ordered by the bounds they mark, from `Zero` up to `Unknown`, and a
+//! variable records the join of the directions of its widening steps. The crop meet then
+//! only refines the bounds that widening moved. The original analysis writes the signs as
+//! '0', '-', '+' and '?'.
+
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub enum Sign {
+    #[default]
+    Zero,
+    Negative,
+    Positive,
+    Unknown,
+}
+
+impl Sign {
+    /// The sign marking the lower bound if `lower` holds and the upper one if `upper` does.
+    pub fn of_bounds(lower: bool, upper: bool) -> Self {
+        match (lower, upper) {
+            (false, false) => Sign::Zero,
+            (true, false) => Sign::Negative,
+            (false, true) => Sign::Positive,
+            (true, true) => Sign::Unknown,
+        }
+    }
+
+    /// Whether the lower bound is marked.
+    pub fn lower(self) -> bool {
+        matches!(self, Sign::Negative | Sign::Unknown)
+    }
+
+    /// Whether the upper bound is marked.
+    pub fn upper(self) -> bool {
+        matches!(self, Sign::Positive | Sign::Unknown)
+    }
+
+    /// The bounds marked by either sign.
+    pub fn join(self, other: Self) -> Self {
+        Self::of_bounds(self.lower() || other.lower(), self.upper() || other.upper())
+    }
+
+    /// The bounds marked by both signs.
+    pub fn meet(self, other: Self) -> Self {
+        Self::of_bounds(self.lower() && other.lower(), self.upper() && other.upper())
+    }
+
+    /// The bounds marked by `self` but not by `other`.
+    pub fn without(self, other: Self) -> Self {
+        Self::of_bounds(
+            self.lower() && !other.lower(),
+            self.upper() && !other.upper(),
+        )
+    }
+
+    pub fn leq(self, other: Self) -> bool {
+        self.join(other) == other
+    }
+}
+
+impl fmt::Display for Sign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            Sign::Zero => '0',
+            Sign::Negative => '-',
+            Sign::Positive => '+',
+            Sign::Unknown => '?',
+        };
+        write!(f, "{}", c)
+    }
+}
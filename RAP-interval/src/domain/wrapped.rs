@@ -0,0 +1,519 @@
+//! Wrapped intervals, after Navas, Schachte, Søndergaard and Stuckey, "Signedness-Agnostic
+//! Program Analysis: Precise Integer Bounds for Low-Level Code" (APLAS 2012).
+//!
+//! A wrapped interval `[start, end]` of width `n` is the arc going clockwise from `start` to
+//! `end` on the circle of the `n`-bit patterns, so `[250, 3]` of width 8 holds 250..=255 and
+//! 0..=3. Bit patterns are interpreted as signed or unsigned numbers only by the operations
+//! that need it (division, remainder, right shifts and extension), which lets the domain
+//! follow arithmetic that wraps around without losing all precision.
+
+use num_traits::{FromPrimitive, PrimInt};
+
+use super::range::{IntType, Range, RangeType};
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum WrappedKind {
+    Bottom,
+    Top,
+    Interval { start: u128, end: u128 },
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct WrappedRange {
+    pub bits: u16,
+    pub kind: WrappedKind,
+}
+
+/// All patterns of `bits` bits.
+fn mask(bits: u16) -> u128 {
+    u128::MAX >> (128 - bits)
+}
+
+/// The signed value of the `bits`-bit pattern `x`.
+fn sext(x: u128, bits: u16) -> i128 {
+    ((x << (128 - bits)) as i128) >> (128 - bits)
+}
+
+impl WrappedRange {
+    /// The arc from `start` to `end`; the arc covering every pattern is top.
+    pub fn new(bits: u16, start: u128, end: u128) -> Self {
+        let (start, end) = (start & mask(bits), end & mask(bits));
+        if end.wrapping_sub(start) & mask(bits) == mask(bits) {
+            return Self::top(bits);
+        }
+        Self {
+            bits,
+            kind: WrappedKind::Interval { start, end },
+        }
+    }
+
+    pub fn bottom(bits: u16) -> Self {
+        Self {
+            bits,
+            kind: WrappedKind::Bottom,
+        }
+    }
+
+    pub fn top(bits: u16) -> Self {
+        Self {
+            bits,
+            kind: WrappedKind::Top,
+        }
+    }
+
+    pub fn constant(bits: u16, value: u128) -> Self {
+        Self::new(bits, value, value)
+    }
+
+    pub fn is_bottom(&self) -> bool {
+        self.kind == WrappedKind::Bottom
+    }
+
+    pub fn is_top(&self) -> bool {
+        self.kind == WrappedKind::Top
+    }
+
+    /// The first and last pattern of the arc; top starts at 0.
+    pub fn bounds(&self) -> Option<(u128, u128)> {
+        match self.kind {
+            WrappedKind::Bottom => None,
+            WrappedKind::Top => Some((0, mask(self.bits))),
+            WrappedKind::Interval { start, end } => Some((start, end)),
+        }
+    }
+
+    /// The number of patterns in the arc, minus one.
+    fn span(&self) -> u128 {
+        match self.bounds() {
+            Some((start, end)) => end.wrapping_sub(start) & mask(self.bits),
+            None => 0,
+        }
+    }
+
+    fn mask(&self) -> u128 {
+        mask(self.bits)
+    }
+
+    pub fn contains(&self, x: u128) -> bool {
+        match self.kind {
+            WrappedKind::Bottom => false,
+            WrappedKind::Top => x <= self.mask(),
+            WrappedKind::Interval { start, .. } => {
+                x <= self.mask() && x.wrapping_sub(start) & self.mask() <= self.span()
+            }
+        }
+    }
+
+    /// Whether every pattern of `self` is in `other`.
+    pub fn leq(&self, other: &Self) -> bool {
+        match (self.kind, other.kind) {
+            (WrappedKind::Bottom, _) | (_, WrappedKind::Top) => true,
+            (_, WrappedKind::Bottom) | (WrappedKind::Top, _) => false,
+            (
+                WrappedKind::Interval { start: a, end: b },
+                WrappedKind::Interval { start: c, end: d },
+            ) => {
+                other.contains(a)
+                    && other.contains(b)
+                    && (!self.contains(c) || a == c)
+                    && (!self.contains(d) || b == d)
+            }
+        }
+    }
+
+    /// The smallest arc containing both arcs. Two arcs may have two incomparable minimal
+    /// upper bounds, in which case the one that adds fewer patterns is chosen.
+    pub fn join(&self, other: &Self) -> Self {
+        if self.leq(other) {
+            return *other;
+        }
+        if other.leq(self) {
+            return *self;
+        }
+        let (a, b) = self.bounds().unwrap();
+        let (c, d) = other.bounds().unwrap();
+        if other.contains(a) && other.contains(b) && self.contains(c) && self.contains(d) {
+            return Self::top(self.bits);
+        }
+        if other.contains(b) && self.contains(c) {
+            return Self::new(self.bits, a, d);
+        }
+        if other.contains(a) && self.contains(d) {
+            return Self::new(self.bits, c, b);
+        }
+        // Disjoint: close the smaller of the two gaps, or the one after the lower start
+        let gap_after = c.wrapping_sub(b) & self.mask();
+        let gap_before = a.wrapping_sub(d) & self.mask();
+        if (gap_after, a) <= (gap_before, c) {
+            Self::new(self.bits, a, d)
+        } else {
+            Self::new(self.bits, c, b)
+        }
+    }
+
+    /// An arc containing the patterns of both arcs. The intersection of two arcs may be two
+    /// arcs, which are then joined.
+    pub fn meet(&self, other: &Self) -> Self {
+        if self.leq(other) {
+            return *self;
+        }
+        if other.leq(self) {
+            return *other;
+        }
+        let mut result = Self::bottom(self.bits);
+        for (a, b) in self.south_split() {
+            for &(c, d) in &other.south_split() {
+                let (lo, hi) = (a.max(c), b.min(d));
+                if lo <= hi {
+                    result = result.join(&Self::new(self.bits, lo, hi));
+                }
+            }
+        }
+        result
+    }
+
+    /// The arc cut where it crosses from the largest pattern to 0, as unsigned intervals.
+    fn south_split(&self) -> Vec<(u128, u128)> {
+        match self.bounds() {
+            None => Vec::new(),
+            Some((start, end)) if start <= end => vec![(start, end)],
+            Some((start, end)) => vec![(start, self.mask()), (0, end)],
+        }
+    }
+
+    /// The arc also cut where the signed value crosses from the largest to the smallest, so
+    /// that each piece has a single sign and is an interval in both interpretations.
+    fn cut(&self) -> Vec<(u128, u128)> {
+        let north = 1u128 << (self.bits - 1);
+        let mut pieces = Vec::new();
+        for (a, b) in self.south_split() {
+            if a < north && b >= north {
+                pieces.push((a, north - 1));
+                pieces.push((north, b));
+            } else {
+                pieces.push((a, b));
+            }
+        }
+        pieces
+    }
+
+    /// The join of `op` applied to every pair of pieces.
+    fn join_pieces(
+        &self,
+        lhs: Vec<(u128, u128)>,
+        rhs: Vec<(u128, u128)>,
+        op: impl Fn((u128, u128), (u128, u128)) -> Self,
+    ) -> Self {
+        let mut result = Self::bottom(self.bits);
+        for &l in &lhs {
+            for &r in &rhs {
+                result = result.join(&op(l, r));
+            }
+        }
+        result
+    }
+
+    /// The arc from `lo` to `hi` of the unbounded results, if it does not cover everything.
+    fn unsigned_arc(&self, lo: Option<u128>, hi: Option<u128>) -> Self {
+        match (lo, hi) {
+            (Some(lo), Some(hi)) if hi - lo <= self.mask() => Self::new(self.bits, lo, hi),
+            _ => Self::top(self.bits),
+        }
+    }
+
+    fn signed_arc(&self, lo: Option<i128>, hi: Option<i128>) -> Self {
+        match (lo, hi) {
+            (Some(lo), Some(hi)) => match hi.checked_sub(lo) {
+                Some(span) if span as u128 <= self.mask() => {
+                    Self::new(self.bits, lo as u128, hi as u128)
+                }
+                _ => Self::top(self.bits),
+            },
+            _ => Self::top(self.bits),
+        }
+    }
+
+    /// Whether the sum of two arcs' sizes is at most the number of patterns, so that a sum
+    /// or difference of them is an arc.
+    fn fits_sum(&self, other: &Self) -> bool {
+        self.span()
+            .checked_add(other.span())
+            .is_some_and(|span| span <= self.mask())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return Self::bottom(self.bits);
+        }
+        match (self.bounds().unwrap(), other.bounds().unwrap()) {
+            ((a, b), (c, d)) if self.fits_sum(other) => {
+                Self::new(self.bits, a.wrapping_add(c), b.wrapping_add(d))
+            }
+            _ => Self::top(self.bits),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return Self::bottom(self.bits);
+        }
+        match (self.bounds().unwrap(), other.bounds().unwrap()) {
+            ((a, b), (c, d)) if self.fits_sum(other) => {
+                Self::new(self.bits, a.wrapping_sub(d), b.wrapping_sub(c))
+            }
+            _ => Self::top(self.bits),
+        }
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::constant(self.bits, 0).sub(self)
+    }
+
+    /// Each pair of pieces is multiplied as unsigned and as signed numbers; both results
+    /// hold the product, so their meet does too.
+    pub fn mul(&self, other: &Self) -> Self {
+        let bits = self.bits;
+        self.join_pieces(self.cut(), other.cut(), |(a, b), (c, d)| {
+            let unsigned = self.unsigned_arc(a.checked_mul(c), b.checked_mul(d));
+            let (a, b, c, d) = (sext(a, bits), sext(b, bits), sext(c, bits), sext(d, bits));
+            let corners = [
+                a.checked_mul(c),
+                a.checked_mul(d),
+                b.checked_mul(c),
+                b.checked_mul(d),
+            ];
+            let signed = if corners.iter().all(Option::is_some) {
+                let corners = corners.map(Option::unwrap);
+                self.signed_arc(corners.iter().min().copied(), corners.iter().max().copied())
+            } else {
+                Self::top(bits)
+            };
+            unsigned.meet(&signed)
+        })
+    }
+
+    /// The pieces of a divisor without 0, which panics.
+    fn nonzero_pieces(pieces: Vec<(u128, u128)>) -> Vec<(u128, u128)> {
+        pieces
+            .into_iter()
+            .filter(|&(_, d)| d != 0)
+            .map(|(c, d)| (c.max(1), d))
+            .collect()
+    }
+
+    pub fn udiv(&self, other: &Self) -> Self {
+        let divisors = Self::nonzero_pieces(other.south_split());
+        self.join_pieces(self.south_split(), divisors, |(a, b), (c, d)| {
+            Self::new(self.bits, a / d, b / c)
+        })
+    }
+
+    pub fn sdiv(&self, other: &Self) -> Self {
+        let bits = self.bits;
+        let divisors = Self::nonzero_pieces(other.cut());
+        self.join_pieces(self.cut(), divisors, |(a, b), (c, d)| {
+            let (a, b, c, d) = (sext(a, bits), sext(b, bits), sext(c, bits), sext(d, bits));
+            let corners = [
+                a.checked_div(c),
+                a.checked_div(d),
+                b.checked_div(c),
+                b.checked_div(d),
+            ];
+            if corners.iter().any(Option::is_none) {
+                return Self::top(bits);
+            }
+            let corners = corners.map(Option::unwrap);
+            self.signed_arc(corners.iter().min().copied(), corners.iter().max().copied())
+        })
+    }
+
+    pub fn urem(&self, other: &Self) -> Self {
+        let divisors = Self::nonzero_pieces(other.south_split());
+        self.join_pieces(self.south_split(), divisors, |(a, b), (c, d)| {
+            if b < c {
+                Self::new(self.bits, a, b)
+            } else {
+                Self::new(self.bits, 0, b.min(d - 1))
+            }
+        })
+    }
+
+    /// The remainder has the sign of the dividend and is smaller than the divisor.
+    pub fn srem(&self, other: &Self) -> Self {
+        let bits = self.bits;
+        let divisors = Self::nonzero_pieces(other.cut());
+        self.join_pieces(self.cut(), divisors, |(a, b), (c, d)| {
+            let (a, b) = (sext(a, bits), sext(b, bits));
+            let largest = sext(c, bits)
+                .unsigned_abs()
+                .max(sext(d, bits).unsigned_abs());
+            let bound = (largest - 1) as i128;
+            if a >= 0 {
+                Self::new(bits, 0, b.min(bound) as u128)
+            } else {
+                Self::new(bits, a.max(-bound) as u128, 0)
+            }
+        })
+    }
+
+    pub fn not(&self) -> Self {
+        match self.kind {
+            WrappedKind::Interval { start, end } => Self::new(self.bits, !end, !start),
+            _ => *self,
+        }
+    }
+
+    /// A bitwise operation evaluated on unsigned pieces, which never leaves the pattern width.
+    fn bitwise(
+        &self,
+        other: &Self,
+        op: impl Fn(&Range<u128>, &Range<u128>) -> Range<u128>,
+    ) -> Self {
+        self.join_pieces(self.south_split(), other.south_split(), |(a, b), (c, d)| {
+            let result = op(
+                &Range::new(a, b, RangeType::Regular),
+                &Range::new(c, d, RangeType::Regular),
+            );
+            Self::new(self.bits, result.get_lower(), result.get_upper())
+        })
+    }
+
+    pub fn bitand(&self, other: &Self) -> Self {
+        self.bitwise(other, Range::bitand)
+    }
+
+    pub fn bitor(&self, other: &Self) -> Self {
+        self.bitwise(other, Range::bitor)
+    }
+
+    pub fn bitxor(&self, other: &Self) -> Self {
+        self.bitwise(other, Range::bitxor)
+    }
+
+    /// The shift amounts that are actually applied to a value of this width. `amount` may
+    /// be of another width. A `checked` shift panics when the amount is not below the width;
+    /// otherwise the amount is masked.
+    fn shift_amounts(&self, amount: &Self, checked: bool) -> Vec<u32> {
+        let width = self.bits as u128;
+        if amount.is_bottom() {
+            return Vec::new();
+        }
+        if checked {
+            return (0..width)
+                .filter(|&k| amount.contains(k))
+                .map(|k| k as u32)
+                .collect();
+        }
+        if amount.span() >= width - 1 {
+            return (0..self.bits as u32).collect();
+        }
+        let start = amount.bounds().unwrap().0;
+        let mut amounts: Vec<u32> = (0..=amount.span())
+            .map(|i| (start.wrapping_add(i) & amount.mask()) % width)
+            .map(|k| k as u32)
+            .collect();
+        amounts.sort();
+        amounts.dedup();
+        amounts
+    }
+
+    /// `self << amount` is a multiplication by `2^amount`.
+    pub fn shl(&self, amount: &Self, checked: bool) -> Self {
+        let mut result = Self::bottom(self.bits);
+        for k in self.shift_amounts(amount, checked) {
+            result = result.join(&self.mul(&Self::constant(self.bits, 1 << k)));
+        }
+        result
+    }
+
+    /// `self >> amount` with zeros shifted in.
+    pub fn lshr(&self, amount: &Self, checked: bool) -> Self {
+        let mut result = Self::bottom(self.bits);
+        for k in self.shift_amounts(amount, checked) {
+            for (a, b) in self.south_split() {
+                result = result.join(&Self::new(self.bits, a >> k, b >> k));
+            }
+        }
+        result
+    }
+
+    /// `self >> amount` with the sign bit shifted in.
+    pub fn ashr(&self, amount: &Self, checked: bool) -> Self {
+        let mut result = Self::bottom(self.bits);
+        for k in self.shift_amounts(amount, checked) {
+            for (a, b) in self.cut() {
+                let (a, b) = (sext(a, self.bits) >> k, sext(b, self.bits) >> k);
+                result = result.join(&Self::new(self.bits, a as u128, b as u128));
+            }
+        }
+        result
+    }
+
+    /// The patterns as `bits`-bit patterns: truncated, or extended with the sign bit if the
+    /// source is `signed` and with zeros otherwise.
+    pub fn cast(&self, bits: u16, signed: bool) -> Self {
+        if self.is_bottom() {
+            return Self::bottom(bits);
+        }
+        if bits <= self.bits {
+            let (start, end) = self.bounds().unwrap();
+            return if self.is_top() || self.span() >= mask(bits) {
+                Self::top(bits)
+            } else {
+                Self::new(bits, start, end)
+            };
+        }
+        let pieces = if signed {
+            self.cut()
+        } else {
+            self.south_split()
+        };
+        let mut result = Self::bottom(bits);
+        for (a, b) in pieces {
+            let (a, b) = if signed {
+                (sext(a, self.bits) as u128, sext(b, self.bits) as u128)
+            } else {
+                (a, b)
+            };
+            result = result.join(&Self::new(bits, a, b));
+        }
+        result
+    }
+
+    /// The bit patterns of the values of `range` in `bits` bits; unknown ranges give top.
+    pub fn from_range<T: PrimInt>(range: &Range<T>, bits: u16) -> Self {
+        if range.is_empty() {
+            return Self::bottom(bits);
+        }
+        if range.is_unknown() {
+            return Self::top(bits);
+        }
+        let pattern = |x: T| x.to_i128().map(|x| x as u128).or_else(|| x.to_u128());
+        match (pattern(range.get_lower()), pattern(range.get_upper())) {
+            (Some(lb), Some(ub)) if ub.wrapping_sub(lb) <= mask(bits) => Self::new(bits, lb, ub),
+            _ => Self::top(bits),
+        }
+    }
+
+    /// The values of type `ty` with these bit patterns. An arc that crosses the bound of the
+    /// type holds both its smallest and its largest value, which gives the whole type.
+    pub fn to_range<T: PrimInt + FromPrimitive>(&self, ty: IntType) -> Range<T> {
+        let (start, end) = match self.kind {
+            WrappedKind::Bottom => {
+                return Range::new(T::min_value(), T::max_value(), RangeType::Empty)
+            }
+            WrappedKind::Top => return Range::of_type(ty),
+            WrappedKind::Interval { start, end } => (start, end),
+        };
+        let bounds = if ty.signed {
+            let (lb, ub) = (sext(start, self.bits), sext(end, self.bits));
+            (lb <= ub).then(|| (T::from_i128(lb), T::from_i128(ub)))
+        } else {
+            (start <= end).then(|| (T::from_u128(start), T::from_u128(end)))
+        };
+        match bounds {
+            Some((Some(lb), Some(ub))) => Range::new(lb, ub, RangeType::Regular),
+            _ => Range::of_type(ty),
+        }
+    }
+}
@@ -270,28 +270,28 @@ impl SsaVisitor<'_, '_> {
 
 impl<'tcx> Visitor<'tcx> for SsaVisitor<'tcx, '_> {
     fn visit_local(&mut self, local: Local, ctxt: PlaceContext, loc: Location) {
-        // match ctxt {
-        //     PlaceContext::MutatingUse(MutatingUseContext::Projection)
-        //     | PlaceContext::NonMutatingUse(NonMutatingUseContext::Projection) => bug!(),
-        //     // Anything can happen with raw pointers, so remove them.
-        //     PlaceContext::NonMutatingUse(NonMutatingUseContext::AddressOf)
-        //     | PlaceContext::MutatingUse(_) => {
-        //         self.assignments[local] = Set1::Many;
-        //     }
-        //     // Immutable borrows are ok, but we need to delay a check that the type is `Freeze`.
-        //     PlaceContext::NonMutatingUse(
-        //         NonMutatingUseContext::SharedBorrow | NonMutatingUseContext::FakeBorrow,
-        //     ) => {
-        //         self.borrowed_locals.insert(local);
-        //         self.check_dominates(local, loc);
-        //         self.direct_uses[local] += 1;
-        //     }
-        //     PlaceContext::NonMutatingUse(_) => {
-        //         self.check_dominates(local, loc);
-        //         self.direct_uses[local] += 1;
-        //     }
-        //     PlaceContext::NonUse(_) => {}
-        // }
+        match ctxt {
+            PlaceContext::MutatingUse(MutatingUseContext::Projection)
+            | PlaceContext::NonMutatingUse(NonMutatingUseContext::Projection) => bug!(),
+            // Anything can happen with raw pointers, so remove them.
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::RawBorrow)
+            | PlaceContext::MutatingUse(_) => {
+                self.assignments[local] = Set1::Many;
+            }
+            // Immutable borrows are ok, but we need to delay a check that the type is `Freeze`.
+            PlaceContext::NonMutatingUse(
+                NonMutatingUseContext::SharedBorrow | NonMutatingUseContext::FakeBorrow,
+            ) => {
+                self.borrowed_locals.insert(local);
+                self.check_dominates(local, loc);
+                self.direct_uses[local] += 1;
+            }
+            PlaceContext::NonMutatingUse(_) => {
+                self.check_dominates(local, loc);
+                self.direct_uses[local] += 1;
+            }
+            PlaceContext::NonUse(_) => {}
+        }
     }
 
     fn visit_place(&mut self, place: &Place<'tcx>, ctxt: PlaceContext, loc: Location) {
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// An SSA value of a `Function`, i.e. an index into `Function::values`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(pub u32);
+
+/// A basic block of a `Function`, i.e. an index into `Function::blocks`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+impl ValueId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl BlockId {
+    pub const ENTRY: BlockId = BlockId(0);
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Debug for ValueId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl fmt::Debug for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+/// The type of a value. Only scalars carry ranges; everything else is `Opaque`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarTy {
+    Bool,
+    Char,
+    Int {
+        bits: u16,
+        signed: bool,
+    },
+    Float {
+        bits: u16,
+    },
+    /// Pointers, aggregates, unit and anything else the analysis does not track.
+    Opaque,
+}
+
+impl ScalarTy {
+    pub fn is_integer(&self) -> bool {
+        matches!(self, ScalarTy::Int { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub ty: ScalarTy,
+    /// Source-level name such as `i#3`, if known.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operand {
+    Value(ValueId),
+    /// Integer constants are stored sign- or zero-extended according to their type.
+    Const(i128),
+    /// Floating-point constants, as the bits of an `f64`; `f32` constants are widened,
+    /// which is exact.
+    Float(u64),
+}
+
+impl Operand {
+    pub fn value(&self) -> Option<ValueId> {
+        match self {
+            Operand::Value(value) => Some(*value),
+            Operand::Const(_) | Operand::Float(_) => None,
+        }
+    }
+
+    pub fn constant(&self) -> Option<i128> {
+        match self {
+            Operand::Const(c) => Some(*c),
+            Operand::Value(_) | Operand::Float(_) => None,
+        }
+    }
+
+    pub fn float(&self) -> Option<f64> {
+        match self {
+            Operand::Float(bits) => Some(f64::from_bits(*bits)),
+            Operand::Value(_) | Operand::Const(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    /// The predicate that holds on the false side of the comparison.
+    pub fn negate(self) -> CmpOp {
+        match self {
+            CmpOp::Eq => CmpOp::Ne,
+            CmpOp::Ne => CmpOp::Eq,
+            CmpOp::Lt => CmpOp::Ge,
+            CmpOp::Le => CmpOp::Gt,
+            CmpOp::Gt => CmpOp::Le,
+            CmpOp::Ge => CmpOp::Lt,
+        }
+    }
+
+    /// The predicate with its operands swapped, `a < b` <=> `b > a`.
+    pub fn swap(self) -> CmpOp {
+        match self {
+            CmpOp::Eq => CmpOp::Eq,
+            CmpOp::Ne => CmpOp::Ne,
+            CmpOp::Lt => CmpOp::Gt,
+            CmpOp::Le => CmpOp::Ge,
+            CmpOp::Gt => CmpOp::Lt,
+            CmpOp::Ge => CmpOp::Le,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstKind {
+    /// A plain copy of another value or a constant.
+    Assign(Operand),
+    /// `checked` is set for arithmetic that cannot overflow: it panics on overflow (debug
+    /// builds) or overflow is undefined behaviour (`unchecked_*`). Other arithmetic wraps.
+    /// For shifts, `checked` means the amount is below the bit width; otherwise it is masked.
+    Binary {
+        op: BinOp,
+        lhs: Operand,
+        rhs: Operand,
+        checked: bool,
+    },
+    Unary {
+        op: UnOp,
+        operand: Operand,
+    },
+    /// Conversion of `operand` to the type of the destination.
+    Cast {
+        operand: Operand,
+    },
+    /// A boolean comparison.
+    Compare {
+        op: CmpOp,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    /// One operand per predecessor of the block.
+    Phi {
+        operands: Vec<(BlockId, Operand)>,
+    },
+    /// Renames `source` on one successor of a conditional branch, so the branch
+    /// condition can be attached to the new value.
+    Sigma {
+        source: ValueId,
+    },
+    Call {
+        callee: String,
+        args: Vec<Operand>,
+    },
+    /// Anything the analysis does not model (loads, aggregates, ...); its result is unknown.
+    Opaque,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub dest: ValueId,
+    pub kind: InstKind,
+}
+
+impl Instruction {
+    /// The values read by this instruction, in operand order.
+    pub fn uses(&self) -> Vec<ValueId> {
+        let operands: Vec<Operand> = match &self.kind {
+            InstKind::Assign(op)
+            | InstKind::Unary { operand: op, .. }
+            | InstKind::Cast { operand: op } => {
+                vec![*op]
+            }
+            InstKind::Binary { lhs, rhs, .. } | InstKind::Compare { lhs, rhs, .. } => {
+                vec![*lhs, *rhs]
+            }
+            InstKind::Phi { operands } => operands.iter().map(|(_, op)| *op).collect(),
+            InstKind::Sigma { source } => vec![Operand::Value(*source)],
+            InstKind::Call { args, .. } => args.clone(),
+            InstKind::Opaque => Vec::new(),
+        };
+        operands.iter().filter_map(Operand::value).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Terminator {
+    Goto(BlockId),
+    /// Two-way branch on a boolean.
+    Branch {
+        cond: Operand,
+        then_bb: BlockId,
+        else_bb: BlockId,
+    },
+    /// Multi-way branch on an integer, `otherwise` is taken when no value matches.
+    Switch {
+        discr: Operand,
+        targets: Vec<(i128, BlockId)>,
+        otherwise: BlockId,
+    },
+    Return,
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Goto(target) => vec![*target],
+            Terminator::Branch {
+                then_bb, else_bb, ..
+            } => vec![*then_bb, *else_bb],
+            Terminator::Switch {
+                targets, otherwise, ..
+            } => targets
+                .iter()
+                .map(|(_, target)| *target)
+                .chain(std::iter::once(*otherwise))
+                .collect(),
+            Terminator::Return | Terminator::Unreachable => Vec::new(),
+        }
+    }
+}
+
+/// Phis come first in `insts`, then sigmas, then ordinary instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub insts: Vec<Instruction>,
+    pub term: Terminator,
+}
+
+/// A function in SSA form, independent of rustc: every value is defined exactly once,
+/// either as an argument or as the destination of one instruction.
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub args: Vec<ValueId>,
+    pub values: Vec<Value>,
+    /// `blocks[0]` is the entry block.
+    pub blocks: Vec<Block>,
+    /// The values assigned to the return place; the result of the function is their join.
+    pub return_values: Vec<ValueId>,
+}
+
+impl Function {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            args: Vec::new(),
+            values: Vec::new(),
+            blocks: Vec::new(),
+            return_values: Vec::new(),
+        }
+    }
+
+    pub fn new_value(&mut self, ty: ScalarTy, name: Option<String>) -> ValueId {
+        self.values.push(Value { ty, name });
+        ValueId(self.values.len() as u32 - 1)
+    }
+
+    pub fn new_block(&mut self) -> BlockId {
+        self.blocks.push(Block {
+            insts: Vec::new(),
+            term: Terminator::Unreachable,
+        });
+        BlockId(self.blocks.len() as u32 - 1)
+    }
+
+    pub fn value(&self, id: ValueId) -> &Value {
+        &self.values[id.index()]
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.index()]
+    }
+
+    pub fn block_mut(&mut self, id: BlockId) -> &mut Block {
+        &mut self.blocks[id.index()]
+    }
+
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len() as u32).map(BlockId)
+    }
+
+    pub fn push_inst(&mut self, block: BlockId, dest: ValueId, kind: InstKind) {
+        self.block_mut(block).insts.push(Instruction { dest, kind });
+    }
+
+    /// The block and instruction index defining each non-argument value.
+    pub fn def_sites(&self) -> HashMap<ValueId, (BlockId, usize)> {
+        let mut defs = HashMap::new();
+        for block in self.block_ids() {
+            for (i, inst) in self.block(block).insts.iter().enumerate() {
+                defs.insert(inst.dest, (block, i));
+            }
+        }
+        defs
+    }
+
+    /// The instruction defining `value`, or `None` for arguments.
+    pub fn def_of(&self, value: ValueId) -> Option<&Instruction> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.insts.iter())
+            .find(|inst| inst.dest == value)
+    }
+
+    /// Predecessors of every block, in the order their terminators are visited.
+    pub fn predecessors(&self) -> HashMap<BlockId, Vec<BlockId>> {
+        let mut preds: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+        for block in self.block_ids() {
+            preds.entry(block).or_default();
+            for succ in self.block(block).term.successors() {
+                let list = preds.entry(succ).or_default();
+                if !list.contains(&block) {
+                    list.push(block);
+                }
+            }
+        }
+        preds
+    }
+}
@@ -0,0 +1,497 @@
+use rustc_middle::mir::{
+    self, BasicBlock, Local, Location, Place, ProjectionElem, Rvalue, StatementKind,
+    TerminatorKind,
+};
+use rustc_middle::ty::{self, Ty, TyCtxt};
+
+use std::collections::HashMap;
+
+use super::ir::*;
+use crate::SSA::MemorySSA::MemoryAccessKind;
+use crate::SSA::SSATransformer::SSATransformer;
+
+/// Lowers the renamed body of an `SSATransformer` into a `Function`.
+///
+/// Every SSA version of a local becomes one `ValueId`. Phis and sigmas are taken from the
+/// transformer's side tables. Cleanup blocks and unwind edges are dropped: the analysis only
+/// follows normal control flow. `Assert`, `Drop` and `FalseEdge`-style terminators become
+/// plain gotos, and `Call` becomes an instruction followed by a goto to its return block.
+///
+/// Checked arithmetic (`AddWithOverflow` and friends) produces a tuple in MIR; reading its
+/// field 0 lowers to a `Binary` with `checked` set, reading the overflow flag to `Opaque`.
+///
+/// Address-taken locals are not renamed. Each assignment to one defines a fresh value, and a
+/// read asks the memory SSA for the clobbering access: a must-def that wrote the whole local
+/// passes on the value it stored, anything else (a store through a pointer, a call taking
+/// `&mut`, a memory phi) kills it and the read sees an unknown value.
+pub struct Lowering<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    body: &'a mir::Body<'tcx>,
+    ssa: &'a SSATransformer<'tcx>,
+    function: Function,
+    blocks: HashMap<BasicBlock, BlockId>,
+    values: HashMap<Local, ValueId>,
+    /// The statement or terminator being lowered.
+    location: Location,
+    /// The value stored into an address-taken local by the must-def at each location.
+    stored_values: HashMap<Location, ValueId>,
+    /// Locals holding the tuple result of checked arithmetic, with its operator and operands.
+    checked_results: HashMap<Local, (BinOp, Operand, Operand)>,
+}
+
+pub fn lower<'tcx>(tcx: TyCtxt<'tcx>, ssa: &SSATransformer<'tcx>) -> Function {
+    let body = ssa.body.borrow();
+    let name = tcx.def_path_str(ssa.def_id());
+    Lowering {
+        tcx,
+        body: &body,
+        ssa,
+        function: Function::new(name),
+        blocks: HashMap::new(),
+        values: HashMap::new(),
+        location: mir::START_BLOCK.start_location(),
+        stored_values: HashMap::new(),
+        checked_results: HashMap::new(),
+    }
+    .lower()
+}
+
+impl<'a, 'tcx> Lowering<'a, 'tcx> {
+    fn lower(mut self) -> Function {
+        // Reachable, non-cleanup blocks in index order; START_BLOCK comes first.
+        let mut reachable: Vec<BasicBlock> = mir::traversal::reachable(self.body)
+            .map(|(bb, _)| bb)
+            .filter(|&bb| !self.body.basic_blocks[bb].is_cleanup)
+            .collect();
+        reachable.sort();
+        for &bb in &reachable {
+            let id = self.function.new_block();
+            self.blocks.insert(bb, id);
+        }
+        for arg in self.body.args_iter() {
+            let value = self.value_of(arg);
+            self.function.args.push(value);
+        }
+        for &bb in &reachable {
+            self.lower_block(bb);
+        }
+        self.function
+    }
+
+    fn lower_block(&mut self, bb: BasicBlock) {
+        let block = self.blocks[&bb];
+        let (body, ssa) = (self.body, self.ssa);
+        let data = &body.basic_blocks[bb];
+        for (statement_index, statement) in data.statements.iter().enumerate() {
+            let location = Location {
+                block: bb,
+                statement_index,
+            };
+            self.location = location;
+            if let Some(phi) = ssa.phi_at(location) {
+                let dest = self.value_of(phi.dest);
+                let operands = phi
+                    .operands
+                    .iter()
+                    .filter_map(|(pred, operand)| {
+                        let pred = *self.blocks.get(pred)?;
+                        Some((pred, self.lower_operand(block, operand)))
+                    })
+                    .collect();
+                self.function.push_inst(block, dest, InstKind::Phi { operands });
+                continue;
+            }
+            if let Some(sigma) = ssa.sigma_at(location) {
+                let dest = self.value_of(sigma.dest);
+                let source = self.value_of(sigma.source);
+                self.function.push_inst(block, dest, InstKind::Sigma { source });
+                continue;
+            }
+            if let StatementKind::Assign(box (place, rvalue)) = &statement.kind
+                && let Some(local) = place.as_local()
+            {
+                self.lower_assign(block, local, rvalue);
+            }
+        }
+        self.location = body.terminator_loc(bb);
+        self.lower_terminator(block, data.terminator());
+    }
+
+    fn lower_assign(&mut self, block: BlockId, local: Local, rvalue: &Rvalue<'tcx>) {
+        let kind = match rvalue {
+            Rvalue::Use(operand) => {
+                // `x = copy (_9.0)` on a checked-arithmetic tuple is the arithmetic itself.
+                if let Some(kind) = self.checked_field(operand) {
+                    kind
+                } else {
+                    InstKind::Assign(self.lower_operand(block, operand))
+                }
+            }
+            Rvalue::BinaryOp(op, box (lhs, rhs)) => {
+                let lhs = self.lower_operand(block, lhs);
+                let rhs = self.lower_operand(block, rhs);
+                if let Some(cmp) = lower_cmp_op(*op) {
+                    InstKind::Compare { op: cmp, lhs, rhs }
+                } else if let Some((bin, checked)) = lower_bin_op(*op) {
+                    if checked {
+                        // The tuple itself is opaque; its fields are lowered on use.
+                        self.checked_results.insert(local, (bin, lhs, rhs));
+                        InstKind::Opaque
+                    } else {
+                        // `*Unchecked` operators are UB on overflow and for out of range
+                        // shift amounts; like a panic, the analysis may assume neither happens.
+                        let checked = matches!(
+                            op,
+                            mir::BinOp::AddUnchecked
+                                | mir::BinOp::SubUnchecked
+                                | mir::BinOp::MulUnchecked
+                                | mir::BinOp::ShlUnchecked
+                                | mir::BinOp::ShrUnchecked
+                        );
+                        InstKind::Binary {
+                            op: bin,
+                            lhs,
+                            rhs,
+                            checked,
+                        }
+                    }
+                } else {
+                    InstKind::Opaque
+                }
+            }
+            Rvalue::UnaryOp(op, operand) => {
+                let operand = self.lower_operand(block, operand);
+                match op {
+                    mir::UnOp::Neg => InstKind::Unary {
+                        op: UnOp::Neg,
+                        operand,
+                    },
+                    mir::UnOp::Not => InstKind::Unary {
+                        op: UnOp::Not,
+                        operand,
+                    },
+                    _ => InstKind::Opaque,
+                }
+            }
+            // Only numeric conversions change the value by the rules of `Cast`; transmutes
+            // and pointer casts reinterpret bits or addresses.
+            Rvalue::Cast(
+                mir::CastKind::IntToInt
+                | mir::CastKind::FloatToInt
+                | mir::CastKind::IntToFloat
+                | mir::CastKind::FloatToFloat,
+                operand,
+                _,
+            ) => InstKind::Cast {
+                operand: self.lower_operand(block, operand),
+            },
+            _ => InstKind::Opaque,
+        };
+        let dest = self.def_value(local);
+        self.function.push_inst(block, dest, kind);
+    }
+
+    /// Lowers `copy (t.0)` / `copy (t.1)` where `t` holds the result of checked arithmetic.
+    fn checked_field(&self, operand: &mir::Operand<'tcx>) -> Option<InstKind> {
+        let place = operand.place()?;
+        let [ProjectionElem::Field(field, _)] = place.projection.as_slice() else {
+            return None;
+        };
+        let &(op, lhs, rhs) = self.checked_results.get(&place.local)?;
+        Some(match field.as_usize() {
+            0 => InstKind::Binary {
+                op,
+                lhs,
+                rhs,
+                checked: true,
+            },
+            _ => InstKind::Opaque,
+        })
+    }
+
+    fn lower_terminator(&mut self, block: BlockId, terminator: &mir::Terminator<'tcx>) {
+        let term = match &terminator.kind {
+            TerminatorKind::Goto { target }
+            | TerminatorKind::Assert { target, .. }
+            | TerminatorKind::Drop { target, .. }
+            | TerminatorKind::FalseEdge {
+                real_target: target,
+                ..
+            }
+            | TerminatorKind::FalseUnwind {
+                real_target: target,
+                ..
+            } => self.goto(*target),
+            TerminatorKind::SwitchInt { discr, targets } => {
+                let discr_ty = discr.ty(&self.body.local_decls, self.tcx);
+                let discr = self.lower_operand(block, discr);
+                if discr_ty.is_bool() {
+                    // `switchInt(c) -> [0: else, otherwise: then]`
+                    let else_bb = targets.target_for_value(0);
+                    match (self.blocks.get(&targets.otherwise()), self.blocks.get(&else_bb)) {
+                        (Some(&then_bb), Some(&else_bb)) => Terminator::Branch {
+                            cond: discr,
+                            then_bb,
+                            else_bb,
+                        },
+                        _ => Terminator::Unreachable,
+                    }
+                } else {
+                    let signed = discr_ty.is_signed();
+                    let size = discr_ty.primitive_size(self.tcx);
+                    // `u128` values above `i128::MAX` have no `i128` of their own. The cases are
+                    // kept apart by their bit patterns, but the switch then tests an unknown
+                    // value instead of the discriminant, so nothing is learned from it.
+                    let discr =
+                        if !signed && targets.iter().any(|(value, _)| value > i128::MAX as u128) {
+                            self.unknown(block, self.scalar_ty(discr_ty))
+                        } else {
+                            discr
+                        };
+                    let targets_ir = targets
+                        .iter()
+                        .filter_map(|(value, target)| {
+                            let target = *self.blocks.get(&target)?;
+                            let value = if signed {
+                                size.sign_extend(value) as i128
+                            } else {
+                                value as i128
+                            };
+                            Some((value, target))
+                        })
+                        .collect();
+                    match self.blocks.get(&targets.otherwise()) {
+                        Some(&otherwise) => Terminator::Switch {
+                            discr,
+                            targets: targets_ir,
+                            otherwise,
+                        },
+                        None => Terminator::Unreachable,
+                    }
+                }
+            }
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                ..
+            } => {
+                let callee = match func.const_fn_def() {
+                    Some((def_id, _)) => self.tcx.def_path_str(def_id),
+                    None => String::from("<indirect>"),
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| self.lower_operand(block, &arg.node))
+                    .collect();
+                let dest = match destination.as_local() {
+                    Some(local) => self.def_value(local),
+                    None => self.function.new_value(ScalarTy::Opaque, None),
+                };
+                self.function
+                    .push_inst(block, dest, InstKind::Call { callee, args });
+                match target {
+                    Some(target) => self.goto(*target),
+                    None => Terminator::Unreachable,
+                }
+            }
+            TerminatorKind::Return => Terminator::Return,
+            _ => Terminator::Unreachable,
+        };
+        self.function.block_mut(block).term = term;
+    }
+
+    fn goto(&self, target: BasicBlock) -> Terminator {
+        match self.blocks.get(&target) {
+            Some(&target) => Terminator::Goto(target),
+            None => Terminator::Unreachable,
+        }
+    }
+
+    fn lower_operand(&mut self, block: BlockId, operand: &mir::Operand<'tcx>) -> Operand {
+        match operand {
+            mir::Operand::Copy(place) | mir::Operand::Move(place) => {
+                Operand::Value(self.lower_place(block, place))
+            }
+            mir::Operand::Constant(constant) => {
+                let ty = constant.const_.ty();
+                match constant.const_.try_to_scalar_int() {
+                    Some(scalar) if !ty.is_floating_point() => {
+                        let size = scalar.size();
+                        let bits = scalar.to_bits(size);
+                        if ty.is_signed() {
+                            Operand::Const(size.sign_extend(bits) as i128)
+                        } else {
+                            // `u128` constants above `i128::MAX` do not fit an `Operand::Const`
+                            match i128::try_from(bits) {
+                                Ok(value) => Operand::Const(value),
+                                Err(_) => self.unknown(block, self.scalar_ty(ty)),
+                            }
+                        }
+                    }
+                    Some(scalar) if matches!(ty.kind(), ty::Float(ty::FloatTy::F32)) => {
+                        let value = f32::from_bits(scalar.to_bits(scalar.size()) as u32);
+                        Operand::Float((value as f64).to_bits())
+                    }
+                    Some(scalar) if matches!(ty.kind(), ty::Float(ty::FloatTy::F64)) => {
+                        Operand::Float(scalar.to_bits(scalar.size()) as u64)
+                    }
+                    _ => self.unknown(block, ScalarTy::Opaque),
+                }
+            }
+        }
+    }
+
+    /// A fresh value of type `ty` defined by an `Opaque` instruction in `block`.
+    fn unknown(&mut self, block: BlockId, ty: ScalarTy) -> Operand {
+        let dest = self.function.new_value(ty, None);
+        self.function.push_inst(block, dest, InstKind::Opaque);
+        Operand::Value(dest)
+    }
+
+    /// A bare local is its SSA value; any projection reads memory and yields an unknown value.
+    fn lower_place(&mut self, block: BlockId, place: &Place<'tcx>) -> ValueId {
+        if let Some(local) = place.as_local() {
+            return self.read_local(local);
+        }
+        let ty = place.ty(&self.body.local_decls, self.tcx).ty;
+        let dest = self.function.new_value(self.scalar_ty(ty), None);
+        let kind = match self.checked_field(&mir::Operand::Copy(*place)) {
+            Some(kind) => kind,
+            None => InstKind::Opaque,
+        };
+        self.function.push_inst(block, dest, kind);
+        dest
+    }
+
+    /// The value defined by an assignment to `local`.
+    ///
+    /// The return place, address-taken locals and locals written through a projection
+    /// are not renamed, so each of their assignments gets a fresh value. Reads of such
+    /// locals go through `read_local`; apart from the address-taken ones reached by a
+    /// must-def, they see a value that is never defined, i.e. an unknown one.
+    fn def_value(&mut self, local: Local) -> ValueId {
+        if local != mir::RETURN_PLACE && !self.ssa.is_partially_written(local) {
+            if self.is_address_taken(local) {
+                return self.stored_value(local, self.location);
+            }
+            return self.value_of(local);
+        }
+        let ty = self.scalar_ty(self.body.local_decls[local].ty);
+        let value = self
+            .function
+            .new_value(ty, Some(self.ssa.version_name(local)));
+        if local == mir::RETURN_PLACE {
+            self.function.return_values.push(value);
+        }
+        value
+    }
+
+    /// The value of `local` read at the current location.
+    fn read_local(&mut self, local: Local) -> ValueId {
+        let ssa = self.ssa;
+        let Some(memory_ssa) = &ssa.memory_ssa else {
+            return self.value_of(local);
+        };
+        if !memory_ssa.is_address_taken(local) {
+            return self.value_of(local);
+        }
+        let clobber = memory_ssa
+            .use_at(self.location)
+            .map(|id| memory_ssa.access(memory_ssa.clobbering_access(id, local)));
+        match clobber {
+            Some(MemoryAccessKind::Def {
+                location,
+                must: true,
+                ..
+            }) => self.stored_value(local, *location),
+            _ => self.value_of(local),
+        }
+    }
+
+    fn is_address_taken(&self, local: Local) -> bool {
+        self.ssa
+            .memory_ssa
+            .as_ref()
+            .is_some_and(|memory_ssa| memory_ssa.is_address_taken(local))
+    }
+
+    /// The value the must-def at `location` stores into the address-taken `local`. Reads may
+    /// be lowered before the definition, so the value is created by whichever comes first.
+    fn stored_value(&mut self, local: Local, location: Location) -> ValueId {
+        if let Some(&value) = self.stored_values.get(&location) {
+            return value;
+        }
+        let ty = self.scalar_ty(self.body.local_decls[local].ty);
+        let value = self
+            .function
+            .new_value(ty, Some(self.ssa.version_name(local)));
+        self.stored_values.insert(location, value);
+        value
+    }
+
+    fn value_of(&mut self, local: Local) -> ValueId {
+        if let Some(&value) = self.values.get(&local) {
+            return value;
+        }
+        let ty = self.scalar_ty(self.body.local_decls[local].ty);
+        let value = self
+            .function
+            .new_value(ty, Some(self.ssa.version_name(local)));
+        self.values.insert(local, value);
+        value
+    }
+
+    fn scalar_ty(&self, ty: Ty<'tcx>) -> ScalarTy {
+        let pointer_bits = self.tcx.data_layout.pointer_size.bits() as u16;
+        match ty.kind() {
+            ty::Bool => ScalarTy::Bool,
+            ty::Char => ScalarTy::Char,
+            ty::Int(int_ty) => ScalarTy::Int {
+                bits: int_ty.bit_width().map_or(pointer_bits, |bits| bits as u16),
+                signed: true,
+            },
+            ty::Uint(uint_ty) => ScalarTy::Int {
+                bits: uint_ty.bit_width().map_or(pointer_bits, |bits| bits as u16),
+                signed: false,
+            },
+            ty::Float(float_ty) => ScalarTy::Float {
+                bits: float_ty.bit_width() as u16,
+            },
+            _ => ScalarTy::Opaque,
+        }
+    }
+}
+
+fn lower_cmp_op(op: mir::BinOp) -> Option<CmpOp> {
+    Some(match op {
+        mir::BinOp::Eq => CmpOp::Eq,
+        mir::BinOp::Ne => CmpOp::Ne,
+        mir::BinOp::Lt => CmpOp::Lt,
+        mir::BinOp::Le => CmpOp::Le,
+        mir::BinOp::Gt => CmpOp::Gt,
+        mir::BinOp::Ge => CmpOp::Ge,
+        _ => return None,
+    })
+}
+
+/// The IR operator and whether it is the overflow-checked variant.
+fn lower_bin_op(op: mir::BinOp) -> Option<(BinOp, bool)> {
+    Some(match op {
+        mir::BinOp::Add | mir::BinOp::AddUnchecked => (BinOp::Add, false),
+        mir::BinOp::Sub | mir::BinOp::SubUnchecked => (BinOp::Sub, false),
+        mir::BinOp::Mul | mir::BinOp::MulUnchecked => (BinOp::Mul, false),
+        mir::BinOp::AddWithOverflow => (BinOp::Add, true),
+        mir::BinOp::SubWithOverflow => (BinOp::Sub, true),
+        mir::BinOp::MulWithOverflow => (BinOp::Mul, true),
+        mir::BinOp::Div => (BinOp::Div, false),
+        mir::BinOp::Rem => (BinOp::Rem, false),
+        mir::BinOp::BitAnd => (BinOp::BitAnd, false),
+        mir::BinOp::BitOr => (BinOp::BitOr, false),
+        mir::BinOp::BitXor => (BinOp::BitXor, false),
+        mir::BinOp::Shl | mir::BinOp::ShlUnchecked => (BinOp::Shl, false),
+        mir::BinOp::Shr | mir::BinOp::ShrUnchecked => (BinOp::Shr, false),
+        _ => return None,
+    })
+}
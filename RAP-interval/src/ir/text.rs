@@ -0,0 +1,885 @@
+//! A textual syntax for `Function`, so analyses can be tested without rustc.
+//!
+//! ```text
+//! fn example(%0 "n": i32) -> [%7] {
+//!     undef %8: i32
+//! bb0:
+//!     %1 "i#1": i32 = copy 0
+//!     goto bb1
+//! bb1:
+//!     %2 "i#2": i32 = phi [bb0: %1], [bb2: %5]
+//!     %3: bool = lt %2, %0
+//!     br %3, bb2, bb3
+//! bb2:
+//!     %4: i32 = sigma %2
+//!     %5 "i#3": i32 = add.checked %4, 1
+//!     goto bb1
+//! bb3:
+//!     %6: i32 = sigma %2
+//!     %7 "_0": i32 = copy %6
+//!     return
+//! }
+//! ```
+//!
+//! Values are written `%n`, optionally followed by their source name in quotes and always
+//! followed by their type. Values that are neither arguments nor defined by an instruction
+//! are declared with `undef` before the first block. Blocks are written `bbN` and `N` is
+//! their index. Floating-point constants are written as Rust debug-prints an `f64`, such as
+//! `1.5`, `-2e-9`, `inf` or `NaN`. `//` starts a comment. Printing a parsed function gives back the same text
+//! up to whitespace and comments.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::ir::*;
+
+impl fmt::Display for ScalarTy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalarTy::Bool => write!(f, "bool"),
+            ScalarTy::Char => write!(f, "char"),
+            ScalarTy::Int { bits, signed: true } => write!(f, "i{}", bits),
+            ScalarTy::Int {
+                bits,
+                signed: false,
+            } => write!(f, "u{}", bits),
+            ScalarTy::Float { bits } => write!(f, "f{}", bits),
+            ScalarTy::Opaque => write!(f, "opaque"),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Value(value) => write!(f, "{:?}", value),
+            Operand::Const(c) => write!(f, "{}", c),
+            Operand::Float(bits) => write!(f, "{:?}", f64::from_bits(*bits)),
+        }
+    }
+}
+
+impl BinOp {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "mul",
+            BinOp::Div => "div",
+            BinOp::Rem => "rem",
+            BinOp::BitAnd => "and",
+            BinOp::BitOr => "or",
+            BinOp::BitXor => "xor",
+            BinOp::Shl => "shl",
+            BinOp::Shr => "shr",
+        }
+    }
+}
+
+impl UnOp {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            UnOp::Neg => "neg",
+            UnOp::Not => "not",
+        }
+    }
+}
+
+impl CmpOp {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "eq",
+            CmpOp::Ne => "ne",
+            CmpOp::Lt => "lt",
+            CmpOp::Le => "le",
+            CmpOp::Gt => "gt",
+            CmpOp::Ge => "ge",
+        }
+    }
+}
+
+const BIN_OPS: [BinOp; 10] = [
+    BinOp::Add,
+    BinOp::Sub,
+    BinOp::Mul,
+    BinOp::Div,
+    BinOp::Rem,
+    BinOp::BitAnd,
+    BinOp::BitOr,
+    BinOp::BitXor,
+    BinOp::Shl,
+    BinOp::Shr,
+];
+const UN_OPS: [UnOp; 2] = [UnOp::Neg, UnOp::Not];
+const CMP_OPS: [CmpOp; 6] = [
+    CmpOp::Eq,
+    CmpOp::Ne,
+    CmpOp::Lt,
+    CmpOp::Le,
+    CmpOp::Gt,
+    CmpOp::Ge,
+];
+
+impl fmt::Display for InstKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstKind::Assign(operand) => write!(f, "copy {}", operand),
+            InstKind::Binary {
+                op,
+                lhs,
+                rhs,
+                checked,
+            } => {
+                let suffix = if *checked { ".checked" } else { "" };
+                write!(f, "{}{} {}, {}", op.mnemonic(), suffix, lhs, rhs)
+            }
+            InstKind::Unary { op, operand } => write!(f, "{} {}", op.mnemonic(), operand),
+            InstKind::Cast { operand } => write!(f, "cast {}", operand),
+            InstKind::Compare { op, lhs, rhs } => {
+                write!(f, "{} {}, {}", op.mnemonic(), lhs, rhs)
+            }
+            InstKind::Phi { operands } => {
+                write!(f, "phi")?;
+                for (i, (block, operand)) in operands.iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    write!(f, "{}[{:?}: {}]", sep, block, operand)?;
+                }
+                Ok(())
+            }
+            InstKind::Sigma { source } => write!(f, "sigma {:?}", source),
+            InstKind::Call { callee, args } => {
+                write!(f, "call {:?}(", callee)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            InstKind::Opaque => write!(f, "opaque"),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Goto(target) => write!(f, "goto {:?}", target),
+            Terminator::Branch {
+                cond,
+                then_bb,
+                else_bb,
+            } => write!(f, "br {}, {:?}, {:?}", cond, then_bb, else_bb),
+            Terminator::Switch {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                write!(f, "switch {} [", discr)?;
+                for (i, (value, target)) in targets.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {:?}", value, target)?;
+                }
+                write!(f, "], otherwise {:?}", otherwise)
+            }
+            Terminator::Return => write!(f, "return"),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+impl Function {
+    /// `%n "name": ty`
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>, id: ValueId) -> fmt::Result {
+        let value = self.value(id);
+        write!(f, "{:?}", id)?;
+        if let Some(name) = &value.name {
+            write!(f, " {:?}", name)?;
+        }
+        write!(f, ": {}", value.ty)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Paths such as `<impl Foo for Bar>::new` are quoted
+        let is_path = self
+            .name
+            .starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == ':');
+        if is_path {
+            write!(f, "fn {}(", self.name)?;
+        } else {
+            write!(f, "fn {:?}(", self.name)?;
+        }
+        for (i, &arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            self.fmt_value(f, arg)?;
+        }
+        write!(f, ")")?;
+        if !self.return_values.is_empty() {
+            let values: Vec<String> = self
+                .return_values
+                .iter()
+                .map(|value| format!("{:?}", value))
+                .collect();
+            write!(f, " -> [{}]", values.join(", "))?;
+        }
+        writeln!(f, " {{")?;
+
+        let defs = self.def_sites();
+        for index in 0..self.values.len() {
+            let id = ValueId(index as u32);
+            if !self.args.contains(&id) && !defs.contains_key(&id) {
+                write!(f, "    undef ")?;
+                self.fmt_value(f, id)?;
+                writeln!(f)?;
+            }
+        }
+
+        for block in self.block_ids() {
+            writeln!(f, "{:?}:", block)?;
+            let data = self.block(block);
+            for inst in &data.insts {
+                write!(f, "    ")?;
+                self.fmt_value(f, inst.dest)?;
+                writeln!(f, " = {}", inst.kind)?;
+            }
+            writeln!(f, "    {}", data.term)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// A syntax error, with the 1-based line it occurred on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Keywords, mnemonics, types, function and block names.
+    Ident(String),
+    Value(u32),
+    Int(i128),
+    /// The bits of an `f64`.
+    Float(u64),
+    Str(String),
+    Punct(char),
+    Arrow,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{}`", ident),
+            Token::Value(value) => write!(f, "`%{}`", value),
+            Token::Int(c) => write!(f, "`{}`", c),
+            Token::Float(bits) => write!(f, "`{:?}`", f64::from_bits(*bits)),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Punct(c) => write!(f, "`{}`", c),
+            Token::Arrow => write!(f, "`->`"),
+        }
+    }
+}
+
+fn tokenize(line: &str, line_no: usize) -> Result<Vec<Token>, ParseError> {
+    let error = |message: String| ParseError {
+        line: line_no,
+        message,
+    };
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '/' {
+            chars.next();
+            if chars.next() != Some('/') {
+                return Err(error(String::from("expected `//`")));
+            }
+            break;
+        } else if c == '-' {
+            chars.next();
+            match chars.peek() {
+                Some('>') => {
+                    chars.next();
+                    tokens.push(Token::Arrow);
+                }
+                Some(d) if d.is_ascii_digit() => {
+                    let literal = format!("-{}", take_number(&mut chars));
+                    tokens.push(number(&literal).map_err(error)?);
+                }
+                Some('i') => {
+                    if take_while(&mut chars, |c| c.is_alphabetic()) != "inf" {
+                        return Err(error(String::from("unexpected `-`")));
+                    }
+                    tokens.push(Token::Float(f64::NEG_INFINITY.to_bits()));
+                }
+                _ => return Err(error(String::from("unexpected `-`"))),
+            }
+        } else if c == '%' {
+            chars.next();
+            let digits = take_while(&mut chars, |c| c.is_ascii_digit());
+            let value = digits
+                .parse()
+                .map_err(|_| error(format!("invalid value `%{}`", digits)))?;
+            tokens.push(Token::Value(value));
+        } else if c.is_ascii_digit() {
+            let literal = take_number(&mut chars);
+            tokens.push(number(&literal).map_err(error)?);
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(escaped) => s.push(escaped),
+                        None => return Err(error(String::from("unterminated string"))),
+                    },
+                    Some(c) => s.push(c),
+                    None => return Err(error(String::from("unterminated string"))),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else if c.is_alphabetic() || c == '_' {
+            let ident = take_while(&mut chars, |c| {
+                c.is_alphanumeric() || c == '_' || c == '.' || c == ':'
+            });
+            // `bb0:` is a label, not a path
+            let ident = match ident.strip_suffix(':') {
+                Some(stripped) if !stripped.ends_with(':') => {
+                    tokens.push(Token::Ident(stripped.to_string()));
+                    tokens.push(Token::Punct(':'));
+                    continue;
+                }
+                _ => ident,
+            };
+            tokens.push(Token::Ident(ident));
+        } else if "=:,[](){}".contains(c) {
+            chars.next();
+            tokens.push(Token::Punct(c));
+        } else {
+            return Err(error(format!("unexpected character `{}`", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+/// The characters of a number: digits, a fraction and a signed exponent.
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut s = String::new();
+    while let Some(&c) = chars.peek() {
+        let exponent_sign = (c == '-' || c == '+') && s.ends_with(['e', 'E']);
+        if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+            break;
+        }
+        s.push(c);
+        chars.next();
+    }
+    s
+}
+
+/// An integer, or a floating-point number if it has a fraction or an exponent.
+fn number(literal: &str) -> Result<Token, String> {
+    if literal.contains(['.', 'e', 'E']) {
+        match literal.parse::<f64>() {
+            Ok(value) => Ok(Token::Float(value.to_bits())),
+            Err(_) => Err(format!("invalid number `{}`", literal)),
+        }
+    } else {
+        match literal.parse() {
+            Ok(value) => Ok(Token::Int(value)),
+            Err(_) => Err(format!("integer `{}` out of range", literal)),
+        }
+    }
+}
+
+fn take_while(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    pred: impl Fn(char) -> bool,
+) -> String {
+    let mut s = String::new();
+    while let Some(&c) = chars.peek() {
+        if !pred(c) {
+            break;
+        }
+        s.push(c);
+        chars.next();
+    }
+    s
+}
+
+/// The tokens of one line.
+struct Cursor {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+}
+
+impl Cursor {
+    fn error<T>(&self, message: String) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: self.line,
+            message,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => self.error(String::from("unexpected end of line")),
+        }
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => self.error(format!("expected {}, found {}", expected, token)),
+        }
+    }
+
+    fn punct(&mut self, c: char) -> Result<(), ParseError> {
+        self.expect(Token::Punct(c))
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            token => self.error(format!("expected identifier, found {}", token)),
+        }
+    }
+
+    fn value(&mut self) -> Result<ValueId, ParseError> {
+        match self.next()? {
+            Token::Value(value) => Ok(ValueId(value)),
+            token => self.error(format!("expected value, found {}", token)),
+        }
+    }
+
+    fn int(&mut self) -> Result<i128, ParseError> {
+        match self.next()? {
+            Token::Int(c) => Ok(c),
+            token => self.error(format!("expected integer, found {}", token)),
+        }
+    }
+
+    fn block(&mut self) -> Result<BlockId, ParseError> {
+        let ident = self.ident()?;
+        match ident.strip_prefix("bb").and_then(|n| n.parse().ok()) {
+            Some(n) => Ok(BlockId(n)),
+            None => self.error(format!("expected block, found `{}`", ident)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, ParseError> {
+        match self.next()? {
+            Token::Value(value) => Ok(Operand::Value(ValueId(value))),
+            Token::Int(c) => Ok(Operand::Const(c)),
+            Token::Float(bits) => Ok(Operand::Float(bits)),
+            Token::Ident(ident) if ident == "inf" => Ok(Operand::Float(f64::INFINITY.to_bits())),
+            Token::Ident(ident) if ident == "NaN" => Ok(Operand::Float(f64::NAN.to_bits())),
+            token => self.error(format!("expected operand, found {}", token)),
+        }
+    }
+
+    fn ty(&mut self) -> Result<ScalarTy, ParseError> {
+        let ident = self.ident()?;
+        let bits = |digits: &str| digits.parse::<u16>().ok().filter(|&bits| bits > 0);
+        let ty = match ident.as_str() {
+            "bool" => Some(ScalarTy::Bool),
+            "char" => Some(ScalarTy::Char),
+            "opaque" => Some(ScalarTy::Opaque),
+            _ => {
+                if let Some(digits) = ident.strip_prefix('i') {
+                    bits(digits).map(|bits| ScalarTy::Int { bits, signed: true })
+                } else if let Some(digits) = ident.strip_prefix('u') {
+                    bits(digits).map(|bits| ScalarTy::Int {
+                        bits,
+                        signed: false,
+                    })
+                } else if let Some(digits) = ident.strip_prefix('f') {
+                    bits(digits).map(|bits| ScalarTy::Float { bits })
+                } else {
+                    None
+                }
+            }
+        };
+        match ty {
+            Some(ty) => Ok(ty),
+            None => self.error(format!("unknown type `{}`", ident)),
+        }
+    }
+
+    /// `%n "name": ty`
+    fn value_decl(&mut self) -> Result<(ValueId, Value), ParseError> {
+        let id = self.value()?;
+        let name = match self.peek() {
+            Some(Token::Str(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Some(name)
+            }
+            _ => None,
+        };
+        self.punct(':')?;
+        let ty = self.ty()?;
+        Ok((id, Value { ty, name }))
+    }
+
+    /// A comma separated list up to `close`, the opening delimiter already consumed.
+    fn list<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        if self.eat(&Token::Punct(close)) {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(&Token::Punct(close)) {
+                return Ok(items);
+            }
+            self.punct(',')?;
+        }
+    }
+
+    fn finish(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => self.error(format!("unexpected {}", token)),
+        }
+    }
+}
+
+/// Parses the instruction right of `=`.
+fn parse_inst_kind(cursor: &mut Cursor) -> Result<InstKind, ParseError> {
+    let mnemonic = cursor.ident()?;
+    let (base, checked) = match mnemonic.strip_suffix(".checked") {
+        Some(base) => (base, true),
+        None => (mnemonic.as_str(), false),
+    };
+    if let Some(&op) = BIN_OPS.iter().find(|op| op.mnemonic() == base) {
+        let lhs = cursor.operand()?;
+        cursor.punct(',')?;
+        let rhs = cursor.operand()?;
+        return Ok(InstKind::Binary {
+            op,
+            lhs,
+            rhs,
+            checked,
+        });
+    }
+    if checked {
+        return cursor.error(format!("`{}` cannot be checked", base));
+    }
+    if let Some(&op) = UN_OPS.iter().find(|op| op.mnemonic() == base) {
+        let operand = cursor.operand()?;
+        return Ok(InstKind::Unary { op, operand });
+    }
+    if let Some(&op) = CMP_OPS.iter().find(|op| op.mnemonic() == base) {
+        let lhs = cursor.operand()?;
+        cursor.punct(',')?;
+        let rhs = cursor.operand()?;
+        return Ok(InstKind::Compare { op, lhs, rhs });
+    }
+    match base {
+        "copy" => Ok(InstKind::Assign(cursor.operand()?)),
+        "cast" => Ok(InstKind::Cast {
+            operand: cursor.operand()?,
+        }),
+        "phi" => {
+            let mut operands = Vec::new();
+            while cursor.eat(&Token::Punct('[')) {
+                let block = cursor.block()?;
+                cursor.punct(':')?;
+                let operand = cursor.operand()?;
+                cursor.punct(']')?;
+                operands.push((block, operand));
+                if !cursor.eat(&Token::Punct(',')) {
+                    break;
+                }
+            }
+            Ok(InstKind::Phi { operands })
+        }
+        "sigma" => Ok(InstKind::Sigma {
+            source: cursor.value()?,
+        }),
+        "call" => {
+            let callee = match cursor.next()? {
+                Token::Str(callee) => callee,
+                token => return cursor.error(format!("expected callee, found {}", token)),
+            };
+            cursor.punct('(')?;
+            let args = cursor.list(')', Cursor::operand)?;
+            Ok(InstKind::Call { callee, args })
+        }
+        "opaque" => Ok(InstKind::Opaque),
+        _ => cursor.error(format!("unknown instruction `{}`", mnemonic)),
+    }
+}
+
+fn parse_terminator(cursor: &mut Cursor, keyword: &str) -> Result<Terminator, ParseError> {
+    match keyword {
+        "goto" => Ok(Terminator::Goto(cursor.block()?)),
+        "br" => {
+            let cond = cursor.operand()?;
+            cursor.punct(',')?;
+            let then_bb = cursor.block()?;
+            cursor.punct(',')?;
+            let else_bb = cursor.block()?;
+            Ok(Terminator::Branch {
+                cond,
+                then_bb,
+                else_bb,
+            })
+        }
+        "switch" => {
+            let discr = cursor.operand()?;
+            cursor.punct('[')?;
+            let targets = cursor.list(']', |cursor| {
+                let value = cursor.int()?;
+                cursor.punct(':')?;
+                Ok((value, cursor.block()?))
+            })?;
+            cursor.punct(',')?;
+            cursor.expect(Token::Ident(String::from("otherwise")))?;
+            let otherwise = cursor.block()?;
+            Ok(Terminator::Switch {
+                discr,
+                targets,
+                otherwise,
+            })
+        }
+        "return" => Ok(Terminator::Return),
+        "unreachable" => Ok(Terminator::Unreachable),
+        _ => cursor.error(format!("unknown terminator `{}`", keyword)),
+    }
+}
+
+/// Collects value declarations and checks that every value is declared once.
+struct Values {
+    values: HashMap<ValueId, Value>,
+}
+
+impl Values {
+    fn declare(&mut self, cursor: &Cursor, id: ValueId, value: Value) -> Result<(), ParseError> {
+        if self.values.insert(id, value).is_some() {
+            return cursor.error(format!("{:?} is declared twice", id));
+        }
+        Ok(())
+    }
+}
+
+/// Parses one function in the syntax printed by `Function`'s `Display`.
+pub fn parse(text: &str) -> Result<Function, ParseError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| tokenize(line, i + 1).map(|tokens| (i + 1, tokens)))
+        .filter(|line| !matches!(line, Ok((_, tokens)) if tokens.is_empty()));
+    let end = text.lines().count();
+    let mut next_line = move || -> Result<Cursor, ParseError> {
+        match lines.next() {
+            Some(line) => line.map(|(line, tokens)| Cursor {
+                tokens,
+                pos: 0,
+                line,
+            }),
+            None => Err(ParseError {
+                line: end,
+                message: String::from("unexpected end of input"),
+            }),
+        }
+    };
+
+    let mut values = Values {
+        values: HashMap::new(),
+    };
+
+    // fn name(args) -> [returns] {
+    let mut cursor = next_line()?;
+    cursor.expect(Token::Ident(String::from("fn")))?;
+    let name = match cursor.next()? {
+        Token::Ident(name) | Token::Str(name) => name,
+        token => return cursor.error(format!("expected function name, found {}", token)),
+    };
+    let mut function = Function::new(name);
+    cursor.punct('(')?;
+    let args = cursor.list(')', Cursor::value_decl)?;
+    for (id, value) in args {
+        values.declare(&cursor, id, value)?;
+        function.args.push(id);
+    }
+    if cursor.eat(&Token::Arrow) {
+        cursor.punct('[')?;
+        function.return_values = cursor.list(']', Cursor::value)?;
+    }
+    cursor.punct('{')?;
+    cursor.finish()?;
+
+    let mut blocks: HashMap<BlockId, Block> = HashMap::new();
+    let mut current: Option<(BlockId, Vec<Instruction>)> = None;
+    loop {
+        let mut cursor = next_line()?;
+        match cursor.next()? {
+            Token::Punct('}') => {
+                cursor.finish()?;
+                if current.is_some() {
+                    return cursor.error(String::from("block without terminator"));
+                }
+                break;
+            }
+            Token::Ident(keyword) if keyword == "undef" => {
+                if !blocks.is_empty() || current.is_some() {
+                    return cursor.error(String::from("`undef` after the first block"));
+                }
+                let (id, value) = cursor.value_decl()?;
+                values.declare(&cursor, id, value)?;
+            }
+            Token::Ident(label) if cursor.peek() == Some(&Token::Punct(':')) => {
+                cursor.pos -= 1;
+                let block = cursor.block()?;
+                cursor.punct(':')?;
+                if current.is_some() {
+                    return cursor.error(String::from("block without terminator"));
+                }
+                if blocks.contains_key(&block) {
+                    return cursor.error(format!("{} is defined twice", label));
+                }
+                current = Some((block, Vec::new()));
+            }
+            Token::Value(_) => {
+                cursor.pos -= 1;
+                let Some((_, insts)) = current.as_mut() else {
+                    return cursor.error(String::from("instruction outside of a block"));
+                };
+                let (dest, value) = cursor.value_decl()?;
+                values.declare(&cursor, dest, value)?;
+                cursor.punct('=')?;
+                let kind = parse_inst_kind(&mut cursor)?;
+                insts.push(Instruction { dest, kind });
+            }
+            Token::Ident(keyword) => {
+                let Some((block, insts)) = current.take() else {
+                    return cursor.error(String::from("terminator outside of a block"));
+                };
+                let term = parse_terminator(&mut cursor, &keyword)?;
+                blocks.insert(block, Block { insts, term });
+            }
+            token => return cursor.error(format!("unexpected {}", token)),
+        }
+        cursor.finish()?;
+    }
+    next_line().map_or(Ok(()), |cursor| {
+        cursor.error(String::from("unexpected input after the function"))
+    })?;
+
+    // Ids are indices, so the declared ids must be exactly 0..n.
+    for index in 0..values.values.len() {
+        let id = ValueId(index as u32);
+        match values.values.remove(&id) {
+            Some(value) => function.values.push(value),
+            None => {
+                return Err(ParseError {
+                    line: end,
+                    message: format!("{:?} is not declared", id),
+                })
+            }
+        }
+    }
+    for index in 0..blocks.len() {
+        match blocks.remove(&BlockId(index as u32)) {
+            Some(block) => function.blocks.push(block),
+            None => {
+                return Err(ParseError {
+                    line: end,
+                    message: format!("{:?} is missing", BlockId(index as u32)),
+                })
+            }
+        }
+    }
+    check_references(&function).map_err(|message| ParseError { line: end, message })?;
+    Ok(function)
+}
+
+/// Every value and block that is referred to must exist.
+fn check_references(function: &Function) -> Result<(), String> {
+    let value_exists = |value: ValueId| value.index() < function.values.len();
+    let block_exists = |block: BlockId| block.index() < function.blocks.len();
+    for &value in &function.return_values {
+        if !value_exists(value) {
+            return Err(format!("return value {:?} is not declared", value));
+        }
+    }
+    for block in function.block_ids() {
+        let data = function.block(block);
+        for inst in &data.insts {
+            if let Some(value) = inst.uses().into_iter().find(|&v| !value_exists(v)) {
+                return Err(format!("{:?} uses undeclared {:?}", inst.dest, value));
+            }
+            if let InstKind::Phi { operands } = &inst.kind {
+                if let Some((pred, _)) = operands.iter().find(|(pred, _)| !block_exists(*pred)) {
+                    return Err(format!("{:?} refers to missing {:?}", inst.dest, pred));
+                }
+            }
+        }
+        let cond = match &data.term {
+            Terminator::Branch { cond, .. } => cond.value(),
+            Terminator::Switch { discr, .. } => discr.value(),
+            _ => None,
+        };
+        if let Some(value) = cond.filter(|&v| !value_exists(v)) {
+            return Err(format!(
+                "terminator of {:?} uses undeclared {:?}",
+                block, value
+            ));
+        }
+        if let Some(succ) = data
+            .term
+            .successors()
+            .into_iter()
+            .find(|&b| !block_exists(b))
+        {
+            return Err(format!("{:?} jumps to missing {:?}", block, succ));
+        }
+    }
+    Ok(())
+}
@@ -37,3 +37,5 @@ extern crate tracing;
 
 pub mod SSA;
 pub mod domain;
+pub mod interval_analysis;
+pub mod ir;
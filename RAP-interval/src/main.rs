@@ -61,6 +61,7 @@ use std::io::{self, Write};
 use std::path::PathBuf;
 use std::rc::Rc;
 use tracing::{debug, error, info, warn};
+use RAP_interval::domain::congruence::StridedRange;
 use RAP_interval::domain::ConstraintGraph::ConstraintGraph;
 use RAP_interval::SSA::SSATransformer::*;
 
@@ -132,21 +133,32 @@ impl Callbacks for MyDataflowCallbacks {
 fn analyze_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) {
     let body = tcx.optimized_mir(def_id);
     //不许存储body的可变引用
-    let mut ssa: SSATransformer<'tcx> = SSATransformer::new(tcx, def_id);
+    let options = SSAOptions {
+        phi_placement: PhiPlacement::Pruned,
+        field_sensitive: true,
+        reuse_ssa_locals: true,
+        verify: true,
+    };
+    let mut ssa: SSATransformer<'tcx> = SSATransformer::new(tcx, def_id, options);
+    ssa.insert_sigma_statment();
     ssa.insert_phi_statment();
     ssa.print_phi_mir();
     ssa.rename_variables();
+    for violation in ssa.verify() {
+        println!("SSA violation: {}", violation);
+    }
+    if let Some(memory_ssa) = &ssa.memory_ssa {
+        print!("{:?}", memory_ssa);
+    }
     ssa.analyze();
-    let mut cg: ConstraintGraph<'tcx, u32> = ConstraintGraph::new(tcx);
-    println!("{:?}", cg.vars);
-
-    let p =
-        RAP_interval::domain::ConstraintGraph::ConstraintGraph::<'tcx, u32>::create_random_place(
-            tcx,
-        );
-    println!("{:?}", p);
-
-    cg.build_graph(&body);
+    ssa.print_graphviz();
+    let function = RAP_interval::ir::lowering::lower(tcx, &ssa);
+    print!("{}", function);
+    ssa.print_out_of_ssa_mir();
+    let mut cg: ConstraintGraph<StridedRange<i128>> = ConstraintGraph::new();
+    cg.set_overflow_checks(tcx.sess.overflow_checks());
+    cg.build_graph(&function);
+    cg.find_intervals();
 
     println!("{:?}", cg.vars);
     println!("{:?}", cg.values_branchmap);
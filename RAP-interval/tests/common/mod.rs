@@ -0,0 +1,178 @@
+//! Helpers shared by the domain tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use num_traits::{NumCast, PrimInt};
+use std::fmt::Debug;
+use RAP_interval::domain::range::{Range, RangeType};
+
+/// Every regular range whose bounds are taken from `bounds`.
+pub fn ranges<T: PrimInt>(bounds: &[T]) -> Vec<Range<T>> {
+    let mut ranges = Vec::new();
+    for &lb in bounds {
+        for &ub in bounds.iter().filter(|&&ub| ub >= lb) {
+            ranges.push(Range::new(lb, ub, RangeType::Regular));
+        }
+    }
+    ranges
+}
+
+pub fn values<T: PrimInt>(range: &Range<T>) -> impl Iterator<Item = T> {
+    let (lb, ub) = (range.get_lower(), range.get_upper());
+    (lb.to_i128().unwrap()..=ub.to_i128().unwrap()).map(|v| <T as NumCast>::from(v).unwrap())
+}
+
+pub fn contains<T: PrimInt>(range: &Range<T>, v: T) -> bool {
+    range.is_regular() && range.get_lower() <= v && v <= range.get_upper()
+}
+
+/// How closely an abstract result has to match the concrete ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// Every concrete result that does not panic lies in the abstract result.
+    Sound,
+    /// Sound, and the abstract result is the hull of the concrete results that do not
+    /// panic, or empty if they all do.
+    Exact,
+    /// Sound, and exact whenever no combination of operands panics; used for arithmetic
+    /// that saturates on overflow.
+    ExactWithoutPanics,
+}
+
+/// Checks `abstract_op` against `concrete_op` on all ranges over `lhs` and `rhs`.
+/// `concrete_op` returns `None` where the concrete operation panics.
+pub fn check_binary<T: PrimInt + Debug>(
+    lhs: &[T],
+    rhs: &[T],
+    abstract_op: impl Fn(&Range<T>, &Range<T>) -> Range<T>,
+    concrete_op: impl Fn(T, T) -> Option<T>,
+    precision: Precision,
+) {
+    for x in ranges(lhs) {
+        for y in ranges(rhs) {
+            let result = abstract_op(&x, &y);
+            let mut hull: Option<(T, T)> = None;
+            let mut panics = false;
+            for a in values(&x) {
+                for b in values(&y) {
+                    match concrete_op(a, b) {
+                        Some(v) => {
+                            assert!(
+                                contains(&result, v),
+                                "{:?} {:?} -> {:?} misses {:?}",
+                                x,
+                                y,
+                                result,
+                                v
+                            );
+                            hull = Some(hull.map_or((v, v), |(lb, ub)| (lb.min(v), ub.max(v))));
+                        }
+                        None => panics = true,
+                    }
+                }
+            }
+            let exact = match precision {
+                Precision::Sound => false,
+                Precision::Exact => true,
+                Precision::ExactWithoutPanics => !panics,
+            };
+            if exact {
+                match hull {
+                    Some(hull) => assert_eq!(
+                        (result.get_lower(), result.get_upper()),
+                        hull,
+                        "{:?} {:?}",
+                        x,
+                        y
+                    ),
+                    None => assert!(result.is_empty(), "{:?} {:?}", x, y),
+                }
+            }
+        }
+    }
+}
+
+/// A sample of an abstract domain: some of its elements, each with the sampled concrete
+/// values it stands for.
+pub struct Universe<A, V> {
+    samples: Vec<(A, Vec<V>)>,
+    contains: fn(&A, V) -> bool,
+}
+
+impl<A: Debug, V: Copy + Debug> Universe<A, V> {
+    pub fn new(
+        elements: Vec<A>,
+        members: impl Fn(&A) -> Vec<V>,
+        contains: fn(&A, V) -> bool,
+    ) -> Self {
+        let samples = elements
+            .into_iter()
+            .map(|element| {
+                let values = members(&element);
+                (element, values)
+            })
+            .collect();
+        Universe { samples, contains }
+    }
+
+    pub fn samples(&self) -> &[(A, Vec<V>)] {
+        &self.samples
+    }
+
+    /// Checks that `abstract_op` contains `concrete_op` of every member; members for which
+    /// the concrete operation is undefined are skipped.
+    pub fn check_unary(
+        &self,
+        name: &str,
+        abstract_op: impl Fn(&A) -> A,
+        concrete_op: impl Fn(V) -> Option<V>,
+    ) {
+        for (s, xs) in &self.samples {
+            let result = abstract_op(s);
+            for &x in xs {
+                if let Some(z) = concrete_op(x) {
+                    assert!(
+                        (self.contains)(&result, z),
+                        "{}: {:?} -> {:?} misses {:?} ({:?})",
+                        name,
+                        s,
+                        result,
+                        z,
+                        x
+                    );
+                }
+            }
+        }
+    }
+
+    /// Checks that `abstract_op` contains `concrete_op` for every pair of members; pairs
+    /// for which the concrete operation is undefined are skipped.
+    pub fn check_binary(
+        &self,
+        name: &str,
+        abstract_op: impl Fn(&A, &A) -> A,
+        concrete_op: impl Fn(V, V) -> Option<V>,
+    ) {
+        for (s, xs) in &self.samples {
+            for (t, ys) in &self.samples {
+                let result = abstract_op(s, t);
+                for &x in xs {
+                    for &y in ys {
+                        if let Some(z) = concrete_op(x, y) {
+                            assert!(
+                                (self.contains)(&result, z),
+                                "{}: {:?} {:?} -> {:?} misses {:?} ({:?} {:?})",
+                                name,
+                                s,
+                                t,
+                                result,
+                                z,
+                                x,
+                                y
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! 编译一小段源码并取出其中的函数，供 SSA 相关的测试共用。每个测试 crate 只用到其中一部分。
+#![allow(dead_code)]
+
+use rustc_driver::{Callbacks, Compilation, RunCompiler};
+use rustc_hir::def_id::LocalDefId;
+use rustc_interface::{interface::Compiler, Queries};
+use rustc_middle::mir::*;
+use rustc_middle::ty::TyCtxt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use RAP_interval::SSA::SSATransformer::{SSAOptions, SSATransformer};
+
+/// 最简单的循环：循环头合并入口和回边上的 `i`
+pub const COUNT: &str = r#"
+pub fn count(n: u32) -> u32 {
+    let mut i = 0;
+    while i < n {
+        i += 1;
+    }
+    i
+}
+"#;
+
+struct FunctionCallbacks<F> {
+    name: &'static str,
+    f: Option<F>,
+}
+
+impl<F> Callbacks for FunctionCallbacks<F>
+where
+    F: for<'tcx> FnOnce(TyCtxt<'tcx>, LocalDefId) + Send,
+{
+    fn after_analysis<'tcx>(
+        &mut self,
+        _compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        let f = self.f.take().unwrap();
+        let name = self.name;
+        let mut tcx = queries.global_ctxt().unwrap();
+        tcx.enter(|tcx| {
+            let def_id = tcx
+                .hir()
+                .body_owners()
+                .find(|id| tcx.def_path_str(*id) == name)
+                .unwrap();
+            f(tcx, def_id);
+        });
+        Compilation::Stop
+    }
+}
+
+/// 以调试模式、不做 MIR 优化编译 `source`，对其中名为 `name` 的函数调用 `f`
+pub fn with_function<F>(source: &str, name: &'static str, f: F)
+where
+    F: for<'tcx> FnOnce(TyCtxt<'tcx>, LocalDefId) + Send,
+{
+    // 编译器会话不能在同一进程中并行运行
+    static LOCK: Mutex<()> = Mutex::new(());
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let _guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let path = std::env::temp_dir().join(format!(
+        "ssa_test_{}_{}_{}.rs",
+        std::process::id(),
+        name,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, source).unwrap();
+    let args: Vec<String> = [
+        "rustc",
+        path.to_str().unwrap(),
+        "--crate-type=lib",
+        "--edition=2021",
+        "-Zmir-opt-level=0",
+        "-Coverflow-checks=on",
+        "-Awarnings",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let mut callbacks = FunctionCallbacks { name, f: Some(f) };
+    RunCompiler::new(&args, &mut callbacks).run().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// 源码变量 `name` 在原始 body 中对应的变量
+pub fn local_named(body: &Body<'_>, name: &str) -> Local {
+    body.var_debug_info
+        .iter()
+        .find_map(|info| match info.value {
+            VarDebugInfoContents::Place(place) if info.name.as_str() == name => place.as_local(),
+            _ => None,
+        })
+        .unwrap()
+}
+
+/// `dest = copy src` 形式的拷贝
+pub fn copy_of(statement: &Statement<'_>) -> Option<(Local, Local)> {
+    match &statement.kind {
+        StatementKind::Assign(box (dest, Rvalue::Use(Operand::Copy(src) | Operand::Move(src)))) => {
+            Some((dest.as_local()?, src.as_local()?))
+        }
+        _ => None,
+    }
+}
+
+/// 依次插入 sigma、phi 并重命名
+pub fn transform<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    def_id: LocalDefId,
+    options: SSAOptions,
+) -> SSATransformer<'tcx> {
+    let mut ssa = SSATransformer::new(tcx, def_id, options);
+    ssa.insert_sigma_statment();
+    ssa.insert_phi_statment();
+    ssa.rename_variables();
+    ssa
+}
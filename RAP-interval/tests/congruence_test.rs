@@ -0,0 +1,168 @@
+mod common;
+
+mod tests {
+    use crate::common::Universe;
+    use RAP_interval::domain::abstract_domain::AbstractDomain;
+    use RAP_interval::domain::congruence::{Congruence, StridedRange};
+    use RAP_interval::domain::range::{IntType, Range, RangeType};
+
+    const VALUES: std::ops::RangeInclusive<i128> = -24..=24;
+
+    /// Bottom, constants and the classes of small moduli.
+    fn congruences() -> Universe<Congruence, i128> {
+        let mut congruences = vec![Congruence::bottom()];
+        for c in [-7, -1, 0, 1, 2, 6] {
+            congruences.push(Congruence::constant(c));
+        }
+        for modulus in 1..=8 {
+            for residue in 0..modulus {
+                congruences.push(Congruence::new(modulus, residue as i128));
+            }
+        }
+        Universe::new(
+            congruences,
+            |c| VALUES.filter(|&x| c.contains(x)).collect(),
+            Congruence::contains,
+        )
+    }
+
+    #[test]
+    fn test_arithmetic_is_sound() {
+        let congruences = congruences();
+        congruences.check_binary("add", Congruence::add, |x, y| Some(x + y));
+        congruences.check_binary("sub", Congruence::sub, |x, y| Some(x - y));
+        congruences.check_binary("mul", Congruence::mul, |x, y| Some(x * y));
+        congruences.check_binary("div", Congruence::div, |x, y| x.checked_div(y));
+        congruences.check_binary("rem", Congruence::rem, |x, y| x.checked_rem(y));
+        congruences.check_binary("and", Congruence::bitand, |x, y| Some(x & y));
+        congruences.check_binary("or", Congruence::bitor, |x, y| Some(x | y));
+        congruences.check_binary("xor", Congruence::bitxor, |x, y| Some(x ^ y));
+        congruences.check_binary(
+            "shl",
+            |x, y| x.shl(y, 8, true),
+            |x, y| (0..8).contains(&y).then(|| x << y),
+        );
+        congruences.check_unary("neg", Congruence::neg, |x| Some(-x));
+        // 2k + 1 is odd and 4k + 4k is a multiple of 4
+        let k = Congruence::top();
+        let odd = Congruence::constant(2)
+            .mul(&k)
+            .add(&Congruence::constant(1));
+        assert_eq!(odd, Congruence::new(2, 1));
+        let four = Congruence::new(4, 0);
+        assert_eq!(four.add(&four), four);
+        assert_eq!(four.mul(&Congruence::new(6, 3)), Congruence::new(12, 0));
+    }
+
+    #[test]
+    fn test_lattice() {
+        let congruences = congruences();
+        for (s, _) in congruences.samples() {
+            for (t, _) in congruences.samples() {
+                let (join, meet) = (s.join(t), s.meet(t));
+                assert!(s.leq(&join) && t.leq(&join));
+                assert_eq!(join, t.join(s));
+                for x in VALUES {
+                    // The meet is exact
+                    assert_eq!(meet.contains(x), s.contains(x) && t.contains(x));
+                    if s.leq(t) && s.contains(x) {
+                        assert!(t.contains(x));
+                    }
+                }
+            }
+        }
+        assert_eq!(
+            Congruence::new(4, 1).meet(&Congruence::new(6, 3)),
+            Congruence::new(12, 9)
+        );
+        assert!(Congruence::new(4, 1)
+            .meet(&Congruence::new(6, 0))
+            .is_bottom());
+        assert_eq!(
+            Congruence::constant(0).join(&Congruence::constant(12)),
+            Congruence::new(12, 0)
+        );
+    }
+
+    #[test]
+    fn test_fit_wraps_around() {
+        let u8 = IntType::new(8, false);
+        let i8 = IntType::new(8, true);
+        assert_eq!(
+            Congruence::constant(300).fit(u8, false),
+            Congruence::constant(44)
+        );
+        assert_eq!(
+            Congruence::constant(200).fit(i8, false),
+            Congruence::constant(-56)
+        );
+        // Multiples of 3 wrap around to anything, multiples of 4 stay multiples of 4
+        assert!(Congruence::new(3, 0).fit(u8, false).is_top());
+        assert_eq!(Congruence::new(4, 0).fit(u8, false), Congruence::new(4, 0));
+        assert_eq!(Congruence::new(3, 0).fit(u8, true), Congruence::new(3, 0));
+        assert_eq!(Congruence::new(3, 1).cast(u8, i8), Congruence::top());
+        assert_eq!(
+            Congruence::new(3, 1).cast(u8, IntType::new(16, true)),
+            Congruence::new(3, 1)
+        );
+        assert_eq!(
+            Congruence::constant(-1).cast(i8, IntType::new(16, false)),
+            Congruence::constant(65535)
+        );
+        assert_eq!(
+            Congruence::constant(5).not_in(u8),
+            Congruence::constant(250)
+        );
+    }
+
+    #[test]
+    fn test_reduced_product() {
+        let range = |lower, upper| Range::new(lower, upper, RangeType::Regular);
+        let bounds = |s: &StridedRange<i32>| (s.range.get_lower(), s.range.get_upper());
+
+        // [1, 99] of multiples of 4 is [4, 96]
+        let s = StridedRange::new(range(1, 99), Congruence::new(4, 0));
+        assert_eq!(bounds(&s), (4, 96));
+        let s = StridedRange::new(range(-9, 9), Congruence::new(4, 3));
+        assert_eq!(bounds(&s), (-9, 7));
+        // A single member makes the congruence a constant
+        let s = StridedRange::new(range(5, 10), Congruence::new(8, 7));
+        assert_eq!(bounds(&s), (7, 7));
+        assert_eq!(s.congruence, Congruence::constant(7));
+        let s = StridedRange::new(range(3, 3), Congruence::top());
+        assert_eq!(s.congruence, Congruence::constant(3));
+        // No member at all
+        let s = StridedRange::new(range(5, 6), Congruence::new(8, 0));
+        assert!(s.range.is_empty() && s.congruence.is_bottom());
+        let s = StridedRange::new(range(i32::MIN, i32::MAX), Congruence::new(4, 1));
+        assert_eq!(bounds(&s), (i32::MIN + 1, i32::MAX - 2));
+
+        // x in [0, 96] step 4, plus 3, stays below 100
+        let x = StridedRange::new(range(0, 98), Congruence::new(4, 0));
+        let three = StridedRange::new(range(3, 3), Congruence::constant(3));
+        let y = x.add(&three);
+        assert_eq!(bounds(&y), (3, 99));
+        assert_eq!(y.congruence, Congruence::new(4, 3));
+        let z = x.mul(&x.join(&three));
+        assert_eq!(z.congruence, Congruence::new(4, 0));
+
+        // Reduction keeps every value in both
+        for s in [
+            Congruence::new(3, 2),
+            Congruence::new(5, 0),
+            Congruence::constant(4),
+        ] {
+            for lower in -10..=10 {
+                for upper in lower..=10 {
+                    let reduced = StridedRange::new(range(lower, upper), s);
+                    for x in lower..=upper {
+                        if s.contains(x as i128) {
+                            assert!(reduced.range.get_lower() <= x);
+                            assert!(x <= reduced.range.get_upper());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,449 @@
+mod tests {
+    use RAP_interval::domain::congruence::{Congruence, StridedRange};
+    use RAP_interval::domain::domain::{BasicIntervalTrait, IntervalType, Operation};
+    use RAP_interval::domain::range::{Range, RangeType};
+    use RAP_interval::domain::sign::Sign;
+    use RAP_interval::domain::ConstraintGraph::ConstraintGraph;
+    use RAP_interval::ir::ir::{BlockId, ValueId};
+    use RAP_interval::ir::text::parse;
+
+    fn build(text: &str) -> ConstraintGraph<Range<u32>> {
+        let function = parse(text).unwrap();
+        let mut cg: ConstraintGraph<Range<u32>> = ConstraintGraph::new();
+        cg.build_graph(&function);
+        cg
+    }
+
+    fn basic_range(itv: &IntervalType<Range<u32>>) -> &Range<u32> {
+        match itv {
+            IntervalType::Basic(basic) => basic.get_range(),
+            IntervalType::Symb(_) => panic!("expected a basic interval"),
+        }
+    }
+
+    #[test]
+    fn test_branch_on_constant() {
+        let cg = build(include_str!("ssa/branch.ssa"));
+        let vbm = &cg.values_branchmap[&ValueId(0)];
+        assert_eq!(vbm.get_bb_true(), BlockId(1));
+        assert_eq!(vbm.get_bb_false(), BlockId(2));
+
+        // x < 10
+        let itv_t = basic_range(vbm.get_itv_t());
+        assert!(itv_t.is_regular());
+        assert_eq!((itv_t.get_lower(), itv_t.get_upper()), (0, 9));
+        let itv_f = basic_range(vbm.get_itv_f());
+        assert!(itv_f.is_regular());
+        assert_eq!((itv_f.get_lower(), itv_f.get_upper()), (10, u32::MAX));
+    }
+
+    #[test]
+    fn test_sigma_operations() {
+        let cg = build(include_str!("ssa/branch.ssa"));
+        // The sigmas %2 and %3 are constrained by the two sides of the branch
+        let sigma_t = &cg.oprs[cg.defmap[&ValueId(2)]];
+        assert_eq!(sigma_t.get_sink(), ValueId(2));
+        assert_eq!(sigma_t.get_instruction(), (BlockId(1), 0));
+        assert_eq!(sigma_t.intersect.get_range().get_upper(), 9);
+        let sigma_f = &cg.oprs[cg.defmap[&ValueId(3)]];
+        assert_eq!(sigma_f.intersect.get_range().get_lower(), 10);
+
+        assert!(cg.usemap[&ValueId(0)].contains(&cg.defmap[&ValueId(2)]));
+        assert!(cg.usemap[&ValueId(0)].contains(&cg.defmap[&ValueId(3)]));
+        // The comparison is not modelled and may give either boolean
+        let compare = cg.oprs[cg.defmap[&ValueId(1)]].eval(&cg.vars);
+        assert_eq!((compare.get_lower(), compare.get_upper()), (0, 1));
+    }
+
+    #[test]
+    fn test_branch_on_copied_operand() {
+        // MIR compares a temporary copy, `_3 = copy _1; _2 = Lt(move _3, 10)`,
+        // while the sigmas rename the source
+        let mut cg = build(
+            "fn f(%0 \"x\": u32) {\n\
+             bb0:\n\
+             \x20   %1: u32 = copy %0\n\
+             \x20   %2: bool = lt %1, 10\n\
+             \x20   br %2, bb1, bb2\n\
+             bb1:\n\
+             \x20   %3 \"x#1\": u32 = sigma %0\n\
+             \x20   return\n\
+             bb2:\n\
+             \x20   %4 \"x#2\": u32 = sigma %0\n\
+             \x20   return\n\
+             }\n",
+        );
+        let sigma_t = &cg.oprs[cg.defmap[&ValueId(3)]];
+        assert_eq!(sigma_t.intersect.get_range().get_upper(), 9);
+        let sigma_f = &cg.oprs[cg.defmap[&ValueId(4)]];
+        assert_eq!(sigma_f.intersect.get_range().get_lower(), 10);
+
+        cg.find_intervals();
+        let range = cg.vars[&ValueId(3)].get_range();
+        assert_eq!((range.get_lower(), range.get_upper()), (0, 9));
+        let range = cg.vars[&ValueId(4)].get_range();
+        assert_eq!((range.get_lower(), range.get_upper()), (10, u32::MAX));
+    }
+
+    #[test]
+    fn test_constant_on_the_left() {
+        let cg = build(
+            "fn f(%0: u8) {\n\
+             bb0:\n\
+             \x20   %1: bool = ge 200, %0\n\
+             \x20   br %1, bb1, bb2\n\
+             bb1:\n\
+             \x20   return\n\
+             bb2:\n\
+             \x20   return\n\
+             }\n",
+        );
+        // 200 >= x, i.e. x <= 200
+        let vbm = &cg.values_branchmap[&ValueId(0)];
+        let itv_t = basic_range(vbm.get_itv_t());
+        assert_eq!((itv_t.get_lower(), itv_t.get_upper()), (0, 200));
+        let itv_f = basic_range(vbm.get_itv_f());
+        assert_eq!((itv_f.get_lower(), itv_f.get_upper()), (201, u32::MAX));
+    }
+
+    #[test]
+    fn test_out_of_range_constant() {
+        let cg = build(
+            "fn f(%0: i32) {\n\
+             bb0:\n\
+             \x20   %1: bool = lt %0, -5\n\
+             \x20   br %1, bb1, bb2\n\
+             bb1:\n\
+             \x20   return\n\
+             bb2:\n\
+             \x20   return\n\
+             }\n",
+        );
+        // No u32 is below -5
+        let vbm = &cg.values_branchmap[&ValueId(0)];
+        assert!(basic_range(vbm.get_itv_t()).is_empty());
+        let itv_f = basic_range(vbm.get_itv_f());
+        assert_eq!((itv_f.get_lower(), itv_f.get_upper()), (0, u32::MAX));
+    }
+
+    #[test]
+    fn test_branch_between_variables() {
+        let cg = build(include_str!("ssa/loop.ssa"));
+        // i#2 < n constrains both operands symbolically
+        for (value, bound) in [(ValueId(2), ValueId(0)), (ValueId(0), ValueId(2))] {
+            match cg.values_branchmap[&value].get_itv_t() {
+                IntervalType::Symb(symb) => assert_eq!(symb.get_bound(), bound),
+                IntervalType::Basic(_) => panic!("expected a symbolic interval"),
+            }
+        }
+        let sigma = cg.defmap[&ValueId(4)];
+        assert!(cg.symbmap[&ValueId(0)].contains(&sigma));
+        assert_eq!(cg.oprs.len(), 7);
+    }
+
+    #[test]
+    fn test_eval_arithmetic() {
+        let mut cg = build(
+            "fn f(%0: u32, %1: u32) {\n\
+             bb0:\n\
+             \x20   %2: u32 = add %0, 5\n\
+             \x20   %3: u32 = div %2, %1\n\
+             \x20   %4: u32 = rem %3, 4\n\
+             \x20   return\n\
+             }\n",
+        );
+        let set = |cg: &mut ConstraintGraph<Range<u32>>, v: u32, lb: u32, ub: u32| {
+            let node = cg.vars.get_mut(&ValueId(v)).unwrap();
+            node.set_range(Range::new(lb, ub, RangeType::Regular));
+        };
+        set(&mut cg, 0, 10, 20);
+        set(&mut cg, 1, 0, 3);
+        for (v, expected) in [(2, (15, 25)), (3, (5, 25)), (4, (0, 3))] {
+            let op = &cg.oprs[cg.defmap[&ValueId(v)]];
+            let range = op.eval(&cg.vars);
+            assert_eq!((range.get_lower(), range.get_upper()), expected);
+            set(&mut cg, v, expected.0, expected.1);
+        }
+    }
+
+    fn solve(text: &str) -> ConstraintGraph<Range<i32>> {
+        let function = parse(text).unwrap();
+        let mut cg: ConstraintGraph<Range<i32>> = ConstraintGraph::new();
+        cg.build_graph(&function);
+        cg.find_intervals();
+        cg
+    }
+
+    fn range_of(cg: &ConstraintGraph<Range<i32>>, v: u32) -> (i32, i32) {
+        let range = cg.vars[&ValueId(v)].get_range();
+        assert!(range.is_regular(), "%{}: {:?}", v, range);
+        (range.get_lower(), range.get_upper())
+    }
+
+    #[test]
+    fn test_find_intervals_nested_loop() {
+        let cg = solve(include_str!("ssa/nested_loop.ssa"));
+        let expected = [
+            (0, (0, 0)),      // k#1
+            (1, (0, 100)),    // k#2
+            (3, (0, 99)),     // k#3
+            (15, (1, 100)),   // k#4
+            (16, (100, 100)), // k#5
+            (6, (0, 99)),     // i#2
+            (7, (0, 99)),     // j#2
+            (9, (0, 98)),     // i#3
+            (10, (1, 99)),    // j#3
+            (11, (1, 99)),    // i#4
+            (12, (0, 98)),    // j#4
+            (13, (0, 99)),    // i#5
+        ];
+        for (v, range) in expected {
+            assert_eq!(range_of(&cg, v), range, "%{}", v);
+        }
+    }
+
+    #[test]
+    fn test_find_intervals_arguments_are_unbounded() {
+        let cg = solve(include_str!("ssa/loop.ssa"));
+        // `n` is an argument, so `i < n` only bounds `i` by `i32::MAX - 1`
+        assert_eq!(range_of(&cg, 0), (i32::MIN, i32::MAX));
+        assert_eq!(range_of(&cg, 2), (0, i32::MAX));
+        assert_eq!(range_of(&cg, 4), (0, i32::MAX - 1));
+        assert_eq!(range_of(&cg, 5), (1, i32::MAX));
+    }
+
+    #[test]
+    fn test_abstract_states() {
+        let cg = solve(include_str!("ssa/loop.ssa"));
+        let state = |v: u32| cg.vars[&ValueId(v)].get_abstract_state();
+        // Widening moved the upper bounds of the loop; the one of `i#4` was cropped
+        assert_eq!(state(2), Sign::Positive);
+        assert_eq!(state(4), Sign::Zero);
+        assert_eq!(state(5), Sign::Positive);
+        // Bounds of the type that no widening produced are not marked
+        assert_eq!(range_of(&cg, 0), (i32::MIN, i32::MAX));
+        assert_eq!(state(0), Sign::Zero);
+        assert_eq!(range_of(&cg, 6), (0, i32::MAX));
+        assert_eq!(state(6), Sign::Zero);
+        let cg = solve(include_str!("ssa/nested_loop.ssa"));
+        for v in [1, 6, 9] {
+            assert_eq!(
+                cg.vars[&ValueId(v)].get_abstract_state(),
+                Sign::Zero,
+                "%{}",
+                v
+            );
+        }
+        // Widening jumped to the threshold 99, which no iterate is tighter than
+        assert_eq!(range_of(&cg, 3), (0, 99));
+        assert_eq!(cg.vars[&ValueId(3)].get_abstract_state(), Sign::Positive);
+    }
+
+    #[test]
+    fn test_find_intervals_narrows_unwidened_ranges() {
+        // The loop is never entered, so its variables stay unknown while widening and get
+        // the full range; narrowing refines it with the branch.
+        let cg = solve(
+            "fn f() {\n\
+             bb0:\n\
+             \x20   return\n\
+             bb1:\n\
+             \x20   %0: i32 = phi [bb2: %3]\n\
+             \x20   %1: bool = lt %0, 10\n\
+             \x20   br %1, bb2, bb3\n\
+             bb2:\n\
+             \x20   %2: i32 = sigma %0\n\
+             \x20   %3: i32 = add.checked %2, 1\n\
+             \x20   goto bb1\n\
+             bb3:\n\
+             \x20   return\n\
+             }\n",
+        );
+        assert_eq!(range_of(&cg, 0), (i32::MIN + 1, 10));
+        assert_eq!(range_of(&cg, 2), (i32::MIN + 1, 9));
+        assert_eq!(range_of(&cg, 3), (i32::MIN + 1, 10));
+        for v in [0, 2, 3] {
+            assert_eq!(
+                cg.vars[&ValueId(v)].get_abstract_state(),
+                Sign::Zero,
+                "%{}",
+                v
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_intervals_unreachable_branch() {
+        let cg = solve(
+            "fn f() {\n\
+             bb0:\n\
+             \x20   %0: i32 = copy 5\n\
+             \x20   %1: bool = gt %0, 10\n\
+             \x20   br %1, bb1, bb2\n\
+             bb1:\n\
+             \x20   %2: i32 = sigma %0\n\
+             \x20   %3: i32 = mul %2, 2\n\
+             \x20   return\n\
+             bb2:\n\
+             \x20   %4: i32 = sigma %0\n\
+             \x20   return\n\
+             }\n",
+        );
+        assert!(cg.vars[&ValueId(2)].get_range().is_empty());
+        assert!(cg.vars[&ValueId(3)].get_range().is_empty());
+        assert_eq!(range_of(&cg, 4), (5, 5));
+    }
+
+    #[test]
+    fn test_find_intervals_fits_types() {
+        let function = parse(
+            "fn f(%0: u8, %1: i16) {\n\
+             bb0:\n\
+             \x20   %2: u8 = add.checked %0, 1\n\
+             \x20   %3: u8 = add %0, 1\n\
+             \x20   %4: u16 = cast %2\n\
+             \x20   %5: i8 = cast %3\n\
+             \x20   %6: u8 = shr %0, 9\n\
+             \x20   %7: u8 = not %6\n\
+             \x20   %8: bool = lt %0, 3\n\
+             \x20   %9: i16 = mul.checked %1, 4\n\
+             \x20   return\n\
+             }\n",
+        )
+        .unwrap();
+        let mut cg: ConstraintGraph<Range<i128>> = ConstraintGraph::new();
+        cg.build_graph(&function);
+        cg.find_intervals();
+        let range_of = |v: u32| {
+            let range = cg.vars[&ValueId(v)].get_range();
+            assert!(range.is_regular(), "%{}: {:?}", v, range);
+            (range.get_lower(), range.get_upper())
+        };
+        let expected = [
+            (0, (0, 255)),
+            (1, (-32768, 32767)),
+            // A checked addition never exceeds the type, a wrapping one may give anything
+            (2, (1, 255)),
+            (3, (0, 255)),
+            (4, (1, 255)),
+            (5, (-128, 127)),
+            // The amount is masked to 9 % 8 = 1
+            (6, (0, 127)),
+            (7, (128, 255)),
+            (8, (0, 1)),
+            // Plain ranges do not know that the product is a multiple of 4
+            (9, (-32768, 32767)),
+        ];
+        for (v, range) in expected {
+            assert_eq!(range_of(v), range, "%{}", v);
+        }
+
+        // With congruences the checked product stays a multiple of 4 below i16::MAX
+        let mut cg: ConstraintGraph<StridedRange<i128>> = ConstraintGraph::new();
+        cg.build_graph(&function);
+        cg.find_intervals();
+        let product = cg.vars[&ValueId(9)].get_range();
+        assert_eq!(
+            (product.range.get_lower(), product.range.get_upper()),
+            (-32768, 32764)
+        );
+        assert_eq!(product.congruence, Congruence::new(4, 0));
+    }
+
+    #[test]
+    fn test_find_intervals_wrapping() {
+        let text = "fn f(%0: u8) {\n\
+                    bb0:\n\
+                    \x20   %1: u8 = and %0, 3\n\
+                    \x20   %2: u8 = sub %1, 4\n\
+                    \x20   %3: u8 = add %2, 8\n\
+                    \x20   %4: u8 = add.checked %1, 4\n\
+                    \x20   %5: u8 = add.checked %0, 1\n\
+                    \x20   return\n\
+                    }\n";
+        for overflow_checks in [true, false] {
+            let mut cg: ConstraintGraph<Range<i128>> = ConstraintGraph::new();
+            cg.set_overflow_checks(overflow_checks);
+            cg.build_graph(&parse(text).unwrap());
+            cg.find_intervals();
+            let range_of = |v: u32| {
+                let range = cg.vars[&ValueId(v)].get_range();
+                (range.get_lower(), range.get_upper())
+            };
+            // 0..=3 minus 4 wraps around to 252..=255, and back to 4..=7
+            assert_eq!(range_of(2), (252, 255));
+            assert_eq!(range_of(3), (4, 7));
+            assert_eq!(range_of(4), (4, 7));
+            // Only an overflow check rules out that 255 + 1 wraps around to 0
+            let lower = if overflow_checks { 1 } else { 0 };
+            assert_eq!(range_of(5), (lower, 255));
+        }
+    }
+
+    #[test]
+    fn test_find_intervals_floats() {
+        let mut cg: ConstraintGraph<Range<i128>> = ConstraintGraph::new();
+        cg.build_graph(&parse(include_str!("ssa/float.ssa")).unwrap());
+        cg.find_intervals();
+        let range_of = |v: u32| {
+            let range = cg.vars[&ValueId(v)].get_range();
+            (range.get_lower(), range.get_upper())
+        };
+        // The largest f64 below 100.5
+        let below = f64::from_bits(100.5f64.to_bits() - 1);
+        assert_eq!(
+            cg.floats[&ValueId(2)].bounds,
+            Some((f64::NEG_INFINITY, below))
+        );
+        assert!(!cg.floats[&ValueId(2)].nan);
+        // 2x < 201 saturates to 0 below
+        assert_eq!(range_of(4), (0, 200));
+        // x < 100.5 is never >= 1e300
+        assert_eq!(range_of(5), (0, 0));
+        // -inf / NaN is NaN
+        assert!(cg.floats[&ValueId(8)].bounds.is_none());
+        assert!(cg.floats[&ValueId(8)].nan);
+        // NaN takes the false branch and converts to 0
+        assert!(cg.floats[&ValueId(9)].nan);
+        assert_eq!(range_of(10), (0, i32::MAX as i128));
+    }
+
+    #[test]
+    fn test_find_intervals_strided() {
+        let mut cg: ConstraintGraph<StridedRange<i128>> = ConstraintGraph::new();
+        cg.build_graph(&parse(include_str!("ssa/strided.ssa")).unwrap());
+        cg.find_intervals();
+        let value_of = |v: u32| cg.vars[&ValueId(v)].get_range();
+        let expected = [
+            (0, (0, 100), Congruence::new(4, 0)),       // i#1
+            (2, (0, 96), Congruence::new(4, 0)),        // i#2
+            (3, (4, 100), Congruence::new(4, 0)),       // i#3
+            (4, (3, 99), Congruence::new(4, 3)),        // i#2 + 3
+            (6, (1, 193), Congruence::new(8, 1)),       // 2 * i#2 + 1
+            (7, (1, 1), Congruence::constant(1)),       // odd & 1
+            (8, (100, 100), Congruence::constant(100)), // i#4
+        ];
+        for (v, range, congruence) in expected {
+            let value = value_of(v);
+            let bounds = (value.range.get_lower(), value.range.get_upper());
+            assert_eq!(bounds, range, "%{}", v);
+            assert_eq!(value.congruence, congruence, "%{}", v);
+        }
+    }
+
+    #[test]
+    fn test_find_intervals_congruences_alone() {
+        let mut cg: ConstraintGraph<Congruence> = ConstraintGraph::new();
+        cg.build_graph(&parse(include_str!("ssa/strided.ssa")).unwrap());
+        cg.find_intervals();
+        let expected = [
+            (0, Congruence::new(4, 0)),
+            (4, Congruence::new(4, 3)),
+            (6, Congruence::new(8, 1)),
+            (8, Congruence::new(4, 0)),
+        ];
+        for (v, congruence) in expected {
+            assert_eq!(cg.vars[&ValueId(v)].get_range(), &congruence, "%{}", v);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+#![feature(box_patterns)]
+#![feature(rustc_private)]
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+
+mod compiler;
+
+mod tests {
+    use crate::compiler::{local_named, transform, with_function};
+    use rustc_middle::mir::*;
+    use RAP_interval::SSA::SSATransformer::{SSAOptions, SSATransformer, SSAVersion};
+
+    #[test]
+    fn test_field_splitting() {
+        let source = r#"
+            pub fn fields(a: i32, b: i32) -> i32 {
+                let t = (a, b);
+                t.0 + t.1
+            }
+        "#;
+        with_function(source, "fields", |tcx, def_id| {
+            let t = local_named(tcx.optimized_mir(def_id), "t");
+            let unsplit = SSATransformer::new(tcx, def_id, SSAOptions::default());
+            assert!(unsplit.field_locals.is_empty());
+
+            let options = SSAOptions {
+                field_sensitive: true,
+                ..SSAOptions::default()
+            };
+            let ssa = SSATransformer::new(tcx, def_id, options);
+            // t 和加法的溢出检查元组都被拆成两个字段变量
+            let mut fields_of_t: Vec<(usize, Local)> = ssa
+                .field_locals
+                .iter()
+                .filter(|(_, (parent, _))| *parent == t)
+                .map(|(&field_local, (_, field))| (field.as_usize(), field_local))
+                .collect();
+            fields_of_t.sort();
+            assert_eq!(fields_of_t.len(), 2);
+            assert_eq!(ssa.field_locals.len(), 4);
+
+            // 字段只在整体赋值之后被读取一次，其余的字段访问都换成了字段变量
+            let body = ssa.body.borrow();
+            let mut extracts = 0;
+            for data in body.basic_blocks.iter() {
+                for statement in &data.statements {
+                    let StatementKind::Assign(box (lhs, Rvalue::Use(Operand::Copy(src)))) =
+                        &statement.kind
+                    else {
+                        continue;
+                    };
+                    let Some(&(parent, expected)) =
+                        lhs.as_local().and_then(|lhs| ssa.field_locals.get(&lhs))
+                    else {
+                        continue;
+                    };
+                    assert_eq!(src.projection.as_slice().len(), 1);
+                    assert!(matches!(
+                        src.projection[0],
+                        ProjectionElem::Field(field, _) if (src.local, field) == (parent, expected)
+                    ));
+                    extracts += 1;
+                }
+                // 溢出标志以独立的变量出现在 Assert 中
+                if let TerminatorKind::Assert { cond, .. } = &data.terminator().kind {
+                    let cond = cond.place().unwrap();
+                    assert!(cond.projection.is_empty());
+                    assert!(ssa.field_locals.contains_key(&cond.local));
+                }
+            }
+            assert_eq!(extracts, ssa.field_locals.len());
+            drop(body);
+
+            // 字段变量以 `t.0`、`t.1` 的名字参与重命名
+            let ssa = transform(tcx, def_id, options);
+            assert!(ssa.verify().is_empty());
+            for (field, field_local) in fields_of_t {
+                let names: Vec<String> = ssa
+                    .versions
+                    .values()
+                    .filter(|version| version.local == field_local && version.version > 0)
+                    .map(SSAVersion::short_name)
+                    .collect();
+                assert_eq!(names, vec![format!("t.{}#1", field)]);
+            }
+        });
+    }
+}
@@ -0,0 +1,184 @@
+mod common;
+
+mod tests {
+    use crate::common::Universe;
+    use RAP_interval::domain::float::FloatRange;
+    use RAP_interval::domain::range::{IntType, Range, RangeType};
+    use RAP_interval::ir::ir::CmpOp;
+
+    const SAMPLES: [f64; 14] = [
+        f64::NEG_INFINITY,
+        -1e300,
+        -3.5,
+        -1.0,
+        -0.0,
+        0.0,
+        1e-310,
+        0.1,
+        1.0,
+        2.5,
+        16777217.0,
+        1e300,
+        f64::INFINITY,
+        f64::NAN,
+    ];
+
+    /// The samples as values of `bits` bits.
+    fn samples(bits: u16) -> Vec<f64> {
+        SAMPLES
+            .iter()
+            .map(|&x| if bits == 32 { x as f32 as f64 } else { x })
+            .collect()
+    }
+
+    /// Ranges between samples, with and without NaN, with the samples in each range and its
+    /// bounds.
+    fn ranges(bits: u16) -> Universe<FloatRange, f64> {
+        let samples = samples(bits);
+        let mut ranges = vec![
+            FloatRange::bottom(bits),
+            FloatRange::constant(bits, f64::NAN),
+        ];
+        for &lo in &samples {
+            for &hi in samples.iter().filter(|&&hi| lo <= hi) {
+                ranges.push(FloatRange::new(bits, lo, hi, false));
+                ranges.push(FloatRange::new(bits, lo, hi, true));
+            }
+        }
+        Universe::new(
+            ranges,
+            |range| {
+                let mut members: Vec<f64> = samples
+                    .iter()
+                    .copied()
+                    .filter(|&x| range.contains(x))
+                    .collect();
+                if let Some((lo, hi)) = range.bounds {
+                    members.extend([lo, hi]);
+                }
+                members
+            },
+            FloatRange::contains,
+        )
+    }
+
+    /// Checks `abstract_op` at both widths, evaluating the concrete operation at the width.
+    fn check_binary(
+        name: &str,
+        abstract_op: impl Fn(&FloatRange, &FloatRange) -> FloatRange,
+        op32: impl Fn(f32, f32) -> f32,
+        op64: impl Fn(f64, f64) -> f64,
+    ) {
+        ranges(32).check_binary(name, &abstract_op, |x, y| {
+            Some(op32(x as f32, y as f32) as f64)
+        });
+        ranges(64).check_binary(name, &abstract_op, |x, y| Some(op64(x, y)));
+    }
+
+    #[test]
+    fn test_arithmetic_is_sound() {
+        check_binary("add", FloatRange::add, |x, y| x + y, |x, y| x + y);
+        check_binary("sub", FloatRange::sub, |x, y| x - y, |x, y| x - y);
+        check_binary("mul", FloatRange::mul, |x, y| x * y, |x, y| x * y);
+        check_binary("div", FloatRange::div, |x, y| x / y, |x, y| x / y);
+        check_binary("rem", FloatRange::rem, |x, y| x % y, |x, y| x % y);
+        check_binary("join", FloatRange::join, |x, _| x, |x, _| x);
+        let ranges = ranges(64);
+        ranges.check_unary("neg", FloatRange::neg, |x| Some(-x));
+        ranges.check_unary("cast", |s| s.cast(32), |x| Some(x as f32 as f64));
+    }
+
+    #[test]
+    fn test_comparisons_are_sound() {
+        let ops = [
+            CmpOp::Eq,
+            CmpOp::Ne,
+            CmpOp::Lt,
+            CmpOp::Le,
+            CmpOp::Gt,
+            CmpOp::Ge,
+        ];
+        let compare = |op: CmpOp, x: f64, y: f64| match op {
+            CmpOp::Eq => x == y,
+            CmpOp::Ne => x != y,
+            CmpOp::Lt => x < y,
+            CmpOp::Le => x <= y,
+            CmpOp::Gt => x > y,
+            CmpOp::Ge => x >= y,
+        };
+        for bits in [32, 64] {
+            let ranges = ranges(bits);
+            for (s, xs) in ranges.samples() {
+                for (t, ys) in ranges.samples() {
+                    for op in ops {
+                        let (may_true, may_false) = s.compare(op, t);
+                        let (restrict_true, restrict_false) =
+                            (s.restrict(op, t, true), s.restrict(op, t, false));
+                        for &x in xs {
+                            for &y in ys {
+                                let holds = compare(op, x, y);
+                                assert!(if holds { may_true } else { may_false });
+                                let restricted = if holds { restrict_true } else { restrict_false };
+                                assert!(
+                                    restricted.contains(x),
+                                    "{:?} {:?} {:?} {}: {} {}",
+                                    s,
+                                    op,
+                                    t,
+                                    holds,
+                                    x,
+                                    y
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // x < 1.0 leaves the f32 values below 1.0
+        let one = FloatRange::constant(32, 1.0);
+        let below = FloatRange::top(32).restrict(CmpOp::Lt, &one, true);
+        let largest_below = 1.0 - f32::EPSILON as f64 / 2.0;
+        assert_eq!(below.bounds, Some((f64::NEG_INFINITY, largest_below)));
+        assert!(!below.nan);
+        // !(x < 1.0) also holds for NaN
+        let above = FloatRange::top(32).restrict(CmpOp::Lt, &one, false);
+        assert_eq!(above.bounds, Some((1.0, f64::INFINITY)));
+        assert!(above.nan);
+    }
+
+    #[test]
+    fn test_casts_to_integers_saturate() {
+        let to_u8 = |lo, hi, nan| {
+            let range: Range<i128> =
+                FloatRange::new(64, lo, hi, nan).to_int_range(IntType::new(8, false));
+            (range.get_lower(), range.get_upper())
+        };
+        assert_eq!(to_u8(-5.5, 300.7, false), (0, 255));
+        assert_eq!(to_u8(1.9, 2.9, false), (1, 2));
+        // NaN converts to 0
+        assert_eq!(to_u8(10.0, 20.0, true), (0, 20));
+        assert_eq!(to_u8(f64::NEG_INFINITY, -1.0, false), (0, 0));
+        let range: Range<i128> =
+            FloatRange::new(64, -1e10, 1e10, false).to_int_range(IntType::new(32, true));
+        assert_eq!(
+            (range.get_lower(), range.get_upper()),
+            (i32::MIN as i128, i32::MAX as i128)
+        );
+        assert!(FloatRange::bottom(64)
+            .to_int_range::<i128>(IntType::new(8, false))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_from_integers() {
+        let ty = IntType::new(64, false);
+        let range = FloatRange::from_int_range::<i128>(&Range::of_type(ty), 32);
+        assert_eq!(range.bounds, Some((0.0, u64::MAX as f32 as f64)));
+        assert!(!range.nan);
+        // 2^24 + 1 is not an f32
+        let odd: Range<i128> = Range::new(16777217, 16777217, RangeType::Regular);
+        let range = FloatRange::from_int_range(&odd, 32);
+        assert_eq!(range.bounds, Some((16777216.0, 16777216.0)));
+    }
+}
@@ -0,0 +1,175 @@
+mod tests {
+    use RAP_interval::ir::ir::*;
+    use RAP_interval::ir::text::parse;
+
+    const FIXTURES: [(&str, &str); 4] = [
+        ("loop.ssa", include_str!("ssa/loop.ssa")),
+        ("branch.ssa", include_str!("ssa/branch.ssa")),
+        ("switch.ssa", include_str!("ssa/switch.ssa")),
+        ("float.ssa", include_str!("ssa/float.ssa")),
+    ];
+
+    #[test]
+    fn test_fixtures_round_trip() {
+        for (name, text) in FIXTURES {
+            let function = parse(text).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(function.to_string(), text, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_loop() {
+        let function = parse(FIXTURES[0].1).unwrap();
+        assert_eq!(function.name, "count");
+        assert_eq!(function.args, vec![ValueId(0)]);
+        assert_eq!(function.return_values, vec![ValueId(7)]);
+        assert_eq!(function.blocks.len(), 4);
+        assert_eq!(function.value(ValueId(2)).name.as_deref(), Some("i#2"));
+        assert_eq!(function.value(ValueId(3)).ty, ScalarTy::Bool);
+        assert_eq!(
+            function.def_of(ValueId(2)).unwrap().kind,
+            InstKind::Phi {
+                operands: vec![
+                    (BlockId(0), Operand::Value(ValueId(1))),
+                    (BlockId(2), Operand::Value(ValueId(5))),
+                ]
+            }
+        );
+        assert_eq!(
+            function.def_of(ValueId(5)).unwrap().kind,
+            InstKind::Binary {
+                op: BinOp::Add,
+                lhs: Operand::Value(ValueId(4)),
+                rhs: Operand::Const(1),
+                checked: true,
+            }
+        );
+        assert_eq!(
+            function.block(BlockId(1)).term,
+            Terminator::Branch {
+                cond: Operand::Value(ValueId(3)),
+                then_bb: BlockId(2),
+                else_bb: BlockId(3),
+            }
+        );
+        assert_eq!(
+            function.predecessors()[&BlockId(1)],
+            vec![BlockId(0), BlockId(2)]
+        );
+    }
+
+    #[test]
+    fn test_parse_switch() {
+        let function = parse(FIXTURES[2].1).unwrap();
+        assert_eq!(function.name, "<impl Foo for Bar>::pick");
+        assert!(function.def_of(ValueId(2)).is_none());
+        assert_eq!(
+            function.value(ValueId(2)).ty,
+            ScalarTy::Int {
+                bits: 64,
+                signed: true
+            }
+        );
+        assert_eq!(
+            function.def_of(ValueId(5)).unwrap().kind,
+            InstKind::Call {
+                callee: String::from("std::cmp::max"),
+                args: vec![Operand::Value(ValueId(2)), Operand::Const(-128)],
+            }
+        );
+        assert_eq!(
+            function.block(BlockId(0)).term,
+            Terminator::Switch {
+                discr: Operand::Value(ValueId(0)),
+                targets: vec![(-1, BlockId(1)), (0, BlockId(2)), (7, BlockId(2))],
+                otherwise: BlockId(3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let text = "// leading comment\n\
+                    fn f() {\n\
+                    \n\
+                    bb0: // entry\n\
+                    \x20   %0: u8 = copy 255 // max\n\
+                    \x20   return\n\
+                    }\n";
+        let function = parse(text).unwrap();
+        assert_eq!(
+            function.to_string(),
+            "fn f() {\nbb0:\n    %0: u8 = copy 255\n    return\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_floats() {
+        let function = parse(include_str!("ssa/float.ssa")).unwrap();
+        let operands: Vec<Operand> = function.blocks[1]
+            .insts
+            .iter()
+            .filter_map(|inst| match &inst.kind {
+                InstKind::Binary { rhs, .. } | InstKind::Compare { rhs, .. } => Some(*rhs),
+                _ => None,
+            })
+            .collect();
+        let floats: Vec<f64> = operands.iter().map(|op| op.float().unwrap()).collect();
+        assert_eq!(floats[..3], [2.0, 1e300, f64::NEG_INFINITY]);
+        assert!(floats[3].is_nan());
+        // An integer with an exponent is a float
+        let function = parse("fn f() {\nbb0:\n    %0: f32 = copy 1e3\n    return\n}\n").unwrap();
+        assert_eq!(
+            function.to_string(),
+            "fn f() {\nbb0:\n    %0: f32 = copy 1000.0\n    return\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            (
+                "fn f() {\nbb0:\n    %0: i32 = copy %1\n    return\n}\n",
+                5,
+                "undeclared %1",
+            ),
+            (
+                "fn f() {\nbb0:\n    %0: i32 = copy 1\n}\n",
+                4,
+                "without terminator",
+            ),
+            (
+                "fn f() {\nbb0:\n    %0: i32 = copy 1\n    %0: i32 = copy 2\n    return\n}\n",
+                4,
+                "declared twice",
+            ),
+            (
+                "fn f() {\nbb0:\n    %1: i32 = copy 1\n    return\n}\n",
+                5,
+                "%0 is not declared",
+            ),
+            ("fn f() {\nbb0:\n    goto bb1\n}\n", 4, "missing bb1"),
+            (
+                "fn f() {\nbb0:\n    %0: i32 = frob 1\n    return\n}\n",
+                3,
+                "unknown instruction",
+            ),
+            (
+                "fn f() {\nbb0:\n    %0: i33x = copy 1\n    return\n}\n",
+                3,
+                "unknown type",
+            ),
+            ("fn f() {\nbb0:\n    return\n", 3, "end of input"),
+            (
+                "fn f() {\nbb0:\n    %0: f64 = copy 1.2.3\n    return\n}\n",
+                3,
+                "invalid number",
+            ),
+        ];
+        for (text, line, message) in cases {
+            let error = parse(text).unwrap_err();
+            assert_eq!(error.line, line, "{}", text);
+            assert!(error.message.contains(message), "{}: {}", text, error);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+#![feature(box_patterns)]
+#![feature(rustc_private)]
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+
+mod compiler;
+
+mod tests {
+    use crate::compiler::{transform, with_function};
+    use RAP_interval::domain::congruence::{Congruence, StridedRange};
+    use RAP_interval::domain::range::Range;
+    use RAP_interval::domain::ConstraintGraph::ConstraintGraph;
+    use RAP_interval::ir::ir::{BinOp, Function, InstKind, Operand, ScalarTy, Terminator, ValueId};
+    use RAP_interval::ir::lowering::lower;
+    use RAP_interval::SSA::SSATransformer::SSAOptions;
+
+    /// 名为 `name` 的值
+    fn value_named(function: &Function, name: &str) -> ValueId {
+        let index = function
+            .values
+            .iter()
+            .position(|value| value.name.as_deref() == Some(name))
+            .unwrap();
+        ValueId(index as u32)
+    }
+
+    #[test]
+    fn test_lowering_reads_address_taken_local_through_clobbering_def() {
+        let source = r#"
+            pub fn clobber(p: bool) -> u32 {
+                let mut x = 7;
+                let r = &mut x;
+                if p {
+                    *r = 100;
+                }
+                let a = x;
+                x = 5;
+                let b = x;
+                a + b
+            }
+        "#;
+        with_function(source, "clobber", |tcx, def_id| {
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let function = lower(tcx, &ssa);
+            let copied =
+                |name: &str| match &function.def_of(value_named(&function, name)).unwrap().kind {
+                    InstKind::Assign(Operand::Value(value)) => *value,
+                    kind => panic!("unexpected definition of {}: {:?}", name, kind),
+                };
+            // `x = 5` 是整体覆盖 x 的 must-def，之后读到的就是它写入的值
+            let b = copied("b#1");
+            assert_eq!(
+                function.def_of(b).unwrap().kind,
+                InstKind::Assign(Operand::Const(5))
+            );
+            // 经由 `r` 的写入可能改写了 x，汇合处的 MemoryPhi 使读到的值未知
+            let a = copied("a#1");
+            assert!(function.def_of(a).is_none());
+        });
+    }
+
+    #[test]
+    fn test_sigma_refines_through_copied_compare_operand() {
+        // `_3 = copy _1; _2 = Lt(move _3, const 10_u32); switchInt(move _2)`
+        let source = r#"
+            pub fn clamp(x: u32) -> u32 {
+                if x < 10 { x } else { 9 }
+            }
+        "#;
+        with_function(source, "clamp", |tcx, def_id| {
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let function = lower(tcx, &ssa);
+            let mut cg: ConstraintGraph<Range<u32>> = ConstraintGraph::new();
+            cg.build_graph(&function);
+            cg.find_intervals();
+            let mut ranges: Vec<(u32, u32)> = function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.insts)
+                .filter(|inst| matches!(inst.kind, InstKind::Sigma { .. }))
+                .map(|inst| {
+                    let range = cg.vars[&inst.dest].get_range();
+                    (range.get_lower(), range.get_upper())
+                })
+                .collect();
+            ranges.sort();
+            assert_eq!(ranges, vec![(0, 9), (10, u32::MAX)]);
+        });
+    }
+
+    #[test]
+    fn test_u128_constants_above_i128_max_are_unknown() {
+        let source = r#"
+            pub fn wide(x: u128) -> u128 {
+                let m = u128::MAX;
+                match x {
+                    u128::MAX => 0,
+                    1 => 1,
+                    _ => m,
+                }
+            }
+        "#;
+        with_function(source, "wide", |tcx, def_id| {
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let function = lower(tcx, &ssa);
+            let unknown = |operand: &Operand| {
+                let value = operand.value().unwrap();
+                assert_eq!(
+                    function.value(value).ty,
+                    ScalarTy::Int {
+                        bits: 128,
+                        signed: false
+                    }
+                );
+                assert_eq!(function.def_of(value).unwrap().kind, InstKind::Opaque);
+            };
+            // `u128::MAX` 不会回绕成 -1，而是一个未知的 u128
+            let InstKind::Assign(operand) =
+                &function.def_of(value_named(&function, "m#1")).unwrap().kind
+            else {
+                panic!("m#1 is not a copy");
+            };
+            unknown(operand);
+            // 判别值里有超出 i128 的值时，switch 测试的是一个未知值而不是 x
+            let x = value_named(&function, "x#0");
+            let switches: Vec<&Terminator> = function
+                .blocks
+                .iter()
+                .map(|block| &block.term)
+                .filter(|term| matches!(term, Terminator::Switch { .. }))
+                .collect();
+            let [Terminator::Switch { discr, targets, .. }] = switches[..] else {
+                panic!("expected one switch: {:?}", switches);
+            };
+            assert_ne!(*discr, Operand::Value(x));
+            unknown(discr);
+            assert_eq!(targets.len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_bit_casts_are_opaque() {
+        // `f32::to_bits` / `f32::from_bits` 的实现，调试模式下不会被内联，这里直接写出
+        let source = r#"
+            pub fn round_trip(x: f32) -> f32 {
+                let n = x as u32;
+                let bits: u32 = unsafe { std::mem::transmute(x) };
+                let y: f32 = unsafe { std::mem::transmute(bits) };
+                y + n as f32
+            }
+        "#;
+        with_function(source, "round_trip", |tcx, def_id| {
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let function = lower(tcx, &ssa);
+            let kind = |name: &str| &function.def_of(value_named(&function, name)).unwrap().kind;
+            // 数值转换按值换算
+            assert!(matches!(kind("n#1"), InstKind::Cast { .. }));
+            // 按位重新解释的结果与原值的大小无关
+            assert_eq!(kind("bits#1"), &InstKind::Opaque);
+            assert_eq!(kind("y#1"), &InstKind::Opaque);
+        });
+    }
+
+    #[test]
+    fn test_strided_index_is_tightened() {
+        // 即 `for i in (0..98).step_by(4)`；调试模式下 `StepBy::next` 是不透明的调用，
+        // 这里把步长写成循环变量的递增
+        let source = r#"
+            pub fn strided(buf: &[u8; 100]) -> u32 {
+                let mut sum = 0;
+                let mut i = 0;
+                while i < 98 {
+                    sum += buf[i + 3] as u32;
+                    i += 4;
+                }
+                sum
+            }
+        "#;
+        with_function(source, "strided", |tcx, def_id| {
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let function = lower(tcx, &ssa);
+            let mut cg: ConstraintGraph<StridedRange<i128>> = ConstraintGraph::new();
+            cg.build_graph(&function);
+            cg.find_intervals();
+            let (i, index) = function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.insts)
+                .find_map(|inst| match inst.kind {
+                    InstKind::Binary {
+                        op: BinOp::Add,
+                        lhs: Operand::Value(i),
+                        rhs: Operand::Const(3),
+                        ..
+                    } => Some((i, inst.dest)),
+                    _ => None,
+                })
+                .unwrap();
+            let bounds = |value: ValueId| {
+                let range = &cg.vars[&value].get_range().range;
+                (range.get_lower(), range.get_upper())
+            };
+            // 区间本身只知道 i ∈ [0, 97]，同余 i ≡ 0 (mod 4) 把上界收紧到 96
+            assert_eq!(bounds(i), (0, 96));
+            assert_eq!(cg.vars[&i].get_range().congruence, Congruence::new(4, 0));
+            // 于是 i + 3 ∈ [3, 99]，对长度为 100 的数组的访问不会越界
+            assert_eq!(bounds(index), (3, 99));
+            assert_eq!(
+                cg.vars[&index].get_range().congruence,
+                Congruence::new(4, 3)
+            );
+        });
+    }
+}
@@ -0,0 +1,99 @@
+#![feature(box_patterns)]
+#![feature(rustc_private)]
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+
+mod compiler;
+
+mod tests {
+    use crate::compiler::{copy_of, local_named, transform, with_function};
+    use rustc_middle::mir::*;
+    use std::collections::HashSet;
+    use RAP_interval::SSA::MemorySSA::MemoryAccessKind;
+    use RAP_interval::SSA::SSATransformer::SSAOptions;
+
+    #[test]
+    fn test_memory_ssa_for_borrowed_local() {
+        let source = r#"
+            pub fn memory(p: bool) -> u32 {
+                let mut x = 7;
+                let r = &mut x;
+                if p {
+                    *r = 100;
+                }
+                let a = x;
+                x = 5;
+                let b = x;
+                a + b
+            }
+        "#;
+        with_function(source, "memory", |tcx, def_id| {
+            let body = tcx.optimized_mir(def_id);
+            let (x, r) = (local_named(body, "x"), local_named(body, "r"));
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let memory_ssa = ssa.memory_ssa.as_ref().unwrap();
+            assert!(memory_ssa.is_address_taken(x));
+            assert!(!memory_ssa.is_address_taken(r));
+            assert_eq!(memory_ssa.pointees(r), HashSet::from([x]));
+
+            // 两次直接赋值是 must-def，经由 r 的写入只是可能写入，汇合处有一个 MemoryPhi
+            let defs: Vec<bool> = memory_ssa
+                .accesses
+                .iter()
+                .filter_map(|access| match access {
+                    MemoryAccessKind::Def { clobbers, must, .. } if clobbers.contains(&x) => {
+                        Some(*must)
+                    }
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(defs.iter().filter(|&&must| must).count(), 2);
+            assert_eq!(defs.iter().filter(|&&must| !must).count(), 1);
+            let phis = memory_ssa
+                .accesses
+                .iter()
+                .filter(|access| matches!(access, MemoryAccessKind::Phi { .. }))
+                .count();
+            assert_eq!(phis, 1);
+
+            // x 不参与重命名：`a = x` 读到的是 MemoryPhi，`b = x` 读到的是 `x = 5`
+            let body = ssa.body.borrow();
+            let mut reads = Vec::new();
+            for (block, data) in body.basic_blocks.iter_enumerated() {
+                for (statement_index, statement) in data.statements.iter().enumerate() {
+                    if copy_of(statement).is_some_and(|(_, src)| src == x) {
+                        reads.push(Location {
+                            block,
+                            statement_index,
+                        });
+                    }
+                }
+            }
+            reads.sort();
+            assert_eq!(reads.len(), 2);
+            let clobbering = |location: Location| {
+                let id = memory_ssa.use_at(location).unwrap();
+                memory_ssa.access(memory_ssa.clobbering_access(id, x))
+            };
+            assert!(matches!(clobbering(reads[0]), MemoryAccessKind::Phi { .. }));
+            let MemoryAccessKind::Def {
+                location,
+                must: true,
+                ..
+            } = clobbering(reads[1])
+            else {
+                panic!("`b = x` does not read a must-def");
+            };
+            // 即两次读取之间的 `x = 5`
+            assert!(reads[0] < *location && *location < reads[1]);
+            let StatementKind::Assign(box (place, _)) =
+                &body.basic_blocks[location.block].statements[location.statement_index].kind
+            else {
+                panic!("{:?} is not an assignment", location);
+            };
+            assert_eq!(place.as_local(), Some(x));
+        });
+    }
+}
@@ -0,0 +1,162 @@
+mod common;
+
+mod tests {
+    use crate::common::{check_binary, contains, ranges, values, Precision};
+    use RAP_interval::domain::range::{Range, RangeType};
+
+    type AbstractOp<T> = fn(&Range<T>, &Range<T>) -> Range<T>;
+
+    const I8_BOUNDS: [i8; 9] = [-128, -100, -2, -1, 0, 1, 3, 100, 127];
+    const U8_BOUNDS: [u8; 7] = [0, 1, 2, 100, 128, 254, 255];
+
+    #[test]
+    fn test_add_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::add,
+            |a, b| a.checked_add(b),
+            Precision::ExactWithoutPanics,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::add,
+            |a, b| a.checked_add(b),
+            Precision::ExactWithoutPanics,
+        );
+    }
+
+    #[test]
+    fn test_sub_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::sub,
+            |a, b| a.checked_sub(b),
+            Precision::ExactWithoutPanics,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::sub,
+            |a, b| a.checked_sub(b),
+            Precision::ExactWithoutPanics,
+        );
+    }
+
+    #[test]
+    fn test_mul_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::mul,
+            |a, b| a.checked_mul(b),
+            Precision::ExactWithoutPanics,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::mul,
+            |a, b| a.checked_mul(b),
+            Precision::ExactWithoutPanics,
+        );
+    }
+
+    #[test]
+    fn test_div_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::div,
+            |a, b| a.checked_div(b),
+            Precision::ExactWithoutPanics,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::div,
+            |a, b| a.checked_div(b),
+            Precision::ExactWithoutPanics,
+        );
+    }
+
+    #[test]
+    fn test_rem_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::rem,
+            |a, b| a.checked_rem(b),
+            Precision::Sound,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::rem,
+            |a, b| a.checked_rem(b),
+            Precision::Sound,
+        );
+    }
+
+    #[test]
+    fn test_neg_exhaustive() {
+        for x in ranges(&I8_BOUNDS) {
+            let result = x.neg();
+            for a in values(&x) {
+                if let Some(v) = a.checked_neg() {
+                    assert!(
+                        contains(&result, v),
+                        "{:?} -> {:?} misses {:?}",
+                        x,
+                        result,
+                        v
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow_saturates() {
+        let r = |lb: i8, ub: i8| Range::new(lb, ub, RangeType::Regular);
+        let sum = r(100, 127).add(&r(100, 100));
+        assert_eq!((sum.get_lower(), sum.get_upper()), (127, 127));
+        let product = r(-128, 127).mul(&r(2, 2));
+        assert_eq!((product.get_lower(), product.get_upper()), (-128, 127));
+        let quotient = r(-128, -128).div(&r(-1, -1));
+        assert_eq!((quotient.get_lower(), quotient.get_upper()), (127, 127));
+        let negated = r(-128, 0).neg();
+        assert_eq!((negated.get_lower(), negated.get_upper()), (0, 127));
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let r = |lb: u8, ub: u8| Range::new(lb, ub, RangeType::Regular);
+        assert!(r(1, 10).div(&r(0, 0)).is_empty());
+        assert!(r(1, 10).rem(&r(0, 0)).is_empty());
+        let quotient = r(10, 20).div(&r(0, 5));
+        assert_eq!((quotient.get_lower(), quotient.get_upper()), (2, 20));
+        let remainder = r(10, 20).rem(&r(0, 5));
+        assert_eq!((remainder.get_lower(), remainder.get_upper()), (0, 4));
+    }
+
+    #[test]
+    fn test_empty_and_unknown() {
+        let regular = Range::new(1i8, 2, RangeType::Regular);
+        let empty = Range::new(1i8, 2, RangeType::Empty);
+        let unknown = Range::new(1i8, 2, RangeType::Unknown);
+        let ops: [AbstractOp<i8>; 5] = [Range::add, Range::sub, Range::mul, Range::div, Range::rem];
+        for op in ops {
+            assert!(op(&regular, &empty).is_empty());
+            assert!(op(&empty, &regular).is_empty());
+            // Empty dominates unknown
+            assert!(op(&empty, &unknown).is_empty());
+            assert!(op(&unknown, &empty).is_empty());
+            assert!(op(&regular, &unknown).is_unknown());
+            assert!(op(&unknown, &regular).is_unknown());
+        }
+        assert!(empty.neg().is_empty());
+        assert!(unknown.neg().is_unknown());
+    }
+}
@@ -0,0 +1,207 @@
+mod common;
+
+mod tests {
+    use crate::common::{check_binary, ranges, Precision};
+    use RAP_interval::domain::abstract_domain::AbstractDomain;
+    use RAP_interval::domain::range::{IntType, Range, RangeType};
+    use RAP_interval::ir::ir::BinOp;
+
+    const I8_BOUNDS: [i8; 10] = [-128, -100, -17, -2, -1, 0, 1, 6, 100, 127];
+    const U8_BOUNDS: [u8; 8] = [0, 1, 6, 17, 100, 128, 254, 255];
+    const I8_AMOUNTS: [i8; 7] = [-9, -1, 0, 1, 3, 7, 8];
+    const U8_AMOUNTS: [u8; 7] = [0, 1, 3, 7, 8, 9, 200];
+
+    #[test]
+    fn test_bitand_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::bitand,
+            |a, b| Some(a & b),
+            Precision::Exact,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::bitand,
+            |a, b| Some(a & b),
+            Precision::Exact,
+        );
+    }
+
+    #[test]
+    fn test_bitor_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::bitor,
+            |a, b| Some(a | b),
+            Precision::Exact,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::bitor,
+            |a, b| Some(a | b),
+            Precision::Exact,
+        );
+    }
+
+    #[test]
+    fn test_bitxor_exhaustive() {
+        check_binary(
+            &I8_BOUNDS,
+            &I8_BOUNDS,
+            Range::bitxor,
+            |a, b| Some(a ^ b),
+            Precision::Exact,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_BOUNDS,
+            Range::bitxor,
+            |a, b| Some(a ^ b),
+            Precision::Exact,
+        );
+    }
+
+    #[test]
+    fn test_bitxor_nonzero_lower_bounds() {
+        let r = |lb: u8, ub: u8| Range::new(lb, ub, RangeType::Regular);
+        let xored = r(12, 14).bitxor(&r(5, 9));
+        assert_eq!((xored.get_lower(), xored.get_upper()), (4, 11));
+        let xored = r(100, 200).bitxor(&r(64, 70));
+        assert_eq!((xored.get_lower(), xored.get_upper()), (32, 255));
+        let r = |lb: i8, ub: i8| Range::new(lb, ub, RangeType::Regular);
+        let xored = r(-20, -10).bitxor(&r(-7, -3));
+        assert_eq!((xored.get_lower(), xored.get_upper()), (8, 23));
+        const BOUNDS: [u8; 7] = [3, 5, 12, 14, 64, 100, 200];
+        check_binary(
+            &BOUNDS,
+            &BOUNDS,
+            Range::bitxor,
+            |a, b| Some(a ^ b),
+            Precision::Exact,
+        );
+    }
+
+    #[test]
+    fn test_not_exhaustive() {
+        for x in ranges(&I8_BOUNDS) {
+            let result = x.not();
+            let expected = (!x.get_upper(), !x.get_lower());
+            assert_eq!((result.get_lower(), result.get_upper()), expected);
+        }
+    }
+
+    #[test]
+    fn test_shift_masked_exhaustive() {
+        // Unchecked MIR shifts mask the amount, like `wrapping_shl`
+        check_binary(
+            &I8_BOUNDS,
+            &I8_AMOUNTS,
+            |x, n| x.shl(n, false),
+            |a, b| Some(a.wrapping_shl(b as u32)),
+            Precision::Sound,
+        );
+        check_binary(
+            &I8_BOUNDS,
+            &I8_AMOUNTS,
+            |x, n| x.shr(n, false),
+            |a, b| Some(a.wrapping_shr(b as u32)),
+            Precision::Exact,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_AMOUNTS,
+            |x, n| x.shl(n, false),
+            |a, b| Some(a.wrapping_shl(b as u32)),
+            Precision::Sound,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_AMOUNTS,
+            |x, n| x.shr(n, false),
+            |a, b| Some(a.wrapping_shr(b as u32)),
+            Precision::Exact,
+        );
+    }
+
+    #[test]
+    fn test_shift_checked_exhaustive() {
+        // Checked shifts panic when the amount is out of range
+        let amount = |b: i128| (0..8).contains(&b).then_some(b as u32);
+        check_binary(
+            &I8_BOUNDS,
+            &I8_AMOUNTS,
+            |x, n| x.shl(n, true),
+            |a, b| amount(b as i128).map(|b| a << b),
+            Precision::Sound,
+        );
+        check_binary(
+            &I8_BOUNDS,
+            &I8_AMOUNTS,
+            |x, n| x.shr(n, true),
+            |a, b| amount(b as i128).map(|b| a >> b),
+            Precision::Exact,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_AMOUNTS,
+            |x, n| x.shl(n, true),
+            |a, b| amount(b as i128).map(|b| a << b),
+            Precision::Sound,
+        );
+        check_binary(
+            &U8_BOUNDS,
+            &U8_AMOUNTS,
+            |x, n| x.shr(n, true),
+            |a, b| amount(b as i128).map(|b| a >> b),
+            Precision::Exact,
+        );
+    }
+
+    #[test]
+    fn test_shl_without_lost_bits_is_exact() {
+        let r = |lb: i8, ub: i8| Range::new(lb, ub, RangeType::Regular);
+        let shifted = r(-4, 3).shl(&r(1, 4), true);
+        assert_eq!((shifted.get_lower(), shifted.get_upper()), (-64, 48));
+        // 64 << 1 changes the sign
+        let shifted = r(0, 64).shl(&r(1, 1), true);
+        assert_eq!((shifted.get_lower(), shifted.get_upper()), (-128, 127));
+        // Masked amounts within one period keep their order: 9..=10 is 1..=2
+        let shifted = r(1, 3).shl(&r(9, 10), false);
+        assert_eq!((shifted.get_lower(), shifted.get_upper()), (2, 12));
+        assert!(r(1, 3).shl(&r(8, 10), true).is_empty());
+    }
+
+    #[test]
+    fn test_i128_patterns() {
+        let r = |lb: i128, ub: i128| Range::new(lb, ub, RangeType::Regular);
+        let masked = r(i128::MIN, i128::MAX).bitand(&r(0, 255));
+        assert_eq!((masked.get_lower(), masked.get_upper()), (0, 255));
+        let ored = r(-1, -1).bitor(&r(0, 5));
+        assert_eq!((ored.get_lower(), ored.get_upper()), (-1, -1));
+        let r = |lb: u128, ub: u128| Range::new(lb, ub, RangeType::Regular);
+        let xored = r(u128::MAX - 1, u128::MAX).bitxor(&r(1, 1));
+        assert_eq!(
+            (xored.get_lower(), xored.get_upper()),
+            (u128::MAX - 1, u128::MAX)
+        );
+    }
+
+    #[test]
+    fn test_u128_amounts_above_i128_max() {
+        let r = |lb: u128, ub: u128| Range::new(lb, ub, RangeType::Regular);
+        let u128 = IntType::new(128, false);
+        let huge = r(u128::MAX - 1, u128::MAX);
+        let anded = r(0, u128::MAX).binary(BinOp::BitAnd, &huge, u128, true);
+        assert_eq!((anded.get_lower(), anded.get_upper()), (0, u128::MAX));
+        assert!(r(1, 8).binary(BinOp::Shr, &huge, u128, true).is_empty());
+        assert!(r(1, 8).shr(&huge, true).is_empty());
+        let shifted = r(1, 8).binary(BinOp::Shr, &huge, u128, false);
+        assert_eq!((shifted.get_lower(), shifted.get_upper()), (0, 8));
+        let shifted = r(1, 8).shl(&r(0, u128::MAX), false);
+        assert!(shifted.is_regular());
+    }
+}
@@ -0,0 +1,224 @@
+mod tests {
+    use RAP_interval::domain::range::{IntType, Range, RangeType};
+    use RAP_interval::domain::sign::Sign;
+
+    const BOUNDS: [i8; 8] = [-128, -50, -1, 0, 1, 7, 50, 127];
+    const THRESHOLDS: [i8; 3] = [-10, 0, 100];
+
+    fn regular(lb: i8, ub: i8) -> Range<i8> {
+        Range::new(lb, ub, RangeType::Regular)
+    }
+
+    fn empty() -> Range<i8> {
+        Range::new(0, 0, RangeType::Empty)
+    }
+
+    fn unknown() -> Range<i8> {
+        Range::new(0, 0, RangeType::Unknown)
+    }
+
+    /// All regular ranges over `BOUNDS`, and the empty range.
+    fn ranges() -> Vec<Range<i8>> {
+        let mut ranges = vec![empty()];
+        for lb in BOUNDS {
+            for ub in BOUNDS.into_iter().filter(|&ub| ub >= lb) {
+                ranges.push(regular(lb, ub));
+            }
+        }
+        ranges
+    }
+
+    /// Equality up to the bounds stored in empty and unknown ranges.
+    fn same(a: &Range<i8>, b: &Range<i8>) -> bool {
+        match (a.is_regular(), b.is_regular()) {
+            (true, true) => a == b,
+            _ => a.rtype == b.rtype,
+        }
+    }
+
+    fn leq(a: &Range<i8>, b: &Range<i8>) -> bool {
+        a.is_empty()
+            || (b.is_regular() && b.get_lower() <= a.get_lower() && a.get_upper() <= b.get_upper())
+    }
+
+    fn contains(a: &Range<i8>, v: i8) -> bool {
+        a.is_regular() && a.get_lower() <= v && v <= a.get_upper()
+    }
+
+    #[test]
+    fn test_join_is_least_upper_bound() {
+        for x in ranges() {
+            assert!(same(&x.join(&x), &x), "{:?}", x);
+            for y in ranges() {
+                let j = x.join(&y);
+                assert!(same(&j, &y.join(&x)), "{:?} {:?}", x, y);
+                assert!(leq(&x, &j) && leq(&y, &j), "{:?} {:?}", x, y);
+                // Least: every upper bound contains the join
+                for z in ranges().iter().filter(|z| leq(&x, z) && leq(&y, z)) {
+                    assert!(leq(&j, z), "{:?} {:?} {:?}", x, y, z);
+                }
+                for z in ranges() {
+                    assert!(same(&x.join(&y).join(&z), &x.join(&y.join(&z))));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_meet_is_intersection() {
+        for x in ranges() {
+            assert!(same(&x.meet(&x), &x), "{:?}", x);
+            for y in ranges() {
+                let m = x.meet(&y);
+                assert!(same(&m, &y.meet(&x)), "{:?} {:?}", x, y);
+                for v in i8::MIN..=i8::MAX {
+                    assert_eq!(
+                        contains(&m, v),
+                        contains(&x, v) && contains(&y, v),
+                        "{:?} {:?} {}",
+                        x,
+                        y,
+                        v
+                    );
+                }
+                // Absorption
+                assert!(same(&x.join(&x.meet(&y)), &x), "{:?} {:?}", x, y);
+                assert!(same(&x.meet(&x.join(&y)), &x), "{:?} {:?}", x, y);
+            }
+        }
+        assert!(regular(0, 5).meet(&regular(6, 9)).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_is_ignored_by_join_and_meet() {
+        assert!(empty().join(&unknown()).is_unknown());
+        for x in ranges().into_iter().filter(|x| x.is_regular()) {
+            assert!(same(&x.join(&unknown()), &x));
+            assert!(same(&unknown().join(&x), &x));
+            assert!(same(&x.meet(&unknown()), &x));
+            assert!(same(&unknown().meet(&x), &x));
+        }
+    }
+
+    #[test]
+    fn test_widen_is_upper_bound() {
+        for x in ranges() {
+            for y in ranges() {
+                let w = x.widen(&y, &THRESHOLDS);
+                assert!(leq(&x.join(&y), &w), "{:?} {:?} -> {:?}", x, y, w);
+                // Bounds that grow land on a threshold or on the bound of the type
+                if x.is_regular() && y.is_regular() && y.get_upper() > x.get_upper() {
+                    assert!(THRESHOLDS.contains(&w.get_upper()) || w.get_upper() == i8::MAX);
+                }
+                if x.is_regular() && y.is_regular() && y.get_lower() < x.get_lower() {
+                    assert!(THRESHOLDS.contains(&w.get_lower()) || w.get_lower() == i8::MIN);
+                }
+            }
+        }
+        assert!(same(&unknown().widen(&regular(1, 2), &[]), &regular(1, 2)));
+        assert!(same(
+            &regular(0, 0).widen(&regular(0, 1), &[]),
+            &regular(0, 127)
+        ));
+        assert!(same(
+            &regular(0, 0).widen(&regular(-3, 1), &THRESHOLDS),
+            &regular(-10, 100)
+        ));
+    }
+
+    #[test]
+    fn test_widening_terminates() {
+        // An increasing chain [0, 0], [0, 1], [0, 2], ... stabilizes after one step per
+        // threshold and one to reach the bound of the type.
+        let mut w = regular(0, 0);
+        let mut changes = 0;
+        for ub in 1..=i8::MAX {
+            let next = w.widen(&regular(0, ub), &THRESHOLDS);
+            if !same(&next, &w) {
+                changes += 1;
+            }
+            w = next;
+        }
+        assert!(same(&w, &regular(0, 127)));
+        assert_eq!(changes, 2);
+    }
+
+    #[test]
+    fn test_narrow_refines_between() {
+        for x in ranges() {
+            for y in ranges().iter().filter(|y| leq(y, &x)) {
+                let n = x.narrow(y);
+                assert!(leq(y, &n) && leq(&n, &x), "{:?} {:?} -> {:?}", x, y, n);
+                // Narrowing again with the same iterate changes nothing
+                assert!(same(&n.narrow(y), &n), "{:?} {:?}", x, y);
+            }
+        }
+        assert!(same(
+            &regular(0, 127).narrow(&regular(0, 99)),
+            &regular(0, 99)
+        ));
+        // Finite bounds are kept
+        assert!(same(
+            &regular(0, 50).narrow(&regular(1, 7)),
+            &regular(0, 50)
+        ));
+        assert!(empty().narrow(&regular(1, 7)).is_empty());
+        // The bounds of a narrower type are refined like those of `T`
+        let ty = IntType::new(4, true);
+        assert!(same(
+            &regular(-8, 7).narrow_in(&regular(-2, 5), ty),
+            &regular(-2, 5)
+        ));
+        assert!(same(
+            &regular(0, 7).narrow_in(&regular(1, 3), ty),
+            &regular(0, 3)
+        ));
+    }
+
+    #[test]
+    fn test_growth() {
+        assert_eq!(regular(0, 7).growth(&regular(0, 50)), Sign::Positive);
+        assert_eq!(regular(0, 7).growth(&regular(-1, 7)), Sign::Negative);
+        assert_eq!(regular(0, 7).growth(&regular(-1, 50)), Sign::Unknown);
+        assert_eq!(regular(0, 7).growth(&regular(1, 7)), Sign::Zero);
+        // The first value of a variable is not a growth
+        assert_eq!(unknown().growth(&regular(-128, 127)), Sign::Zero);
+        assert_eq!(empty().growth(&regular(-128, 127)), Sign::Zero);
+        // Widening moves exactly the bounds that grow
+        for x in ranges().into_iter().filter(|x| x.is_regular()) {
+            for y in ranges() {
+                let w = x.widen(&y, &THRESHOLDS);
+                assert_eq!(x.growth(&w), x.growth(&y), "{:?} {:?}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crop_refines_marked_bounds() {
+        let states = [Sign::Zero, Sign::Negative, Sign::Positive, Sign::Unknown];
+        for x in ranges() {
+            for y in ranges().iter().filter(|y| leq(y, &x)) {
+                for state in states {
+                    let c = x.crop(y, state);
+                    assert!(leq(y, &c) && leq(&c, &x), "{:?} {:?} -> {:?}", x, y, c);
+                    // Only the marked bounds moved
+                    assert!(c.growth(&x).leq(state), "{:?} {:?} {}", x, y, state);
+                }
+            }
+        }
+        // Only the marked bound is cropped, and only when it gets tighter
+        assert!(same(
+            &regular(0, 127).crop(&regular(1, 99), Sign::Positive),
+            &regular(0, 99)
+        ));
+        assert!(same(
+            &regular(-128, 50).crop(&regular(-3, 60), Sign::Negative),
+            &regular(-3, 50)
+        ));
+        assert!(same(
+            &regular(-128, 127).crop(&regular(-3, 60), Sign::Zero),
+            &regular(-128, 127)
+        ));
+        assert!(regular(0, 127).crop(&empty(), Sign::Positive).is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+mod tests {
+    use RAP_interval::domain::sign::Sign;
+
+    const SIGNS: [Sign; 4] = [Sign::Zero, Sign::Negative, Sign::Positive, Sign::Unknown];
+
+    #[test]
+    fn test_bounds() {
+        for lower in [false, true] {
+            for upper in [false, true] {
+                let sign = Sign::of_bounds(lower, upper);
+                assert_eq!((sign.lower(), sign.upper()), (lower, upper));
+            }
+        }
+        assert_eq!(Sign::default(), Sign::Zero);
+        let printed: Vec<String> = SIGNS.iter().map(|s| s.to_string()).collect();
+        assert_eq!(printed, ["0", "-", "+", "?"]);
+    }
+
+    #[test]
+    fn test_lattice() {
+        for a in SIGNS {
+            assert_eq!(a.join(a), a);
+            assert_eq!(a.meet(a), a);
+            assert_eq!(a.join(Sign::Zero), a);
+            assert_eq!(a.join(Sign::Unknown), Sign::Unknown);
+            assert_eq!(a.meet(Sign::Unknown), a);
+            assert_eq!(a.meet(Sign::Zero), Sign::Zero);
+            for b in SIGNS {
+                assert_eq!(a.join(b), b.join(a));
+                assert_eq!(a.meet(b), b.meet(a));
+                assert!(a.leq(a.join(b)) && a.meet(b).leq(a));
+                assert_eq!(a.leq(b), a.join(b) == b);
+                for c in SIGNS {
+                    assert_eq!(a.join(b).join(c), a.join(b.join(c)));
+                    assert_eq!(a.meet(b).meet(c), a.meet(b.meet(c)));
+                }
+            }
+        }
+        assert_eq!(Sign::Negative.join(Sign::Positive), Sign::Unknown);
+        assert_eq!(Sign::Negative.meet(Sign::Positive), Sign::Zero);
+        assert!(!Sign::Negative.leq(Sign::Positive));
+    }
+
+    #[test]
+    fn test_without() {
+        for a in SIGNS {
+            for b in SIGNS {
+                let c = a.without(b);
+                assert!(c.leq(a));
+                assert_eq!(c.meet(b), Sign::Zero);
+                assert_eq!(c.join(a.meet(b)), a);
+            }
+        }
+        assert_eq!(Sign::Unknown.without(Sign::Positive), Sign::Negative);
+        assert_eq!(Sign::Positive.without(Sign::Negative), Sign::Positive);
+    }
+}
@@ -0,0 +1,225 @@
+#![feature(box_patterns)]
+#![feature(rustc_private)]
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_index;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_mir_transform;
+
+mod compiler;
+
+mod tests {
+    use crate::compiler::{copy_of, transform, with_function};
+    use rustc_index::IndexVec;
+    use rustc_middle::mir::*;
+    use rustc_middle::ty::TyCtxt;
+    use rustc_mir_transform::validate::Validator;
+    use std::collections::{HashMap, HashSet};
+    use RAP_interval::SSA::SSADestructor::SSADestructor;
+    use RAP_interval::SSA::SSATransformer::{PhiNode, SSAOptions};
+    use RAP_interval::SSA::SSAVerifier::{OutOfSSAVerifier, OutOfSSAViolation};
+
+    /// 用 rustc 自己的 MIR 校验器检查消去 phi 后的 body，不合法时校验器直接报错
+    fn validate<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>) {
+        let mut body = body.clone();
+        let validator = Validator {
+            when: "SSA destruction".to_string(),
+            mir_phase: body.phase,
+        };
+        validator.run_pass(tcx, &mut body);
+    }
+
+    #[test]
+    fn test_destruct_places_phi_copies_on_incoming_edges() {
+        let source = r#"
+            pub fn rotate(n: u32) -> (u32, u32) {
+                let mut p = (0, 1);
+                let mut i = 0;
+                while i < n {
+                    p = (p.1, p.0);
+                    i += 1;
+                }
+                p
+            }
+        "#;
+        with_function(source, "rotate", |tcx, def_id| {
+            let options = SSAOptions {
+                reuse_ssa_locals: true,
+                verify: true,
+                ..SSAOptions::default()
+            };
+            let ssa = transform(tcx, def_id, options);
+            assert!(!ssa.phi_functions.is_empty());
+            // `verify` 打开时 destruct 会用 OutOfSSAVerifier 检查结果
+            let destructed = ssa.destruct();
+            validate(tcx, &destructed);
+            // 每个 phi 操作数都变成了对应前驱（或拆分出的边块）上的一条拷贝
+            let copies: HashSet<(Local, Local)> = destructed
+                .basic_blocks
+                .iter()
+                .flat_map(|data| &data.statements)
+                .filter_map(copy_of)
+                .collect();
+            for phi in ssa.phi_functions.values().flatten() {
+                for (_, operand) in &phi.operands {
+                    let source = operand.place().unwrap().local;
+                    assert!(copies.contains(&(phi.dest, source)), "{:?}", phi);
+                }
+            }
+            // 用户的 `(0, 1)` 和 `(p.1, p.0)` 保留
+            let tuples = destructed
+                .basic_blocks
+                .iter()
+                .flat_map(|data| &data.statements)
+                .filter(|statement| {
+                    matches!(
+                        &statement.kind,
+                        StatementKind::Assign(box (
+                            _,
+                            Rvalue::Aggregate(box AggregateKind::Tuple, _),
+                        ))
+                    )
+                })
+                .count();
+            assert_eq!(tuples, 2);
+            // 保留原名的单赋值临时变量仍带着存储标记，改过名的 p、i 及其版本不再带标记
+            let markers: Vec<Local> = destructed
+                .basic_blocks
+                .iter()
+                .flat_map(|data| &data.statements)
+                .filter_map(|statement| match statement.kind {
+                    StatementKind::StorageLive(local) | StatementKind::StorageDead(local) => {
+                        Some(local)
+                    }
+                    _ => None,
+                })
+                .collect();
+            assert!(!markers.is_empty());
+            for local in markers {
+                let original = ssa.versions[&local].local;
+                assert_eq!(ssa.versions[&local].version, 0);
+                assert!(!ssa
+                    .versions
+                    .values()
+                    .any(|version| version.local == original && version.version > 0));
+            }
+        });
+    }
+
+    #[test]
+    fn test_destruct_splits_critical_edge_and_sequentializes_swap() {
+        let source = r#"
+            pub fn swap(c: u8, a: u32, b: u32) -> u32 {
+                a
+            }
+        "#;
+        with_function(source, "swap", |tcx, def_id| {
+            // bb0: goto bb1
+            // bb1: x = phi(bb0: a, bb1: y); y = phi(bb0: b, bb1: x);
+            //      switchInt(c) -> [0: bb2, otherwise: bb1]
+            // bb2: _0 = x; return
+            // bb1 -> bb1 是关键边，边上的拷贝 x = y, y = x 是一次交换
+            let mut body = tcx.optimized_mir(def_id).clone();
+            let source_info = SourceInfo::outermost(body.span);
+            let (c, a, b) = (Local::from_u32(1), Local::from_u32(2), Local::from_u32(3));
+            let decl = body.local_decls[a].clone();
+            let x = body.local_decls.push(decl.clone());
+            let y = body.local_decls.push(decl);
+            let (bb0, bb1, bb2) = (
+                BasicBlock::from_u32(0),
+                BasicBlock::from_u32(1),
+                BasicBlock::from_u32(2),
+            );
+            let block = |statements, kind| BasicBlockData {
+                statements,
+                terminator: Some(Terminator { source_info, kind }),
+                is_cleanup: false,
+            };
+            let placeholder = Statement {
+                source_info,
+                kind: StatementKind::Nop,
+            };
+            *body.basic_blocks_mut() = IndexVec::from_raw(vec![
+                block(vec![], TerminatorKind::Goto { target: bb1 }),
+                block(
+                    vec![placeholder.clone(), placeholder],
+                    TerminatorKind::SwitchInt {
+                        discr: Operand::Copy(Place::from(c)),
+                        targets: SwitchTargets::static_if(0, bb2, bb1),
+                    },
+                ),
+                block(
+                    vec![Statement {
+                        source_info,
+                        kind: StatementKind::Assign(Box::new((
+                            Place::from(RETURN_PLACE),
+                            Rvalue::Use(Operand::Copy(Place::from(x))),
+                        ))),
+                    }],
+                    TerminatorKind::Return,
+                ),
+            ]);
+            let phi = |dest: Local, init: Local, carried: Local| PhiNode {
+                local: dest,
+                dest,
+                operands: vec![
+                    (bb0, Operand::Copy(Place::from(init))),
+                    (bb1, Operand::Copy(Place::from(carried))),
+                ],
+            };
+            let phi_functions = HashMap::from([(bb1, vec![phi(x, a, y), phi(y, b, x)])]);
+
+            let renamed = HashSet::from([x, y]);
+            let destructed = SSADestructor::new(&body, &phi_functions, renamed).destruct();
+            validate(tcx, &destructed);
+            assert!(OutOfSSAVerifier::new(&destructed, &phi_functions)
+                .verify()
+                .is_empty());
+
+            // 入口边的前驱以 Goto 结束，拷贝直接放在前驱末尾
+            let copies: Vec<_> = destructed.basic_blocks[bb0]
+                .statements
+                .iter()
+                .map(|statement| copy_of(statement).unwrap())
+                .collect();
+            assert_eq!(copies, vec![(x, a), (y, b)]);
+
+            // 关键边被拆分，phi 占位语句被删除
+            assert_eq!(destructed.basic_blocks.len(), 4);
+            let split = BasicBlock::from_u32(3);
+            assert!(destructed.basic_blocks[bb1].statements.is_empty());
+            let TerminatorKind::SwitchInt { targets, .. } =
+                &destructed.basic_blocks[bb1].terminator().kind
+            else {
+                panic!("expected a SwitchInt");
+            };
+            assert_eq!(targets.target_for_value(0), bb2);
+            assert_eq!(targets.otherwise(), split);
+            assert!(matches!(
+                destructed.basic_blocks[split].terminator().kind,
+                TerminatorKind::Goto { target } if target == bb1
+            ));
+
+            // 交换借助临时变量串行化：tmp = x; x = y; y = tmp
+            let tmp = Local::from_u32(6);
+            let copies: Vec<_> = destructed.basic_blocks[split]
+                .statements
+                .iter()
+                .map(|statement| copy_of(statement).unwrap())
+                .collect();
+            assert_eq!(copies, vec![(tmp, x), (x, y), (y, tmp)]);
+
+            // 拷贝留在有两个后继的块里会覆盖另一个后继需要的值
+            let mut unsplit = destructed.clone();
+            let moved = std::mem::take(&mut unsplit.basic_blocks_mut()[split].statements);
+            unsplit.basic_blocks_mut()[bb1].statements.extend(moved);
+            let violations = OutOfSSAVerifier::new(&unsplit, &phi_functions).verify();
+            assert_eq!(violations.len(), 2);
+            assert!(violations.iter().all(|violation| matches!(
+                violation,
+                OutOfSSAViolation::CopyNotOnEdge { phi_block, .. } if *phi_block == bb1
+            )));
+        });
+    }
+}
@@ -0,0 +1,83 @@
+#![feature(box_patterns)]
+#![feature(rustc_private)]
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+
+mod compiler;
+
+mod tests {
+    use crate::compiler::{transform, with_function, COUNT};
+    use rustc_middle::mir::*;
+    use std::collections::{HashMap, HashSet};
+    use RAP_interval::SSA::SSAGraphviz::SSAGraphviz;
+    use RAP_interval::SSA::SSATransformer::SSAOptions;
+
+    #[test]
+    fn test_graphviz_export() {
+        with_function(COUNT, "count", |tcx, def_id| {
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let body = ssa.body.borrow();
+            let graphviz = SSAGraphviz::new(
+                &body,
+                &ssa.phi_functions,
+                &ssa.sigma_functions,
+                "count \"ssa\"".to_string(),
+            );
+            let render = |write: &dyn Fn(&mut Vec<u8>) -> std::io::Result<()>| {
+                let mut output = Vec::new();
+                write(&mut output).unwrap();
+                String::from_utf8(output).unwrap()
+            };
+
+            // 控制流图：每个块一个节点，phi / sigma 列在所在块的标签中，每条边一行
+            let cfg = render(&|w| graphviz.write_cfg(w));
+            assert!(cfg.starts_with("digraph \"cfg count \\\"ssa\\\"\" {\n"));
+            assert!(cfg.ends_with("}\n"));
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                let node = cfg
+                    .lines()
+                    .find(|line| line.starts_with(&format!("    {:?} [label=\"{:?}", bb, bb)))
+                    .unwrap();
+                for phi in ssa.phi_functions.get(&bb).into_iter().flatten() {
+                    assert!(node.contains(&format!("{:?}", phi)), "{}", node);
+                }
+                for sigma in ssa.sigma_functions.get(&bb).into_iter().flatten() {
+                    assert!(node.contains(&format!("{:?}", sigma)), "{}", node);
+                }
+                for succ in data.terminator().successors() {
+                    assert!(cfg.contains(&format!("    {:?} -> {:?} [label=", bb, succ)));
+                }
+            }
+            assert!(cfg.contains("phi("));
+            assert!(cfg.contains("sigma("));
+
+            // 支配树：每个可达的非入口块恰有一条来自直接支配者的边
+            let dominators = body.basic_blocks.dominators();
+            let mut dom_tree: HashMap<BasicBlock, Vec<BasicBlock>> = HashMap::new();
+            for bb in body.basic_blocks.indices() {
+                if let Some(idom) = dominators.immediate_dominator(bb) {
+                    dom_tree.entry(idom).or_default().push(bb);
+                }
+            }
+            let tree = render(&|w| graphviz.write_dominator_tree(w, &dom_tree));
+            let edges = tree.lines().filter(|line| line.contains(" -> ")).count();
+            assert_eq!(edges, dom_tree.values().map(Vec::len).sum::<usize>());
+            for (idom, children) in &dom_tree {
+                for child in children {
+                    assert!(tree.contains(&format!("    {:?} -> {:?};", idom, child)));
+                }
+            }
+
+            // 支配前沿以红色虚线画在控制流边之上
+            let header = BasicBlock::from_u32(1);
+            let df = HashMap::from([(header, HashSet::from([header]))]);
+            let frontier = render(&|w| graphviz.write_dominance_frontier(w, &df));
+            assert!(
+                frontier.contains("    bb1 -> bb1 [style=dashed, color=red, constraint=false];")
+            );
+            assert!(frontier.contains("[color=gray];"));
+        });
+    }
+}
@@ -0,0 +1,672 @@
+#![feature(box_patterns)]
+#![feature(rustc_private)]
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+
+mod compiler;
+
+mod tests {
+    use crate::compiler::{copy_of, local_named, transform, with_function, COUNT};
+    use rustc_middle::mir::*;
+    use std::collections::HashSet;
+    use RAP_interval::SSA::SSATransformer::{
+        PhiNode, PhiPlacement, SSAOptions, SSATransformer, SigmaNode,
+    };
+
+    /// 每个 phi 都以块开头的一条 `dest = copy op_0` 出现在 body 中，两侧类型相同，
+    /// 操作数按 `predecessors()` 的顺序标明来源块
+    fn assert_phis_in_body(ssa: &SSATransformer<'_>) {
+        let body = ssa.body.borrow();
+        for (&bb, phis) in &ssa.phi_functions {
+            let predecessors = body.basic_blocks.predecessors()[bb].to_vec();
+            for (i, phi) in phis.iter().enumerate() {
+                let blocks: Vec<BasicBlock> = phi.operands.iter().map(|(pred, _)| *pred).collect();
+                assert_eq!(blocks, predecessors, "{:?}", phi);
+                let StatementKind::Assign(box (place, Rvalue::Use(operand))) =
+                    &body.basic_blocks[bb].statements[i].kind
+                else {
+                    panic!("{:?}[{}] is not the statement of {:?}", bb, i, phi);
+                };
+                assert_eq!(place.as_local(), Some(phi.dest));
+                assert_eq!(operand, &phi.operands[0].1);
+                let source = operand.place().unwrap().local;
+                assert_eq!(
+                    body.local_decls[source].ty, body.local_decls[phi.dest].ty,
+                    "{:?}",
+                    phi
+                );
+            }
+        }
+    }
+
+    /// 合并原始变量 `local` 的所有 phi
+    fn phis_of<'tcx>(ssa: &SSATransformer<'tcx>, local: Local) -> Vec<PhiNode<'tcx>> {
+        ssa.phi_functions
+            .values()
+            .flatten()
+            .filter(|phi| phi.local == local)
+            .cloned()
+            .collect()
+    }
+
+    /// 经由投影被写入的变量保持原名，没有任何新版本
+    fn assert_partial_writes_in_place(ssa: &SSATransformer<'_>) {
+        let body = ssa.body.borrow();
+        for statement in body.basic_blocks.iter().flat_map(|data| &data.statements) {
+            let StatementKind::Assign(box (place, _)) = &statement.kind else {
+                continue;
+            };
+            if !place.projection.is_empty() {
+                assert!(ssa.is_partially_written(place.local), "{:?}", statement);
+                assert!(!ssa
+                    .versions
+                    .values()
+                    .any(|version| version.local == place.local && version.version > 0));
+            }
+        }
+    }
+
+    /// body 中所有构造元组的赋值语句的位置
+    fn tuple_locations(body: &Body<'_>) -> Vec<Location> {
+        let mut locations = Vec::new();
+        for (block, data) in body.basic_blocks.iter_enumerated() {
+            for (statement_index, statement) in data.statements.iter().enumerate() {
+                if let StatementKind::Assign(box (
+                    _,
+                    Rvalue::Aggregate(box AggregateKind::Tuple, _),
+                )) = &statement.kind
+                {
+                    locations.push(Location {
+                        block,
+                        statement_index,
+                    });
+                }
+            }
+        }
+        locations
+    }
+
+    #[test]
+    fn test_phi_statements_are_materialized() {
+        with_function(COUNT, "count", |tcx, def_id| {
+            let i = local_named(tcx.optimized_mir(def_id), "i");
+            let mut ssa = SSATransformer::new(tcx, def_id, SSAOptions::default());
+            ssa.insert_phi_statment();
+            // 只有循环头需要合并 i，入口边和回边各一个操作数
+            let phis: Vec<&PhiNode<'_>> = ssa
+                .phi_functions
+                .values()
+                .flatten()
+                .filter(|phi| phi.local == i)
+                .collect();
+            assert_eq!(phis.len(), 1);
+            assert_eq!(phis[0].operands.len(), 2);
+            assert_phis_in_body(&ssa);
+        });
+    }
+
+    #[test]
+    fn test_user_tuples_are_not_phis() {
+        let source = r#"
+            pub fn rotate(n: u32) -> (u32, u32) {
+                let mut p = (0, 1);
+                let mut i = 0;
+                while i < n {
+                    p = (p.1, p.0);
+                    i += 1;
+                }
+                p
+            }
+        "#;
+        with_function(source, "rotate", |tcx, def_id| {
+            let p = local_named(tcx.optimized_mir(def_id), "p");
+            let mut ssa = SSATransformer::new(tcx, def_id, SSAOptions::default());
+            ssa.insert_phi_statment();
+            assert!(ssa
+                .phi_functions
+                .values()
+                .flatten()
+                .any(|phi| phi.local == p));
+            assert_phis_in_body(&ssa);
+            // 元组类型的 p 的 phi 占位是一条拷贝，body 中的元组构造都来自用户代码：
+            // `(0, 1)` 和 `(p.1, p.0)`
+            let tuples = tuple_locations(&ssa.body.borrow());
+            assert_eq!(tuples.len(), 2);
+            assert!(tuples
+                .iter()
+                .all(|&location| !ssa.is_phi_statement(location)));
+            let phis: Vec<Location> = ssa
+                .phi_functions
+                .iter()
+                .flat_map(|(&block, phis)| {
+                    (0..phis.len()).map(move |statement_index| Location {
+                        block,
+                        statement_index,
+                    })
+                })
+                .collect();
+            assert!(phis.iter().all(|&location| ssa.is_phi_statement(location)));
+            // 打印 MIR 时 phi 前的注释标明每个操作数的来源块
+            for location in phis {
+                let phi = ssa.phi_at(location).unwrap();
+                let annotation = format!("{:?}", phi);
+                assert!(annotation.starts_with(&format!("{:?} = phi(", phi.dest)));
+                for (pred, _) in &phi.operands {
+                    assert!(
+                        annotation.contains(&format!("{:?}: ", pred)),
+                        "{}",
+                        annotation
+                    );
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_rename_loop_counter() {
+        with_function(COUNT, "count", |tcx, def_id| {
+            let i = local_named(tcx.optimized_mir(def_id), "i");
+            let mut ssa = SSATransformer::new(tcx, def_id, SSAOptions::default());
+            ssa.insert_phi_statment();
+            ssa.rename_variables();
+            // phi 语句随操作数一起更新
+            assert_phis_in_body(&ssa);
+            let phis = phis_of(&ssa, i);
+            assert_eq!(phis.len(), 1);
+            let phi = &phis[0];
+            // 入口边和回边各传入 i 的一个不同的新版本
+            let incoming: Vec<Local> = phi
+                .operands
+                .iter()
+                .map(|(_, op)| op.place().unwrap().local)
+                .collect();
+            assert_ne!(incoming[0], incoming[1]);
+            assert!(!incoming.contains(&i) && phi.dest != i);
+
+            // 每个变量至多被赋值一次
+            let body = ssa.body.borrow();
+            let mut defs = HashSet::new();
+            for statement in body.basic_blocks.iter().flat_map(|data| &data.statements) {
+                if let StatementKind::Assign(box (place, _)) = &statement.kind {
+                    if let Some(local) = place.as_local() {
+                        assert!(defs.insert(local), "{:?} is assigned twice", local);
+                    }
+                }
+            }
+            assert!(incoming.iter().all(|local| defs.contains(local)));
+            // 终结符读到的也是重命名后的版本
+            for data in body.basic_blocks.iter() {
+                if let TerminatorKind::SwitchInt { discr: operand, .. }
+                | TerminatorKind::Assert { cond: operand, .. } = &data.terminator().kind
+                {
+                    assert!(defs.contains(&operand.place().unwrap().local));
+                }
+            }
+            // 循环条件读到的是 phi 定义的版本
+            assert!(body
+                .basic_blocks
+                .iter()
+                .flat_map(|data| &data.statements)
+                .any(|statement| copy_of(statement).is_some_and(|(_, src)| src == phi.dest)));
+        });
+    }
+
+    #[test]
+    fn test_sigma_on_both_branch_targets() {
+        let source = r#"
+            pub fn clamp(x: u32) -> u32 {
+                if x < 10 { x } else { 9 }
+            }
+        "#;
+        with_function(source, "clamp", |tcx, def_id| {
+            let x = local_named(tcx.optimized_mir(def_id), "x");
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let mut sigmas: Vec<(BasicBlock, &SigmaNode)> = ssa
+                .sigma_functions
+                .iter()
+                .flat_map(|(&bb, sigmas)| sigmas.iter().map(move |sigma| (bb, sigma)))
+                .filter(|(_, sigma)| sigma.local == x)
+                .collect();
+            // false 分支对应判别值 0，true 分支是 otherwise
+            sigmas.sort_by_key(|(_, sigma)| sigma.value);
+            let [(true_bb, true_sigma), (false_bb, false_sigma)] = sigmas[..] else {
+                panic!("expected one sigma on each target: {:?}", sigmas);
+            };
+            assert_eq!((true_sigma.value, false_sigma.value), (None, Some(0)));
+            assert_eq!(true_sigma.switch_block, false_sigma.switch_block);
+            assert_ne!(true_sigma.dest, false_sigma.dest);
+
+            let body = ssa.body.borrow();
+            for (bb, sigma) in [(true_bb, true_sigma), (false_bb, false_sigma)] {
+                assert_eq!(
+                    body.basic_blocks.predecessors()[bb].as_slice(),
+                    &[sigma.switch_block]
+                );
+                // body 中对应的语句是 `dest = copy source`
+                let statement_index = (0..body.basic_blocks[bb].statements.len())
+                    .find(|&statement_index| {
+                        ssa.sigma_at(Location {
+                            block: bb,
+                            statement_index,
+                        })
+                        .is_some_and(|s| s.dest == sigma.dest)
+                    })
+                    .unwrap();
+                assert_eq!(
+                    copy_of(&body.basic_blocks[bb].statements[statement_index]),
+                    Some((sigma.dest, x))
+                );
+            }
+            // true 分支返回的 x 读到的是 sigma 定义的版本
+            assert!(body.basic_blocks[true_bb]
+                .statements
+                .iter()
+                .any(|statement| copy_of(statement) == Some((RETURN_PLACE, true_sigma.dest))));
+        });
+    }
+
+    #[test]
+    fn test_sigma_on_join_targets() {
+        let source = r#"
+            pub fn pick(x: u32) -> u32 {
+                match x {
+                    0..=9 | 20..=29 => x,
+                    _ => 0,
+                }
+            }
+        "#;
+        with_function(source, "pick", |tcx, def_id| {
+            let original = tcx.optimized_mir(def_id);
+            let x = local_named(original, "x");
+            // 两个区间测试的成功边汇合到同一个分支
+            let joins = original.basic_blocks.iter().any(|data| {
+                matches!(
+                    &data.terminator().kind,
+                    TerminatorKind::SwitchInt { targets, .. }
+                        if targets.all_targets().iter().any(|&target| {
+                            original.basic_blocks.predecessors()[target].len() > 1
+                        })
+                )
+            });
+            assert!(joins);
+
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            assert!(ssa.verify().is_empty());
+            let body = ssa.body.borrow();
+            let mut sigma_dests = HashSet::new();
+            for (&bb, sigmas) in &ssa.sigma_functions {
+                for sigma in sigmas {
+                    // 关键边被拆开，sigma 所在的块只有判断块这一个前驱
+                    assert_eq!(
+                        body.basic_blocks.predecessors()[bb].as_slice(),
+                        &[sigma.switch_block]
+                    );
+                    // sigma 读取时被比较的变量仍然存活
+                    assert!(
+                        !body.basic_blocks[sigma.switch_block].statements.iter().any(
+                            |statement| matches!(
+                                statement.kind,
+                                StatementKind::StorageDead(local) if local == sigma.source
+                            )
+                        )
+                    );
+                    if sigma.local == x {
+                        sigma_dests.insert(sigma.dest);
+                    }
+                }
+            }
+            // 汇合处的 x 由 phi 合并各条分支边上的 sigma 版本
+            assert!(phis_of(&ssa, x).iter().any(|phi| phi
+                .operands
+                .iter()
+                .all(|(_, operand)| sigma_dests.contains(&operand.place().unwrap().local))));
+        });
+    }
+
+    #[test]
+    fn test_phi_placement_modes() {
+        let source = r#"
+            pub fn placement(c: bool, d: bool, a: u32) -> u32 {
+                let mut y = 0;
+                let mut z = 0;
+                if c {
+                    y = 1;
+                    z = 1;
+                } else {
+                    y = 2;
+                    z = 2;
+                }
+                y = a;
+                if d {
+                    y += 1;
+                }
+                y
+            }
+        "#;
+        with_function(source, "placement", |tcx, def_id| {
+            let body = tcx.optimized_mir(def_id);
+            let (y, z) = (local_named(body, "y"), local_named(body, "z"));
+            // z 从未被读取，半剪枝模式不为它放置 phi；
+            // 第一个汇合点之后 y 立即被覆盖，只有剪枝模式能看出它在那里不活跃，
+            // 而 y 在第二个分支中先使用后定义，半剪枝模式仍然为它放置 phi
+            for (phi_placement, expected_y, expected_z) in [
+                (PhiPlacement::Minimal, 2, 1),
+                (PhiPlacement::SemiPruned, 2, 0),
+                (PhiPlacement::Pruned, 1, 0),
+            ] {
+                let options = SSAOptions {
+                    phi_placement,
+                    ..SSAOptions::default()
+                };
+                let ssa = transform(tcx, def_id, options);
+                assert_eq!(phis_of(&ssa, y).len(), expected_y, "{:?}", phi_placement);
+                assert_eq!(phis_of(&ssa, z).len(), expected_z, "{:?}", phi_placement);
+                assert!(ssa.verify().is_empty(), "{:?}", phi_placement);
+            }
+        });
+    }
+
+    #[test]
+    fn test_reuse_ssa_locals() {
+        let source = r#"
+            pub fn reuse(a: u32, n: u32) -> u32 {
+                let b = a;
+                let k = b & 7;
+                let mut i = 0;
+                while i < n {
+                    i += k;
+                }
+                i
+            }
+        "#;
+        with_function(source, "reuse", |tcx, def_id| {
+            let body = tcx.optimized_mir(def_id);
+            let (a, b, k, i) = (
+                local_named(body, "a"),
+                local_named(body, "b"),
+                local_named(body, "k"),
+                local_named(body, "i"),
+            );
+            // 以 `local` 本身为左值的赋值语句数
+            let assignments = |ssa: &SSATransformer<'_>, local: Local| {
+                ssa.body
+                    .borrow()
+                    .basic_blocks
+                    .iter()
+                    .flat_map(|data| &data.statements)
+                    .filter(|statement| {
+                        matches!(&statement.kind, StatementKind::Assign(box (place, _))
+                            if place.as_local() == Some(local))
+                    })
+                    .count()
+            };
+            // `b & 7` 读取的变量
+            let masked = |ssa: &SSATransformer<'_>| {
+                ssa.body
+                    .borrow()
+                    .basic_blocks
+                    .iter()
+                    .flat_map(|data| &data.statements)
+                    .find_map(|statement| match &statement.kind {
+                        StatementKind::Assign(box (
+                            _,
+                            Rvalue::BinaryOp(BinOp::BitAnd, box (operand, _)),
+                        )) => Some(operand.place().unwrap().local),
+                        _ => None,
+                    })
+                    .unwrap()
+            };
+
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            assert_eq!(assignments(&ssa, k), 0);
+            assert_ne!(masked(&ssa), a);
+
+            let options = SSAOptions {
+                reuse_ssa_locals: true,
+                ..SSAOptions::default()
+            };
+            let ssa = transform(tcx, def_id, options);
+            assert!(ssa.verify().is_empty());
+            // k 已经是单赋值的，保留原名
+            assert_eq!(assignments(&ssa, k), 1);
+            // b 和读取它的临时变量与 a 属于同一个拷贝等价类，都合并到 a
+            assert_eq!(assignments(&ssa, b), 0);
+            assert_eq!(masked(&ssa), a);
+            // 循环变量照常放置 phi 并重命名
+            assert_eq!(phis_of(&ssa, i).len(), 1);
+            assert_eq!(assignments(&ssa, i), 0);
+        });
+    }
+
+    #[test]
+    fn test_dominance_queries_match_rustc() {
+        let source = r#"
+            pub fn diamonds(a: u32, c: bool, d: bool) -> u32 {
+                let mut x = a;
+                if c {
+                    x = 1;
+                } else if d {
+                    x = 2;
+                } else {
+                    x = 3;
+                }
+                while x < 10 {
+                    if d {
+                        x += 2;
+                    } else {
+                        x += 1;
+                    }
+                }
+                x
+            }
+        "#;
+        with_function(source, "diamonds", |tcx, def_id| {
+            let ssa = SSATransformer::new(tcx, def_id, SSAOptions::default());
+            let body = tcx.optimized_mir(def_id);
+            let dominators = body.basic_blocks.dominators();
+            let reachable: Vec<BasicBlock> = body
+                .basic_blocks
+                .indices()
+                .filter(|&bb| dominators.is_reachable(bb))
+                .collect();
+            assert!(reachable.len() > 8);
+            for &a in &reachable {
+                for &b in &reachable {
+                    assert_eq!(
+                        ssa.dominates_(&a, &b),
+                        dominators.dominates(a, b),
+                        "{:?} {:?}",
+                        a,
+                        b
+                    );
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_unwind_edges_and_argument_definitions() {
+        let source = r#"
+            fn helper(v: &Vec<u32>) -> u32 {
+                v.len() as u32
+            }
+
+            pub fn unwinding(v: Vec<u32>, mut n: u32) -> u32 {
+                let mut total = 0;
+                while n > 0 {
+                    total = helper(&v);
+                    n -= 1;
+                }
+                total
+            }
+        "#;
+        with_function(source, "unwinding", |tcx, def_id| {
+            let original = tcx.optimized_mir(def_id);
+            let n = local_named(original, "n");
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            assert!(ssa.verify().is_empty());
+
+            // 参数在入口处隐式定义，循环头的 phi 从入口边接收参数本身
+            let phis = phis_of(&ssa, n);
+            assert_eq!(phis.len(), 1);
+            assert!(phis[0]
+                .operands
+                .iter()
+                .any(|(_, op)| op.place().unwrap().local == n));
+
+            // 可能展开到清理块的调用，其返回值同样得到新版本
+            let body = ssa.body.borrow();
+            let mut unwinding_calls = 0;
+            for data in body.basic_blocks.iter() {
+                if let TerminatorKind::Call {
+                    destination,
+                    unwind: UnwindAction::Cleanup(cleanup),
+                    ..
+                } = &data.terminator().kind
+                {
+                    assert!(body.basic_blocks[*cleanup].is_cleanup);
+                    assert!(destination.local.as_usize() >= original.local_decls.len());
+                    unwinding_calls += 1;
+                }
+            }
+            assert!(unwinding_calls > 0);
+        });
+    }
+
+    #[test]
+    fn test_call_into_shared_return_block() {
+        let source = r#"
+            fn helper(k: u32) -> usize {
+                k as usize
+            }
+
+            pub fn pick(v: Vec<u32>, c: bool) -> usize {
+                let x = if c { helper(1) } else { helper(2) };
+                x + v.len()
+            }
+        "#;
+        with_function(source, "pick", |tcx, def_id| {
+            let original = tcx.optimized_mir(def_id);
+            let x = local_named(original, "x");
+            // 两个调用都直接返回到 if 之后的汇合块
+            let shared = original.basic_blocks.iter().any(|data| {
+                matches!(
+                    data.terminator().kind,
+                    TerminatorKind::Call { target: Some(target), .. }
+                        if original.basic_blocks.predecessors()[target].len() > 1
+                )
+            });
+            assert!(shared);
+
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            assert!(ssa.verify().is_empty());
+            let body = ssa.body.borrow();
+            let predecessors = body.basic_blocks.predecessors();
+            // 返回边被拆开，每个调用的返回块只有调用这一个前驱
+            let mut returns = Vec::new();
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                if let TerminatorKind::Call {
+                    destination,
+                    target: Some(target),
+                    ..
+                } = &data.terminator().kind
+                {
+                    assert_eq!(predecessors[*target].as_slice(), &[bb]);
+                    returns.push((*target, destination.local));
+                }
+            }
+            // 汇合块的 phi 从两个拆出的返回块分别接收对应调用的返回值
+            let phis = phis_of(&ssa, x);
+            assert_eq!(phis.len(), 1);
+            assert_eq!(phis[0].operands.len(), 2);
+            for (pred, operand) in &phis[0].operands {
+                let local = operand.place().unwrap().local;
+                assert!(returns.contains(&(*pred, local)), "{:?}", phis[0]);
+            }
+        });
+    }
+
+    #[test]
+    fn test_version_names_and_positions() {
+        with_function(COUNT, "count", |tcx, def_id| {
+            let body = tcx.optimized_mir(def_id);
+            let (n, i) = (local_named(body, "n"), local_named(body, "i"));
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            assert_eq!(ssa.version_name(n), "n#0");
+            assert_eq!(ssa.version_name(i), "i#0");
+
+            // i 的版本从 1 开始连续编号，名字取自源码
+            let body = ssa.body.borrow();
+            let mut numbers = Vec::new();
+            for (&local, version) in &ssa.versions {
+                assert!(version.position.starts_with("ssa_test_"), "{}", version);
+                if version.name.is_none() {
+                    assert!(version.short_name().starts_with('_'), "{}", version);
+                }
+                if version.local != i || version.version == 0 {
+                    continue;
+                }
+                numbers.push(version.version);
+                assert_eq!(version.name.as_deref(), Some("i"));
+                assert_eq!(ssa.version_name(local), format!("i#{}", version.version));
+                // 初值和自增两处定义对应各自的源码行
+                let location = version.def.unwrap();
+                if let StatementKind::Assign(box (_, Rvalue::Use(operand))) =
+                    &body.basic_blocks[location.block].statements[location.statement_index].kind
+                {
+                    match operand {
+                        Operand::Constant(_) => assert!(version.position.ends_with(":3")),
+                        Operand::Move(place) if !place.projection.is_empty() => {
+                            assert!(version.position.ends_with(":5"))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            numbers.sort();
+            assert_eq!(numbers, (1..=numbers.len()).collect::<Vec<_>>());
+        });
+    }
+
+    #[test]
+    fn test_field_store_keeps_local_out_of_ssa() {
+        let source = r#"
+            pub fn field_store(a: u32, b: u32, c: bool) -> u32 {
+                let mut t = (a, b);
+                if c {
+                    t = (b, a);
+                }
+                t.0 = 7;
+                t.0 + t.1
+            }
+        "#;
+        with_function(source, "field_store", |tcx, def_id| {
+            let t = local_named(tcx.optimized_mir(def_id), "t");
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            // t 在两个分支中被整体赋值，但 `t.0 = 7` 只改写了一部分，不能为它放置 phi
+            assert!(phis_of(&ssa, t).is_empty());
+            assert_partial_writes_in_place(&ssa);
+            assert!(ssa.verify().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_deref_store_keeps_pointer_out_of_ssa() {
+        let source = r#"
+            pub fn deref_store(mut x: u32, c: bool) -> u32 {
+                let r = &mut x;
+                if c {
+                    *r = 5;
+                }
+                x
+            }
+        "#;
+        with_function(source, "deref_store", |tcx, def_id| {
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            assert_partial_writes_in_place(&ssa);
+            assert!(ssa.verify().is_empty());
+        });
+    }
+}
@@ -0,0 +1,97 @@
+#![feature(box_patterns)]
+#![feature(rustc_private)]
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+
+mod compiler;
+
+mod tests {
+    use crate::compiler::{local_named, transform, with_function, COUNT};
+    use rustc_middle::mir::*;
+    use std::collections::{HashMap, HashSet};
+    use RAP_interval::SSA::SSATransformer::SSAOptions;
+    use RAP_interval::SSA::SSAVerifier::{DefSite, SSAVerifier, SSAViolation};
+
+    #[test]
+    fn test_verifier_verdict_on_renamed_loop() {
+        with_function(COUNT, "count", |tcx, def_id| {
+            let i = local_named(tcx.optimized_mir(def_id), "i");
+            let ssa = transform(tcx, def_id, SSAOptions::default());
+            let violations = ssa.verify();
+            assert!(violations.is_empty(), "{:?}", violations);
+
+            let body = ssa.body.borrow();
+            let (header, phi) = ssa
+                .phi_functions
+                .iter()
+                .find_map(|(&bb, phis)| {
+                    phis.iter()
+                        .find(|phi| phi.local == i)
+                        .map(|phi| (bb, phi.clone()))
+                })
+                .unwrap();
+            let latch = phi
+                .operands
+                .iter()
+                .find(|(pred, _)| ssa.dominates_(&header, pred))
+                .map(|(_, op)| op.place().unwrap().local)
+                .unwrap();
+
+            // 不认识 phi 时，循环头中的占位语句读到了回边上才定义的版本
+            let no_phis = HashMap::new();
+            let violations = SSAVerifier::new(&body, &no_phis, ssa.unrenamed_locals()).verify();
+            assert!(violations.iter().any(|violation| matches!(
+                violation,
+                SSAViolation::UseNotDominated { local, location, .. }
+                    if *local == latch && location.block == header
+            )));
+
+            // phi 缺少一个前驱的操作数
+            let mut phi_functions = ssa.phi_functions.clone();
+            for phis in phi_functions.values_mut() {
+                for candidate in phis.iter_mut().filter(|candidate| candidate.local == i) {
+                    candidate.operands.pop();
+                }
+            }
+            let violations =
+                SSAVerifier::new(&body, &phi_functions, ssa.unrenamed_locals()).verify();
+            assert!(violations.iter().any(|violation| matches!(
+                violation,
+                SSAViolation::PhiArityMismatch { dest, location, .. }
+                    if *dest == phi.dest && location.block == header
+            )));
+        });
+    }
+
+    #[test]
+    fn test_verifier_counts_projected_writes() {
+        let source = r#"
+            pub fn projected_write(mut t: (u32, u32)) -> u32 {
+                t.0 = 1;
+                t.0
+            }
+        "#;
+        with_function(source, "projected_write", |tcx, def_id| {
+            let body = tcx.optimized_mir(def_id);
+            let t = local_named(body, "t");
+            let phi_functions = HashMap::new();
+            let violations = SSAVerifier::new(body, &phi_functions, HashSet::new()).verify();
+            assert_eq!(violations.len(), 1);
+            assert!(matches!(
+                violations[0],
+                SSAViolation::MultipleAssignment {
+                    local,
+                    first: DefSite::Argument,
+                    ..
+                } if local == t
+            ));
+            // 没有重命名的变量不做检查
+            let unrenamed = HashSet::from([t]);
+            assert!(SSAVerifier::new(body, &phi_functions, unrenamed)
+                .verify()
+                .is_empty());
+        });
+    }
+}
@@ -0,0 +1,141 @@
+mod common;
+
+mod tests {
+    use crate::common::Universe;
+    use RAP_interval::domain::range::{IntType, Range, RangeType};
+    use RAP_interval::domain::wrapped::WrappedRange;
+
+    const BITS: u16 = 3;
+    const MASK: u128 = 7;
+
+    /// Every wrapped interval of width 3.
+    fn arcs() -> Universe<WrappedRange, u128> {
+        let mut arcs = vec![WrappedRange::bottom(BITS), WrappedRange::top(BITS)];
+        for start in 0..=MASK {
+            for end in 0..=MASK {
+                arcs.push(WrappedRange::new(BITS, start, end));
+            }
+        }
+        Universe::new(
+            arcs,
+            |arc| (0..=MASK).filter(|&x| arc.contains(x)).collect(),
+            // Concrete results wrap around to the width of the result
+            |arc, x| arc.contains(x & ((1 << arc.bits) - 1)),
+        )
+    }
+
+    fn signed(x: u128) -> i128 {
+        ((x << (128 - BITS)) as i128) >> (128 - BITS)
+    }
+
+    #[test]
+    fn test_lattice() {
+        let arcs = arcs();
+        for (s, es) in arcs.samples() {
+            for (t, et) in arcs.samples() {
+                let subset = es.iter().all(|x| et.contains(x));
+                assert_eq!(s.leq(t), subset, "{:?} {:?}", s, t);
+                let join = s.join(t);
+                assert!(s.leq(&join) && t.leq(&join), "{:?} {:?}", s, t);
+                assert_eq!(join, t.join(s), "{:?} {:?}", s, t);
+                let meet = s.meet(t);
+                for x in es.iter().filter(|x| et.contains(x)) {
+                    assert!(meet.contains(*x), "{:?} {:?} -> {:?}", s, t, meet);
+                }
+                if subset {
+                    assert_eq!(meet, *s);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_is_sound() {
+        let arcs = arcs();
+        arcs.check_binary("add", WrappedRange::add, |x, y| Some(x + y));
+        arcs.check_binary("sub", WrappedRange::sub, |x, y| Some(x.wrapping_sub(y)));
+        arcs.check_binary("mul", WrappedRange::mul, |x, y| Some(x * y));
+        arcs.check_binary("udiv", WrappedRange::udiv, |x, y| x.checked_div(y));
+        arcs.check_binary("sdiv", WrappedRange::sdiv, |x, y| {
+            signed(x).checked_div(signed(y)).map(|z| z as u128)
+        });
+        arcs.check_binary("urem", WrappedRange::urem, |x, y| x.checked_rem(y));
+        arcs.check_binary("srem", WrappedRange::srem, |x, y| {
+            signed(x).checked_rem(signed(y)).map(|z| z as u128)
+        });
+        arcs.check_unary("neg", WrappedRange::neg, |x| Some(x.wrapping_neg()));
+        arcs.check_unary("not", WrappedRange::not, |x| Some(!x));
+    }
+
+    #[test]
+    fn test_bitwise_is_sound() {
+        let arcs = arcs();
+        arcs.check_binary("and", WrappedRange::bitand, |x, y| Some(x & y));
+        arcs.check_binary("or", WrappedRange::bitor, |x, y| Some(x | y));
+        arcs.check_binary("xor", WrappedRange::bitxor, |x, y| Some(x ^ y));
+        for checked in [true, false] {
+            let amount = |y: u128| match checked {
+                true => (y < BITS as u128).then_some(y),
+                false => Some(y % BITS as u128),
+            };
+            arcs.check_binary(
+                "shl",
+                |s, t| s.shl(t, checked),
+                |x, y| amount(y).map(|y| x << y),
+            );
+            arcs.check_binary(
+                "lshr",
+                |s, t| s.lshr(t, checked),
+                |x, y| amount(y).map(|y| x >> y),
+            );
+            arcs.check_binary(
+                "ashr",
+                |s, t| s.ashr(t, checked),
+                |x, y| amount(y).map(|y| (signed(x) >> y) as u128),
+            );
+        }
+    }
+
+    #[test]
+    fn test_cast_is_sound() {
+        let arcs = arcs();
+        arcs.check_unary("trunc", |s| s.cast(2, false), Some);
+        arcs.check_unary("zext", |s| s.cast(8, false), Some);
+        arcs.check_unary("sext", |s| s.cast(8, true), |x| Some(signed(x) as u128));
+    }
+
+    #[test]
+    fn test_wrapping_keeps_precision() {
+        let u8_arc = |start, end| WrappedRange::new(8, start, end);
+        // 250..=255 plus 10 wraps around to 4..=9
+        assert_eq!(
+            u8_arc(250, 255).add(&WrappedRange::constant(8, 10)),
+            u8_arc(4, 9)
+        );
+        assert_eq!(u8_arc(250, 255).join(&u8_arc(0, 3)), u8_arc(250, 3));
+        // The same patterns are an interval as `i8` but not as `u8`
+        let range: Range<i32> = u8_arc(250, 3).to_range(IntType::new(8, true));
+        assert_eq!((range.get_lower(), range.get_upper()), (-6, 3));
+        let range: Range<i32> = u8_arc(250, 3).to_range(IntType::new(8, false));
+        assert_eq!((range.get_lower(), range.get_upper()), (0, 255));
+        // -1 * -1 is 1 as a signed product, even though 255 * 255 overflows as unsigned
+        assert_eq!(
+            u8_arc(255, 255).mul(&u8_arc(255, 255)),
+            WrappedRange::constant(8, 1)
+        );
+    }
+
+    #[test]
+    fn test_range_conversion() {
+        let ty = IntType::new(8, true);
+        for (lb, ub) in [(-128, 127), (-6, 3), (0, 0), (5, 100)] {
+            let range: Range<i32> = Range::new(lb, ub, RangeType::Regular);
+            let back: Range<i32> = WrappedRange::from_range(&range, 8).to_range(ty);
+            assert_eq!((back.get_lower(), back.get_upper()), (lb, ub));
+        }
+        let empty: Range<i32> = Range::new(0, 0, RangeType::Empty);
+        assert!(WrappedRange::from_range(&empty, 8).is_bottom());
+        let wide: Range<i32> = Range::new(-1000, 1000, RangeType::Regular);
+        assert!(WrappedRange::from_range(&wide, 8).is_top());
+    }
+}